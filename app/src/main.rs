@@ -259,7 +259,7 @@ fn handle_repl_line<F: flash_core::Flash>(ee: &mut eeprom_emul::Eeprom<F>, line:
             hexdump(off as usize, &buf);
         }
         "boot" => {
-            let inc = parts.iter().any(|s| *s == "--inc");
+            let inc = parts.contains(&"--inc");
             let mut b = [0u8; 4]; ee.read(BOOT_COUNTER_ADDR, &mut b)?; let mut cnt = u32::from_le_bytes(b);
             println!("boot_count={}", cnt);
             if inc { cnt = cnt.wrapping_add(1); ee.write(BOOT_COUNTER_ADDR, &cnt.to_le_bytes())?; println!("incremented -> {}", cnt); }
@@ -299,8 +299,8 @@ fn parse_u32(s: &str) -> Result<u32> {
 fn decode_hex(s: &str) -> Result<Vec<u8>> {
     let s = s.trim();
     let s = s.strip_prefix("0x").unwrap_or(s);
-    let s = s.replace(' ', "").replace('_', "");
-    if s.len() % 2 != 0 { bail!("hex must have even length"); }
+    let s = s.replace([' ', '_'], "");
+    if !s.len().is_multiple_of(2) { bail!("hex must have even length"); }
     hex::decode(s).map_err(|e| anyhow!("{}", e))
 }
 
@@ -4,6 +4,7 @@ use ieee.numeric_std.all;
 
 -- Behavioral SPI NOR flash model (mode 0):
 -- - Commands: WREN(0x06), RDSR(0x05), READ(0x03), PP(0x02), SE(0x20)
+-- - 4-byte addressing: EN4B(0xB7)/EX4B(0xE9) mode plus READ4(0x13), PP4(0x12), SE4(0x21)
 -- - WEL and WIP status bits
 -- - Program: only 1->0; stops at page boundary
 -- - Erase: sets sector to 0xFF
@@ -37,6 +38,7 @@ architecture behav of spi_flash is
   -- Status bits
   signal wel      : std_logic := '0';
   signal wip      : std_logic := '0';
+  signal addr4    : std_logic := '0'; -- 4-byte address mode (EN4B/EX4B)
   signal busy_cnt : integer := 0;
   signal start_prog  : std_logic := '0';
   signal start_erase : std_logic := '0';
@@ -46,15 +48,15 @@ architecture behav of spi_flash is
   signal st : state_t := IDLE;
 
   signal cmd_reg   : byte_t := (others => '0');
-  signal addr_reg  : std_logic_vector(23 downto 0) := (others => '0');
-  signal addr_tmp  : unsigned(23 downto 0) := (others => '0');
+  signal addr_reg  : std_logic_vector(31 downto 0) := (others => '0');
+  signal addr_tmp  : unsigned(31 downto 0) := (others => '0');
   signal rx_shift  : byte_t := (others => '0');
   signal tx_shift  : byte_t := (others => '0');
   signal rx_bitcnt : integer range 0 to 8 := 0;
   signal tx_bitidx : integer range 0 to 7 := 7; -- bit index for tx_shift
-  signal addr_bytes_needed : integer range 0 to 3 := 0;
+  signal addr_bytes_needed : integer range 0 to 4 := 0;
 
-  signal page_limit_addr : unsigned(23 downto 0) := (others => '0');
+  signal page_limit_addr : unsigned(31 downto 0) := (others => '0');
   signal bytes_programmed_in_txn : integer := 0;
 
   -- Edge detect handled with process-local variables for robust zero-time CS toggles
@@ -94,13 +96,14 @@ begin
 
   -- Main SPI logic
   spi_proc: process(sclk, cs_n, reset_n, wip)
-    variable addr_acc   : std_logic_vector(23 downto 0);
+    variable addr_acc   : std_logic_vector(31 downto 0);
     variable sector_base: integer;
     variable rx_byte    : std_logic_vector(7 downto 0);
     variable addr_int   : integer;
     variable status     : std_logic_vector(7 downto 0);
     variable cur        : byte_t;
-    variable addr_bytes_left : integer range 0 to 3 := 0;
+    variable addr_bytes_left : integer range 0 to 4 := 0;
+    variable addr_len   : integer range 3 to 4;
     variable sclk_prev_v : std_logic := '0';
     variable cs_prev_v   : std_logic := '1';
   begin
@@ -114,6 +117,7 @@ begin
       tx_bitidx <= 7;
       addr_bytes_needed <= 0;
       wel <= '0';
+      addr4 <= '0';
       cs_prev_v := '1';
       sclk_prev_v := '0';
       bytes_programmed_in_txn <= 0;
@@ -128,16 +132,16 @@ begin
       if cs_prev_v = '0' and cs_n = '1' then
         -- rising edge: end of transaction
         report "CS rising, end of txn. cmd_reg=" & integer'image(to_integer(unsigned(cmd_reg))) & " st=" & integer'image(state_t'pos(st)) severity note;
-        if cmd_reg = x"02" then -- PP
+        if cmd_reg = x"02" or cmd_reg = x"12" then -- PP / PP4
           if wel = '1' and bytes_programmed_in_txn > 0 then
             -- Enter busy and clear WEL
             wel <= '0';
             start_prog <= '1';
           end if;
-        elsif cmd_reg = x"20" then -- SE
+        elsif cmd_reg = x"20" or cmd_reg = x"21" then -- SE / SE4
           if wel = '1' then
             -- perform erase now and then become busy
-            sector_base := (to_integer(unsigned(addr_reg)) / SECTOR_SIZE) * SECTOR_SIZE;
+            sector_base := (to_integer(unsigned(addr_reg(30 downto 0))) / SECTOR_SIZE) * SECTOR_SIZE;
             for i in sector_base to sector_base + SECTOR_SIZE - 1 loop
               if i >= 0 and i < MEM_BYTES then
                 mem(i) <= (others => '1');
@@ -228,29 +232,32 @@ begin
                   report "CMD RDSR" severity note;
                   st <= RDSR_STREAM;
                   tx_bitidx <= 7;
-                elsif rx_byte = x"03" then -- READ
-                  report "CMD READ" severity note;
-                  st <= ADDR;
-                  addr_reg <= (others => '0');
-                  addr_bytes_needed <= 3;
-                  addr_bytes_left := 3;
-                elsif rx_byte = x"02" then -- PP
-                  report "CMD PP" severity note;
-                  st <= ADDR;
-                  addr_reg <= (others => '0');
-                  addr_bytes_needed <= 3;
-                  addr_bytes_left := 3;
-                elsif rx_byte = x"20" then -- SE
-                  report "CMD SE" severity note;
+                elsif rx_byte = x"B7" then -- EN4B
+                  report "CMD EN4B" severity note;
+                  addr4 <= '1';
+                  st <= IGNORE;
+                elsif rx_byte = x"E9" then -- EX4B
+                  report "CMD EX4B" severity note;
+                  addr4 <= '0';
+                  st <= IGNORE;
+                elsif rx_byte = x"03" or rx_byte = x"02" or rx_byte = x"20" or
+                      rx_byte = x"13" or rx_byte = x"12" or rx_byte = x"21" then -- READ/PP/SE (+4-byte)
+                  report "CMD with address" severity note;
+                  -- dedicated 4-byte opcodes always take 4 address bytes; legacy ones follow EN4B mode
+                  if rx_byte = x"13" or rx_byte = x"12" or rx_byte = x"21" or addr4 = '1' then
+                    addr_len := 4;
+                  else
+                    addr_len := 3;
+                  end if;
                   st <= ADDR;
                   addr_reg <= (others => '0');
-                  addr_bytes_needed <= 3;
-                  addr_bytes_left := 3;
+                  addr_bytes_needed <= addr_len;
+                  addr_bytes_left := addr_len;
                 else
                   st <= IGNORE; -- unsupported command
                 end if;
               when ADDR =>
-                addr_reg(23 downto 8) <= addr_reg(15 downto 0);
+                addr_reg(31 downto 8) <= addr_reg(23 downto 0);
                 addr_reg(7 downto 0)  <= rx_byte;
                 report "ADDR byte rx=" & integer'image(to_integer(unsigned(rx_byte))) & " left(before dec)=" & integer'image(addr_bytes_left) severity note;
                 if addr_bytes_left > 0 then
@@ -259,18 +266,19 @@ begin
                 report "ADDR left(after dec)=" & integer'image(addr_bytes_left) severity note;
                 if addr_bytes_left = 0 then
                   -- full address captured
-                  addr_int := (to_integer(unsigned(addr_reg(15 downto 0))) * 256) + to_integer(unsigned(rx_byte));
-                  addr_tmp <= to_unsigned(addr_int, 24);
-                  if cmd_reg = x"03" then
+                  -- addr_reg was cleared at command start, so bits above the address width are zero
+                  addr_int := (to_integer(unsigned(addr_reg(22 downto 0))) * 256) + to_integer(unsigned(rx_byte));
+                  addr_tmp <= to_unsigned(addr_int, 32);
+                  if cmd_reg = x"03" or cmd_reg = x"13" then
                     st <= READ_DATA;
                     tx_shift <= mem(addr_int);
                     tx_bitidx <= 7;
-                  elsif cmd_reg = x"02" then
+                  elsif cmd_reg = x"02" or cmd_reg = x"12" then
                     st <= PP_DATA;
                     -- compute address and next page boundary
-                    addr_tmp <= to_unsigned(addr_int, 24);
-                    page_limit_addr <= to_unsigned(((addr_int / PAGE_SIZE) * PAGE_SIZE) + PAGE_SIZE, 24);
-                  elsif cmd_reg = x"20" then
+                    addr_tmp <= to_unsigned(addr_int, 32);
+                    page_limit_addr <= to_unsigned(((addr_int / PAGE_SIZE) * PAGE_SIZE) + PAGE_SIZE, 32);
+                  elsif cmd_reg = x"20" or cmd_reg = x"21" then
                     st <= IGNORE; -- payload none; actual erase on CS rising
                   end if;
                 end if;
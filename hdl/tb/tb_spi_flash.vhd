@@ -79,6 +79,10 @@ architecture tb of tb_spi_flash is
   constant CMD_READ : std_logic_vector(7 downto 0) := x"03";
   constant CMD_PP   : std_logic_vector(7 downto 0) := x"02";
   constant CMD_SE   : std_logic_vector(7 downto 0) := x"20";
+  constant CMD_EN4B : std_logic_vector(7 downto 0) := x"B7";
+  constant CMD_EX4B : std_logic_vector(7 downto 0) := x"E9";
+  constant CMD_READ4: std_logic_vector(7 downto 0) := x"13";
+  constant CMD_PP4  : std_logic_vector(7 downto 0) := x"12";
 
   -- convenience: issue READ command for a single byte at 24-bit address
   procedure spi_read1(
@@ -301,6 +305,35 @@ begin
       wait for CLK_PERIOD;
     end loop;
 
+    -- 4-byte addressing: PP4 at 0x00000040 (4 address bytes without EN4B), READ4 back
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_WREN); cs_n <= '1'; wait for 2*CLK_PERIOD;
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_PP4);
+    spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"40");
+    spi_send_byte(sclk, mosi, x"C3");
+    cs_n <= '1';
+    wait for 2*CLK_PERIOD;
+    for k in 0 to 60 loop
+      cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_RDSR); spi_recv_byte(sclk, mosi, miso, status); cs_n <= '1';
+      exit when status(0) = '0';
+      wait for CLK_PERIOD;
+    end loop;
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_READ4);
+    spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"40");
+    spi_prime(sclk, mosi); spi_recv_byte(sclk, mosi, miso, byte);
+    cs_n <= '1'; wait for CLK_PERIOD;
+    assert byte = x"C3" report "READ4 after PP4 mismatch" severity failure;
+
+    -- EN4B: legacy READ now takes 4 address bytes; EX4B returns to 3
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_EN4B); cs_n <= '1'; wait for 2*CLK_PERIOD;
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_READ);
+    spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"00"); spi_send_byte(sclk, mosi, x"40");
+    spi_prime(sclk, mosi); spi_recv_byte(sclk, mosi, miso, byte);
+    cs_n <= '1'; wait for CLK_PERIOD;
+    assert byte = x"C3" report "READ in 4-byte mode mismatch" severity failure;
+    cs_n <= '0'; spi_send_byte(sclk, mosi, CMD_EX4B); cs_n <= '1'; wait for 2*CLK_PERIOD;
+    spi_read1(sclk, cs_n, mosi, miso, x"00", x"00", x"40", byte);
+    assert byte = x"C3" report "READ after EX4B mismatch" severity failure;
+
     -- Done
     report "TB completed" severity note;
     wait;
@@ -13,8 +13,28 @@ typedef struct FlashLlConfig {
     uint32_t mem_size;   // total flash size in bytes
     uint32_t page_size;
     uint32_t sector_size;
+    uint8_t addr_bytes;  // 3 or 4; 0 = auto (4 when mem_size > 16 MiB)
+    uint8_t read_cmd;    // READ, FAST_READ, DREAD or QREAD (3-byte opcode); 0 = READ
+    uint8_t read_dummy_cycles; // dummy clocks between address and data (0 for READ)
+    // Busy-wait budgets in status polls; 0 = FLASH_LL_DEFAULT_* at init
+    uint32_t program_timeout; // PP, OTP program
+    uint32_t erase_timeout;   // SE, OTP erase
+    uint32_t status_timeout;  // WRSR, WRSR2 (OTP lock)
+    uint32_t suspend_timeout;
+    uint32_t reset_timeout;   // reset, release from deep power-down
+    uint32_t fifo_slack;      // FIFO polls per transfer on top of 8 per byte (then ETIME)
+    // Optional; called after every busy poll (sleep, yield to an RTOS, kick a watchdog)
+    void (*poll_yield)(void *arg);
+    void *poll_yield_arg;
 } FlashLlConfig;
 
+#define FLASH_LL_DEFAULT_PROGRAM_TIMEOUT 100000u
+#define FLASH_LL_DEFAULT_ERASE_TIMEOUT   1000000u
+#define FLASH_LL_DEFAULT_STATUS_TIMEOUT  100000u
+#define FLASH_LL_DEFAULT_SUSPEND_TIMEOUT 1000u
+#define FLASH_LL_DEFAULT_RESET_TIMEOUT   10000u
+#define FLASH_LL_DEFAULT_FIFO_SLACK      1024u
+
 typedef struct FlashLlIo {
     uint32_t (*read)(void *io, uint32_t offset);
     void     (*write)(void *io, uint32_t offset, uint32_t value);
@@ -25,6 +45,9 @@ typedef struct FlashLlCtx {
     FlashLlConfig cfg;
     const FlashLlIo *io_ops;
     void *io; // opaque pointer to IO backend (AXI sim or HAL)
+    uint8_t suspended;   // set by flash_ll_suspend until flash_ll_resume
+    uint32_t erase_addr; // sector of the last flash_ll_sector_erase_start
+    uint8_t powered_down; // between flash_ll_deep_power_down and flash_ll_release_power_down
 } FlashLlCtx;
 
 typedef enum FlashLlErr {
@@ -34,15 +57,46 @@ typedef enum FlashLlErr {
     FLASH_LL_EBUSY = -3,
     FLASH_LL_ETIME = -4,
     FLASH_LL_EOOB = -5,
+    FLASH_LL_EPROT = -6, // target overlaps the block-protected (BP/TB) range
+    FLASH_LL_ESLEEP = -7, // device is in deep power-down
 } FlashLlErr;
 
 int flash_ll_init(FlashLlCtx *ctx, const FlashLlConfig *cfg, const FlashLlIo *ops, void *io_backend);
 int flash_ll_read(FlashLlCtx *ctx, uint32_t addr, void *buf, size_t len);
 int flash_ll_program(FlashLlCtx *ctx, uint32_t addr, const void *data, size_t len);
 int flash_ll_sector_erase(FlashLlCtx *ctx, uint32_t addr);
+// Issue SE and return without waiting; poll WIP, or suspend/resume around other work
+int flash_ll_sector_erase_start(FlashLlCtx *ctx, uint32_t addr);
 int flash_ll_rdsr(FlashLlCtx *ctx, uint8_t *status_out);
+int flash_ll_rdsr2(FlashLlCtx *ctx, uint8_t *status_out);
 int flash_ll_wren(FlashLlCtx *ctx);
+// Poll WIP up to max_ticks + 1 times; FLASH_LL_ETIME if still busy
 int flash_ll_wait_busy(FlashLlCtx *ctx, uint32_t max_ticks);
+// Select the opcode used by flash_ll_read; the 4-byte variant is picked automatically
+int flash_ll_set_read_mode(FlashLlCtx *ctx, uint8_t read_cmd, uint8_t dummy_cycles);
+// Suspend a running erase: 1 if suspended, 0 if the device was idle, <0 on error.
+// Reads, and programs outside the erased sector, are allowed until flash_ll_resume.
+int flash_ll_suspend(FlashLlCtx *ctx);
+// Resume a suspended erase (no-op if none); does not wait for completion
+int flash_ll_resume(FlashLlCtx *ctx);
+// Deep power-down (0xB9); every other call fails with FLASH_LL_ESLEEP until release.
+// Refused with FLASH_LL_EBUSY while a program/erase is running.
+int flash_ll_deep_power_down(FlashLlCtx *ctx);
+// Release from deep power-down (0xAB) and wait out the wake-up latency
+int flash_ll_release_power_down(FlashLlCtx *ctx);
+// Reset enable + reset (0x66, 0x99): aborts in-flight/suspended ops, clears WEL
+int flash_ll_reset(FlashLlCtx *ctx);
+// Security registers (reg 0..2, 256 bytes each). Program/erase fail with
+// FLASH_LL_EPROT once the register is locked; locking is permanent. Both are
+// refused with FLASH_LL_EBUSY while an erase is running or suspended.
+int flash_ll_otp_read(FlashLlCtx *ctx, uint8_t reg, uint32_t off, void *buf, size_t len);
+int flash_ll_otp_program(FlashLlCtx *ctx, uint8_t reg, uint32_t off, const void *data, size_t len);
+int flash_ll_otp_erase(FlashLlCtx *ctx, uint8_t reg);
+int flash_ll_otp_lock(FlashLlCtx *ctx, uint8_t reg);
+// Write Status Register (BP/TB bits); waits for completion
+int flash_ll_wrsr(FlashLlCtx *ctx, uint8_t status);
+// Protected range [start, end) encoded by a status value for this part; 0..0 if none
+void flash_ll_protected_range(const FlashLlCtx *ctx, uint8_t status, uint32_t *start, uint32_t *end);
 
 #ifdef __cplusplus
 }
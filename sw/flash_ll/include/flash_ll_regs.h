@@ -6,20 +6,63 @@
 
 enum {
     FLASH_LL_REG_SPI_CMD    = 0x00,
-    FLASH_LL_REG_SPI_ADDR   = 0x04,
+    FLASH_LL_REG_SPI_ADDR   = 0x04, // 32-bit; upper byte only used by 4-byte commands
     FLASH_LL_REG_SPI_LEN    = 0x08,
     FLASH_LL_REG_SPI_DIN    = 0x0C,
     FLASH_LL_REG_SPI_DOUT   = 0x10,
     FLASH_LL_REG_SPI_CTRL   = 0x14, // bit0=CS_EN, bit1=START
     FLASH_LL_REG_SPI_STATUS = 0x18, // bit0=BUSY, bit1=RX_AVAIL, bit2=TX_SPACE
+    FLASH_LL_REG_SPI_FMT    = 0x1C, // bits[7:0]=DUMMY cycles, bits[9:8]=DATA_LANES (0=x1, 1=x2, 2=x4)
 };
 
 enum {
     FLASH_LL_CMD_WREN = 0x06,
     FLASH_LL_CMD_RDSR = 0x05,
+    FLASH_LL_CMD_WRSR = 0x01,
+    FLASH_LL_CMD_RDSR2 = 0x35,
+    FLASH_LL_CMD_SUSPEND = 0x75,
+    FLASH_LL_CMD_RESUME  = 0x7A,
+    FLASH_LL_CMD_DP    = 0xB9, // deep power-down
+    FLASH_LL_CMD_RDP   = 0xAB, // release from deep power-down
+    FLASH_LL_CMD_RSTEN = 0x66,
+    FLASH_LL_CMD_RST   = 0x99,
+    FLASH_LL_CMD_WRSR2     = 0x31,
+    FLASH_LL_CMD_OTP_READ  = 0x48, // security register read (8 dummy cycles)
+    FLASH_LL_CMD_OTP_PROG  = 0x42,
+    FLASH_LL_CMD_OTP_ERASE = 0x44,
     FLASH_LL_CMD_READ = 0x03,
     FLASH_LL_CMD_PP   = 0x02,
     FLASH_LL_CMD_SE   = 0x20,
+    // 4-byte address mode and dedicated 4-byte opcodes (>16 MiB parts)
+    FLASH_LL_CMD_EN4B  = 0xB7,
+    FLASH_LL_CMD_EX4B  = 0xE9,
+    FLASH_LL_CMD_READ4 = 0x13,
+    FLASH_LL_CMD_PP4   = 0x12,
+    FLASH_LL_CMD_SE4   = 0x21,
+    // Fast/multi-I/O reads (dummy cycles after the address) and 4-byte variants
+    FLASH_LL_CMD_FAST_READ  = 0x0B,
+    FLASH_LL_CMD_DREAD      = 0x3B, // dual output
+    FLASH_LL_CMD_QREAD      = 0x6B, // quad output
+    FLASH_LL_CMD_FAST_READ4 = 0x0C,
+    FLASH_LL_CMD_DREAD4     = 0x3C,
+    FLASH_LL_CMD_QREAD4     = 0x6C,
+};
+
+// Status register bits
+enum {
+    FLASH_LL_SR_WIP = 0x01,
+    FLASH_LL_SR_WEL = 0x02,
+    FLASH_LL_SR_BP_MASK = 0x1C, // BP2..BP0
+    FLASH_LL_SR_BP_SHIFT = 2,
+    FLASH_LL_SR_TB = 0x20,      // protect from the bottom instead of the top
+    FLASH_LL_SR2_LB1 = 0x08,    // status register 2: security register 1 locked (LB2/LB3 follow)
+    FLASH_LL_SR2_SUS = 0x80,    // status register 2: program/erase suspended
+};
+
+// Security (OTP) registers
+enum {
+    FLASH_LL_OTP_REGS = 3,
+    FLASH_LL_OTP_SIZE = 256,
 };
 
 #endif // FLASH_LL_REGS_H
@@ -13,23 +13,50 @@ extern "C" {
 // Register map offsets (bytes)
 enum {
     REG_SPI_CMD    = 0x00, // command byte
-    REG_SPI_ADDR   = 0x04, // 24-bit address packed in 32-bit
+    REG_SPI_ADDR   = 0x04, // 32-bit address; 3-byte commands use the low 24 bits
     REG_SPI_LEN    = 0x08, // transfer length in bytes
     REG_SPI_DIN    = 0x0C, // write data FIFO (LSB byte used)
     REG_SPI_DOUT   = 0x10, // read data FIFO (LSB byte valid)
     REG_SPI_CTRL   = 0x14, // bit0=CS_EN, bit1=START (write 1 to start)
     REG_SPI_STATUS = 0x18, // bit0=BUSY, bit1=RX_AVAIL, bit2=TX_SPACE
+    REG_SPI_FMT    = 0x1C, // bits[7:0]=DUMMY cycles, bits[9:8]=DATA_LANES (0=x1, 1=x2, 2=x4)
 };
 
 // SPI commands we support
 enum {
     SPI_CMD_WREN = 0x06,
     SPI_CMD_RDSR = 0x05,
+    SPI_CMD_WRSR = 0x01,
+    SPI_CMD_RDSR2 = 0x35,
+    SPI_CMD_SUSPEND = 0x75,
+    SPI_CMD_RESUME  = 0x7A,
+    SPI_CMD_DP    = 0xB9, // deep power-down
+    SPI_CMD_RDP   = 0xAB, // release from deep power-down
+    SPI_CMD_RSTEN = 0x66,
+    SPI_CMD_RST   = 0x99,
+    SPI_CMD_WRSR2     = 0x31,
+    SPI_CMD_OTP_READ  = 0x48, // security register read (8 dummy cycles)
+    SPI_CMD_OTP_PROG  = 0x42,
+    SPI_CMD_OTP_ERASE = 0x44,
     SPI_CMD_READ = 0x03,
     SPI_CMD_PP   = 0x02,
     SPI_CMD_SE   = 0x20,
+    SPI_CMD_EN4B  = 0xB7,
+    SPI_CMD_EX4B  = 0xE9,
+    SPI_CMD_READ4 = 0x13,
+    SPI_CMD_PP4   = 0x12,
+    SPI_CMD_SE4   = 0x21,
+    SPI_CMD_FAST_READ  = 0x0B,
+    SPI_CMD_DREAD      = 0x3B,
+    SPI_CMD_QREAD      = 0x6B,
+    SPI_CMD_FAST_READ4 = 0x0C,
+    SPI_CMD_DREAD4     = 0x3C,
+    SPI_CMD_QREAD4     = 0x6C,
 };
 
+// How an active read produces bytes
+enum { AXI_SPI_RD_DATA = 0, AXI_SPI_RD_ONES = 1, AXI_SPI_RD_SKEW = 2 };
+
 typedef struct ByteFifo {
     uint8_t *buf;
     size_t cap;
@@ -46,8 +73,17 @@ typedef struct AxiSpiSim {
     uint32_t len;
     uint32_t ctrl;
     uint32_t status; // bit0=BUSY, bit1=RX_AVAIL, bit2=TX_SPACE
+    uint32_t fmt;    // dummy cycles and data lanes for the next transfers
+    uint64_t bus_cycles; // SPI clocks spent so far (opcode + address + dummy + data)
     ByteFifo tx; // host -> flash (PP data)
     ByteFifo rx; // flash -> host (READ/RDSR data)
+    // read in progress: bytes [rd_pos, rd_len) are streamed into RX as it drains
+    uint8_t  rd_cmd;
+    uint8_t  rd_mode;  // AXI_SPI_RD_*
+    uint32_t rd_addr;
+    uint32_t rd_pos;
+    uint32_t rd_len;
+    int32_t  rd_shift; // bit skew for AXI_SPI_RD_SKEW
 } AxiSpiSim;
 
 int axi_spi_sim_init(AxiSpiSim *s, FlashSim *flash, size_t fifo_cap);
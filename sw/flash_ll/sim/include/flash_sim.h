@@ -9,7 +9,31 @@
 extern "C" {
 #endif
 
-enum { FLASH_SIM_STATUS_WIP = 1u << 0, FLASH_SIM_STATUS_WEL = 1u << 1 };
+enum {
+    FLASH_SIM_STATUS_WIP = 1u << 0,
+    FLASH_SIM_STATUS_WEL = 1u << 1,
+    FLASH_SIM_STATUS_BP0 = 1u << 2, // BP2..BP0 select the protected fraction
+    FLASH_SIM_STATUS_BP1 = 1u << 3,
+    FLASH_SIM_STATUS_BP2 = 1u << 4,
+    FLASH_SIM_STATUS_TB  = 1u << 5, // 0 = protect from the top, 1 = from the bottom
+};
+// Status register 2 (RDSR2 0x35)
+enum {
+    FLASH_SIM_STATUS2_LB1 = 1u << 3, // security register 1..3 locked (OTP, set-only)
+    FLASH_SIM_STATUS2_LB2 = 1u << 4,
+    FLASH_SIM_STATUS2_LB3 = 1u << 5,
+    FLASH_SIM_STATUS2_SUS = 1u << 7, // program/erase suspended
+};
+
+// Security registers: register n (0-based) sits at address (n + 1) << 12,
+// byte offset in A7..A0; accesses wrap inside the register
+enum { FLASH_SIM_OTP_REGS = 3, FLASH_SIM_OTP_SIZE = 256 };
+
+// Operation that owns WIP (or is suspended)
+enum { FLASH_SIM_OP_NONE = 0, FLASH_SIM_OP_PROGRAM = 1, FLASH_SIM_OP_ERASE = 2, FLASH_SIM_OP_WRSR = 3 };
+
+// Status bits writable through WRSR
+#define FLASH_SIM_STATUS_WRSR_MASK (FLASH_SIM_STATUS_BP0 | FLASH_SIM_STATUS_BP1 | FLASH_SIM_STATUS_BP2 | FLASH_SIM_STATUS_TB)
 
 typedef struct FlashSimConfig {
     size_t mem_bytes;       // total bytes in flash
@@ -17,13 +41,26 @@ typedef struct FlashSimConfig {
     size_t sector_size;     // sector erase granularity
     uint32_t prog_busy_ticks;   // simulated busy ticks for page program
     uint32_t erase_busy_ticks;  // simulated busy ticks for sector erase
+    uint32_t read_dummy_cycles; // dummy clocks expected by FAST/DUAL/QUAD reads (0 = 8)
+    uint32_t wake_ticks;        // release-from-deep-power-down latency (tRES1)
 } FlashSimConfig;
 
 typedef struct FlashSim {
     FlashSimConfig cfg;
     uint8_t *mem;           // memory array of size cfg.mem_bytes
-    uint8_t status;         // bit0=WIP, bit1=WEL
+    uint8_t status;         // bit0=WIP, bit1=WEL, bit2..4=BP0..BP2, bit5=TB
     uint32_t busy_ticks;    // remaining busy ticks
+    uint8_t addr4;          // 4-byte address mode (EN4B/EX4B); 0 after init
+    uint8_t status2;        // bit7=SUS
+    uint8_t busy_op;        // FLASH_SIM_OP_* running while WIP
+    uint32_t busy_addr;     // page/sector base of busy_op
+    uint8_t susp_op;        // FLASH_SIM_OP_* parked by suspend
+    uint32_t susp_addr;     // page/sector base of susp_op
+    uint32_t susp_ticks;    // busy ticks left on the suspended op
+    uint8_t powered_down;   // deep power-down (0xB9) until 0xAB
+    uint32_t wake_ticks;    // remaining wake-up latency after 0xAB
+    uint8_t reset_enabled;  // 0x66 seen; cleared by any other command
+    uint8_t otp[FLASH_SIM_OTP_REGS][FLASH_SIM_OTP_SIZE]; // security registers
 } FlashSim;
 
 int flash_sim_init(FlashSim *sim, const FlashSimConfig *cfg);
@@ -35,12 +72,49 @@ void flash_sim_tick(FlashSim *sim, uint32_t ticks);
 // Commands
 void flash_sim_wren(FlashSim *sim);
 uint8_t flash_sim_rdsr(const FlashSim *sim);
+// Enter/exit 4-byte address mode; affects the 3-byte opcodes only
+void flash_sim_en4b(FlashSim *sim);
+void flash_sim_ex4b(FlashSim *sim);
+uint8_t flash_sim_rdsr2(const FlashSim *sim);
+// Suspend (0x75) a running program/erase: WIP clears, SUS sets and the
+// remaining busy ticks are parked. Returns -2 if there is nothing to suspend.
+// While an erase is suspended, pages outside its sector may be programmed.
+int flash_sim_suspend(FlashSim *sim);
+// Resume (0x7A) the suspended op with its remaining ticks; -2 if not suspended or busy
+int flash_sim_resume(FlashSim *sim);
+// Deep power-down (0xB9): everything but release is ignored until woken.
+// Returns -2 while busy (the part ignores it).
+int flash_sim_deep_power_down(FlashSim *sim);
+// Release from deep power-down (0xAB); commands stay ignored for cfg.wake_ticks
+int flash_sim_release_power_down(FlashSim *sim);
+// Powered up and past the wake-up latency: the part accepts commands
+int flash_sim_awake(const FlashSim *sim);
+// Reset enable (0x66) / reset (0x99): reset needs the enable right before it.
+// Clears WEL and SUS, aborts any in-flight or suspended op, exits 4-byte mode;
+// BP/TB and the array keep their contents.
+void flash_sim_reset_enable(FlashSim *sim);
+int flash_sim_reset(FlashSim *sim);
+// Write Status Register 2 (0x31): only the lock bits, which can never be cleared
+int flash_sim_wrsr2(FlashSim *sim, uint8_t value);
+// Security register read (0x48), program (0x42, 1->0 like PP) and erase (0x44).
+// Program/erase need WEL and are rejected (WEL cleared) once the register is
+// locked: program returns 0, erase -5. Invalid addresses read as 0xFF / return -4.
+size_t flash_sim_otp_read(const FlashSim *sim, uint32_t addr, uint8_t *out, size_t len);
+size_t flash_sim_otp_program(FlashSim *sim, uint32_t addr, const uint8_t *data, size_t len);
+int flash_sim_otp_erase(FlashSim *sim, uint32_t addr);
+// Write Status Register: requires WEL and not busy; updates BP/TB, busy like a PP
+int flash_sim_wrsr(FlashSim *sim, uint8_t value);
+// Protected range [start, end) from BP/TB; 0..0 when nothing is protected.
+// BP=1..6 protects mem_bytes >> (7 - BP) (1/64 .. 1/2), BP=7 everything.
+void flash_sim_protected_range(const FlashSim *sim, uint32_t *start, uint32_t *end);
 // READ bytes into out; returns number of bytes read
 size_t flash_sim_read(const FlashSim *sim, uint32_t addr, uint8_t *out, size_t len);
 // Page Program: programs up to page boundary; returns bytes actually programmed
 // Requires WEL set and not busy; sets WIP and clears WEL; applies 1->0 AND semantics
+// Rejected (returns 0, clears WEL) when the page range overlaps the protected area
 size_t flash_sim_page_program(FlashSim *sim, uint32_t addr, const uint8_t *data, size_t len);
 // Sector Erase: requires WEL and not busy; sets to 0xFF across sector
+// Returns -5 (and clears WEL) when the sector overlaps the protected area
 int flash_sim_sector_erase(FlashSim *sim, uint32_t addr);
 
 #ifdef __cplusplus
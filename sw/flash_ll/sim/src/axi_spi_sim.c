@@ -48,33 +48,179 @@ void axi_spi_sim_free(AxiSpiSim *s) {
     fifo_free(&s->rx);
 }
 
+static int is_4byte_cmd(uint8_t cmd) {
+    return cmd == SPI_CMD_READ4 || cmd == SPI_CMD_PP4 || cmd == SPI_CMD_SE4 ||
+           cmd == SPI_CMD_FAST_READ4 || cmd == SPI_CMD_DREAD4 || cmd == SPI_CMD_QREAD4;
+}
+
+// Address as seen by the flash: dedicated 4-byte opcodes always clock out
+// 32 bits, legacy opcodes only do so once the part is in 4-byte mode.
+static uint32_t cmd_addr(const AxiSpiSim *s) {
+    int four = is_4byte_cmd(s->cmd) || s->flash->addr4;
+    return four ? s->addr : (s->addr & 0xFFFFFFu);
+}
+
+static uint32_t cmd_addr_bytes(const AxiSpiSim *s) {
+    return (is_4byte_cmd(s->cmd) || s->flash->addr4) ? 4u : 3u;
+}
+
+// Read opcodes: data lanes (SPI_FMT encoding) and whether the flash inserts
+// dummy cycles before the data. Returns 0 for anything that is not a read.
+static int decode_read(uint8_t cmd, uint32_t *lanes, int *fast) {
+    switch (cmd) {
+        case SPI_CMD_READ: case SPI_CMD_READ4:           *lanes = 0; *fast = 0; return 1;
+        case SPI_CMD_FAST_READ: case SPI_CMD_FAST_READ4: *lanes = 0; *fast = 1; return 1;
+        case SPI_CMD_DREAD: case SPI_CMD_DREAD4:         *lanes = 1; *fast = 1; return 1;
+        case SPI_CMD_QREAD: case SPI_CMD_QREAD4:         *lanes = 2; *fast = 1; return 1;
+        case SPI_CMD_OTP_READ:                           *lanes = 0; *fast = 1; return 1;
+        default: return 0;
+    }
+}
+
+// Array or security registers, depending on the read opcode
+static size_t source_read(const AxiSpiSim *s, uint32_t a, uint8_t *out, size_t len) {
+    if (s->rd_cmd == SPI_CMD_OTP_READ) return flash_sim_otp_read(s->flash, a, out, len);
+    return flash_sim_read(s->flash, a, out, len);
+}
+
+// Byte of the flash output stream at index idx; lines read as 1 while the
+// flash is not driving them (idx < 0 or past the end of the array).
+static uint8_t stream_byte(const AxiSpiSim *s, uint32_t a, int64_t idx) {
+    uint8_t b = 0xFF;
+    if (idx >= 0) (void)source_read(s, a + (uint32_t)idx, &b, 1);
+    return b;
+}
+
+// Next byte of the read stream at position i, as the host samples it
+static uint8_t read_byte_at(const AxiSpiSim *s, uint32_t i) {
+    switch (s->rd_mode) {
+        case AXI_SPI_RD_ONES:
+            return 0xFF;
+        case AXI_SPI_RD_SKEW: {
+            // Wrong dummy count: the host window is skewed against the data
+            // by the cycle difference, so bytes come back bit-shifted.
+            int64_t k0 = (int64_t)i * 8 - s->rd_shift;
+            int64_t q = (k0 >= 0) ? k0 / 8 : -((-k0 + 7) / 8);
+            uint32_t r = (uint32_t)(k0 - q * 8);
+            uint16_t w = (uint16_t)((stream_byte(s, s->rd_addr, q) << 8) | stream_byte(s, s->rd_addr, q + 1));
+            return (uint8_t)(w >> (8 - r));
+        }
+        default:
+            return stream_byte(s, s->rd_addr, i);
+    }
+}
+
+// Move as much of the active read into RX as fits; called on START and
+// whenever the host drains DOUT, so reads can exceed the FIFO size.
+static void rx_refill(AxiSpiSim *s) {
+    while (s->rd_pos < s->rd_len && s->rx.count < s->rx.cap) {
+        fifo_push(&s->rx, read_byte_at(s, s->rd_pos++));
+    }
+}
+
+static void do_read(AxiSpiSim *s, uint32_t lanes, int fast) {
+    uint32_t host_dummy = s->fmt & 0xFFu;
+    uint32_t host_lanes = (s->fmt >> 8) & 0x3u;
+    uint32_t width = 1u << lanes; // bits per SPI clock
+    uint32_t flash_dummy = !fast ? 0 : (s->cmd == SPI_CMD_OTP_READ) ? 8u : s->flash->cfg.read_dummy_cycles;
+    s->bus_cycles += 8u + cmd_addr_bytes(s) * 8u + host_dummy + ((uint64_t)s->len * 8u) / (1u << host_lanes);
+
+    s->rd_addr = cmd_addr(s);
+    s->rd_pos = 0;
+    s->rd_len = s->len;
+    s->rd_shift = 0;
+    if (s->flash->status & FLASH_SIM_STATUS_WIP) {
+        // Array reads are ignored while a program/erase runs (suspend first)
+        s->rd_mode = AXI_SPI_RD_ONES;
+    } else if (host_lanes != lanes) {
+        // Host samples lines the flash does not drive: all ones
+        s->rd_mode = AXI_SPI_RD_ONES;
+    } else if (host_dummy != flash_dummy) {
+        s->rd_mode = AXI_SPI_RD_SKEW;
+        s->rd_shift = ((int32_t)flash_dummy - (int32_t)host_dummy) * (int32_t)width;
+    } else {
+        s->rd_mode = AXI_SPI_RD_DATA;
+    }
+    rx_refill(s);
+}
+
 static void do_start(AxiSpiSim *s) {
-    // handle based on s->cmd
+    // handle based on s->cmd; a new command ends any read still streaming
     uint8_t cmd = s->cmd;
-    if (cmd == SPI_CMD_READ) {
-        // READ: fill RX with LEN bytes from addr
-        uint8_t tmp[256];
-        size_t remain = s->len;
-        uint32_t a = s->addr & 0xFFFFFFu;
-        while (remain > 0) {
-            size_t chunk = remain > sizeof(tmp) ? sizeof(tmp) : remain;
-            size_t got = flash_sim_read(s->flash, a, tmp, chunk);
-            for (size_t i = 0; i < got; ++i) {
-                if (fifo_push(&s->rx, tmp[i]) != 0) break; // stop if RX is full
-            }
-            a += (uint32_t)got;
-            if (got == 0) break;
-            remain -= got;
-            if (s->rx.count == s->rx.cap) break; // RX full
-        }
+    s->rd_len = s->rd_pos = 0;
+    s->rd_cmd = cmd;
+    uint32_t lanes = 0;
+    int fast = 0;
+    if (cmd != SPI_CMD_RSTEN && cmd != SPI_CMD_RST) s->flash->reset_enabled = 0;
+    if (!flash_sim_awake(s->flash) && cmd != SPI_CMD_RDP) {
+        // Powered down or still waking: the part ignores the command and
+        // does not drive DO, so anything clocked in reads as ones
+        s->bus_cycles += 8u;
+        uint32_t n = (decode_read(cmd, &lanes, &fast) || cmd == SPI_CMD_RDSR || cmd == SPI_CMD_RDSR2) ? s->len : 0;
+        for (uint32_t i = 0; i < n && s->rx.count < s->rx.cap; ++i) fifo_push(&s->rx, 0xFF);
+    } else if (cmd == SPI_CMD_RDP) {
+        s->bus_cycles += 8u;
+        (void)flash_sim_release_power_down(s->flash);
+    } else if (cmd == SPI_CMD_DP) {
+        s->bus_cycles += 8u;
+        (void)flash_sim_deep_power_down(s->flash);
+    } else if (cmd == SPI_CMD_RSTEN) {
+        s->bus_cycles += 8u;
+        flash_sim_reset_enable(s->flash);
+    } else if (cmd == SPI_CMD_RST) {
+        s->bus_cycles += 8u;
+        (void)flash_sim_reset(s->flash);
+    } else if (decode_read(cmd, &lanes, &fast)) {
+        do_read(s, lanes, fast);
     } else if (cmd == SPI_CMD_RDSR) {
+        s->bus_cycles += 8u + (uint64_t)s->len * 8u;
         // Stream LEN status bytes
         for (uint32_t i = 0; i < s->len && s->rx.count < s->rx.cap; ++i) {
             fifo_push(&s->rx, flash_sim_rdsr(s->flash));
         }
+    } else if (cmd == SPI_CMD_RDSR2) {
+        s->bus_cycles += 8u + (uint64_t)s->len * 8u;
+        for (uint32_t i = 0; i < s->len && s->rx.count < s->rx.cap; ++i) {
+            fifo_push(&s->rx, flash_sim_rdsr2(s->flash));
+        }
+    } else if (cmd == SPI_CMD_SUSPEND) {
+        s->bus_cycles += 8u;
+        (void)flash_sim_suspend(s->flash);
+    } else if (cmd == SPI_CMD_RESUME) {
+        s->bus_cycles += 8u;
+        (void)flash_sim_resume(s->flash);
+    } else if (cmd == SPI_CMD_WRSR2) {
+        uint8_t v = 0;
+        s->bus_cycles += 16u;
+        if (s->len > 0 && fifo_pop(&s->tx, &v) == 0) (void)flash_sim_wrsr2(s->flash, v);
+    } else if (cmd == SPI_CMD_OTP_PROG) {
+        uint8_t buf[FLASH_SIM_OTP_SIZE];
+        size_t n = 0;
+        while (n < sizeof(buf) && n < s->len) {
+            uint8_t b;
+            if (fifo_pop(&s->tx, &b) != 0) break;
+            buf[n++] = b;
+        }
+        s->bus_cycles += 8u + cmd_addr_bytes(s) * 8u + (uint64_t)n * 8u;
+        (void)flash_sim_otp_program(s->flash, cmd_addr(s), buf, n);
+    } else if (cmd == SPI_CMD_OTP_ERASE) {
+        s->bus_cycles += 8u + cmd_addr_bytes(s) * 8u;
+        (void)flash_sim_otp_erase(s->flash, cmd_addr(s));
+    } else if (cmd == SPI_CMD_WRSR) {
+        // status byte comes from TX like PP data
+        uint8_t v = 0;
+        s->bus_cycles += 16u;
+        if (s->len > 0 && fifo_pop(&s->tx, &v) == 0) (void)flash_sim_wrsr(s->flash, v);
     } else if (cmd == SPI_CMD_WREN) {
+        s->bus_cycles += 8u;
         flash_sim_wren(s->flash);
-    } else if (cmd == SPI_CMD_PP) {
+    } else if (cmd == SPI_CMD_EN4B) {
+        s->bus_cycles += 8u;
+        flash_sim_en4b(s->flash);
+    } else if (cmd == SPI_CMD_EX4B) {
+        s->bus_cycles += 8u;
+        flash_sim_ex4b(s->flash);
+    } else if (cmd == SPI_CMD_PP || cmd == SPI_CMD_PP4) {
         // consume up to LEN bytes from TX and program
         uint8_t buf[256];
         size_t n = 0;
@@ -83,9 +229,11 @@ static void do_start(AxiSpiSim *s) {
             if (fifo_pop(&s->tx, &b) != 0) break;
             buf[n++] = b;
         }
-        (void)flash_sim_page_program(s->flash, s->addr & 0xFFFFFFu, buf, n);
-    } else if (cmd == SPI_CMD_SE) {
-        (void)flash_sim_sector_erase(s->flash, s->addr & 0xFFFFFFu);
+        s->bus_cycles += 8u + cmd_addr_bytes(s) * 8u + (uint64_t)n * 8u;
+        (void)flash_sim_page_program(s->flash, cmd_addr(s), buf, n);
+    } else if (cmd == SPI_CMD_SE || cmd == SPI_CMD_SE4) {
+        s->bus_cycles += 8u + cmd_addr_bytes(s) * 8u;
+        (void)flash_sim_sector_erase(s->flash, cmd_addr(s));
     }
     // Clear START bit
     s->ctrl &= ~((uint32_t)1u << 1);
@@ -95,8 +243,9 @@ static void do_start(AxiSpiSim *s) {
 void axi_spi_write(AxiSpiSim *s, uint32_t offset, uint32_t value) {
     switch (offset) {
         case REG_SPI_CMD:    s->cmd  = (uint8_t)(value & 0xFFu); break;
-        case REG_SPI_ADDR:   s->addr = value & 0xFFFFFFu; break;
+        case REG_SPI_ADDR:   s->addr = value; break;
         case REG_SPI_LEN:    s->len  = value; break;
+        case REG_SPI_FMT:    s->fmt  = value & 0x3FFu; break;
         case REG_SPI_DIN: {
             uint8_t b = (uint8_t)(value & 0xFFu);
             (void)fifo_push(&s->tx, b);
@@ -122,11 +271,13 @@ uint32_t axi_spi_read(AxiSpiSim *s, uint32_t offset) {
         case REG_SPI_DOUT: {
             uint8_t b = 0;
             (void)fifo_pop(&s->rx, &b);
+            rx_refill(s);
             update_status(s);
             return b;
         }
         case REG_SPI_CTRL:   return s->ctrl;
         case REG_SPI_STATUS: return s->status;
+        case REG_SPI_FMT:    return s->fmt;
         default: return 0;
     }
 }
@@ -13,11 +13,23 @@ int flash_sim_init(FlashSim *sim, const FlashSimConfig *cfg) {
         return -1;
     }
     sim->cfg = *cfg;
+    if (sim->cfg.read_dummy_cycles == 0) sim->cfg.read_dummy_cycles = 8;
     sim->mem = (uint8_t*)malloc(cfg->mem_bytes);
     if (!sim->mem) return -2;
     memset(sim->mem, 0xFF, cfg->mem_bytes);
     sim->status = 0; // WIP=0, WEL=0
     sim->busy_ticks = 0;
+    sim->addr4 = 0;
+    sim->status2 = 0;
+    sim->busy_op = FLASH_SIM_OP_NONE;
+    sim->busy_addr = 0;
+    sim->susp_op = FLASH_SIM_OP_NONE;
+    sim->susp_addr = 0;
+    sim->susp_ticks = 0;
+    sim->powered_down = 0;
+    sim->wake_ticks = 0;
+    sim->reset_enabled = 0;
+    memset(sim->otp, 0xFF, sizeof(sim->otp));
     return 0;
 }
 
@@ -30,9 +42,11 @@ void flash_sim_free(FlashSim *sim) {
 
 void flash_sim_tick(FlashSim *sim, uint32_t ticks) {
     if (!sim) return;
+    sim->wake_ticks = (ticks >= sim->wake_ticks) ? 0 : sim->wake_ticks - ticks;
     if (sim->busy_ticks > 0) {
         if (ticks >= sim->busy_ticks) {
             sim->busy_ticks = 0;
+            sim->busy_op = FLASH_SIM_OP_NONE;
             sim->status &= (uint8_t)~FLASH_SIM_STATUS_WIP; // clear WIP
         } else {
             sim->busy_ticks -= ticks;
@@ -40,8 +54,46 @@ void flash_sim_tick(FlashSim *sim, uint32_t ticks) {
     }
 }
 
+int flash_sim_awake(const FlashSim *sim) {
+    return sim && !sim->powered_down && sim->wake_ticks == 0;
+}
+
+int flash_sim_deep_power_down(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return -2;
+    sim->powered_down = 1;
+    return 0;
+}
+
+int flash_sim_release_power_down(FlashSim *sim) {
+    if (!sim) return -1;
+    if (!sim->powered_down) return 0;
+    sim->powered_down = 0;
+    sim->wake_ticks = sim->cfg.wake_ticks;
+    return 0;
+}
+
+void flash_sim_reset_enable(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return;
+    sim->reset_enabled = 1;
+}
+
+int flash_sim_reset(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (!sim->reset_enabled) return -3; // needs 0x66 first
+    sim->reset_enabled = 0;
+    sim->status &= (uint8_t)~(FLASH_SIM_STATUS_WIP | FLASH_SIM_STATUS_WEL);
+    sim->status2 &= (uint8_t)~FLASH_SIM_STATUS2_SUS; // lock bits are non-volatile
+    sim->busy_ticks = 0;
+    sim->busy_op = FLASH_SIM_OP_NONE;
+    sim->susp_op = FLASH_SIM_OP_NONE;
+    sim->susp_ticks = 0;
+    sim->addr4 = 0;
+    return 0;
+}
+
 void flash_sim_wren(FlashSim *sim) {
-    if (!sim) return;
+    if (!flash_sim_awake(sim)) return;
     sim->status |= FLASH_SIM_STATUS_WEL;
 }
 
@@ -49,6 +101,153 @@ uint8_t flash_sim_rdsr(const FlashSim *sim) {
     return sim ? sim->status : 0; 
 }
 
+uint8_t flash_sim_rdsr2(const FlashSim *sim) {
+    return sim ? sim->status2 : 0;
+}
+
+int flash_sim_suspend(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return -2; // no nesting
+    if ((sim->status & FLASH_SIM_STATUS_WIP) == 0) return -2;
+    if (sim->busy_op != FLASH_SIM_OP_PROGRAM && sim->busy_op != FLASH_SIM_OP_ERASE) return -2;
+    sim->susp_op = sim->busy_op;
+    sim->susp_addr = sim->busy_addr;
+    sim->susp_ticks = sim->busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_NONE;
+    sim->busy_ticks = 0;
+    sim->status &= (uint8_t)~FLASH_SIM_STATUS_WIP;
+    sim->status2 |= FLASH_SIM_STATUS2_SUS;
+    return 0;
+}
+
+int flash_sim_resume(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return -1;
+    if ((sim->status2 & FLASH_SIM_STATUS2_SUS) == 0) return -2;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return -2; // op issued during suspend still running
+    sim->busy_op = sim->susp_op;
+    sim->busy_addr = sim->susp_addr;
+    sim->busy_ticks = sim->susp_ticks;
+    sim->susp_op = FLASH_SIM_OP_NONE;
+    sim->susp_ticks = 0;
+    sim->status2 &= (uint8_t)~FLASH_SIM_STATUS2_SUS;
+    if (sim->busy_ticks > 0) sim->status |= FLASH_SIM_STATUS_WIP;
+    else sim->busy_op = FLASH_SIM_OP_NONE;
+    return 0;
+}
+
+void flash_sim_en4b(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return;
+    sim->addr4 = 1;
+}
+
+void flash_sim_ex4b(FlashSim *sim) {
+    if (!flash_sim_awake(sim)) return;
+    sim->addr4 = 0;
+}
+
+int flash_sim_wrsr(FlashSim *sim, uint8_t value) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return -2; // busy
+    if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return -3; // not enabled
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return -2;
+    sim->status = (uint8_t)((sim->status & ~FLASH_SIM_STATUS_WRSR_MASK) | (value & FLASH_SIM_STATUS_WRSR_MASK));
+    sim->status |= FLASH_SIM_STATUS_WIP;
+    sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+    sim->busy_ticks = sim->cfg.prog_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_WRSR;
+    return 0;
+}
+
+int flash_sim_wrsr2(FlashSim *sim, uint8_t value) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return -2; // busy
+    if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return -3; // not enabled
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return -2;
+    const uint8_t lb = FLASH_SIM_STATUS2_LB1 | FLASH_SIM_STATUS2_LB2 | FLASH_SIM_STATUS2_LB3;
+    sim->status2 |= (uint8_t)(value & lb);
+    sim->status |= FLASH_SIM_STATUS_WIP;
+    sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+    sim->busy_ticks = sim->cfg.prog_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_WRSR;
+    return 0;
+}
+
+// Register index for a security register address, or -1
+static int otp_index(uint32_t addr) {
+    uint32_t n = (addr >> 12) & 0xFFFu;
+    return (n >= 1 && n <= FLASH_SIM_OTP_REGS) ? (int)n - 1 : -1;
+}
+
+static int otp_locked(const FlashSim *sim, int idx) {
+    return (sim->status2 & (FLASH_SIM_STATUS2_LB1 << idx)) != 0;
+}
+
+size_t flash_sim_otp_read(const FlashSim *sim, uint32_t addr, uint8_t *out, size_t len) {
+    if (!sim || !out || len == 0) return 0;
+    int idx = otp_index(addr);
+    for (size_t i = 0; i < len; ++i) {
+        out[i] = (idx < 0) ? 0xFF : sim->otp[idx][(addr + i) % FLASH_SIM_OTP_SIZE];
+    }
+    return len;
+}
+
+size_t flash_sim_otp_program(FlashSim *sim, uint32_t addr, const uint8_t *data, size_t len) {
+    if (!flash_sim_awake(sim) || !data || len == 0) return 0;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return 0; // busy
+    if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return 0; // not enabled
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return 0;
+    int idx = otp_index(addr);
+    if (idx < 0 || otp_locked(sim, idx)) {
+        sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+        return 0;
+    }
+    if (len > FLASH_SIM_OTP_SIZE) len = FLASH_SIM_OTP_SIZE;
+    for (size_t i = 0; i < len; ++i) {
+        sim->otp[idx][(addr + i) % FLASH_SIM_OTP_SIZE] &= data[i];
+    }
+    sim->status |= FLASH_SIM_STATUS_WIP;
+    sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+    sim->busy_ticks = sim->cfg.prog_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_PROGRAM;
+    sim->busy_addr = addr;
+    return len;
+}
+
+int flash_sim_otp_erase(FlashSim *sim, uint32_t addr) {
+    if (!flash_sim_awake(sim)) return -1;
+    if (sim->status & FLASH_SIM_STATUS_WIP) return -2; // busy
+    if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return -3; // not enabled
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return -2;
+    int idx = otp_index(addr);
+    if (idx < 0) return -4;
+    if (otp_locked(sim, idx)) {
+        sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+        return -5;
+    }
+    memset(sim->otp[idx], 0xFF, FLASH_SIM_OTP_SIZE);
+    sim->status |= FLASH_SIM_STATUS_WIP;
+    sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+    sim->busy_ticks = sim->cfg.erase_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_ERASE;
+    sim->busy_addr = addr;
+    return 0;
+}
+
+void flash_sim_protected_range(const FlashSim *sim, uint32_t *start, uint32_t *end) {
+    uint32_t mem = (uint32_t)sim->cfg.mem_bytes;
+    uint32_t bp = (sim->status >> 2) & 0x7u;
+    uint32_t size = (bp == 0) ? 0 : (bp == 7) ? mem : (mem >> (7 - bp));
+    if (size == 0) { *start = 0; *end = 0; }
+    else if (sim->status & FLASH_SIM_STATUS_TB) { *start = 0; *end = size; }
+    else { *start = mem - size; *end = mem; }
+}
+
+static int overlaps_protected(const FlashSim *sim, uint32_t addr, size_t len) {
+    uint32_t ps, pe;
+    flash_sim_protected_range(sim, &ps, &pe);
+    return ps < pe && addr < pe && (uint64_t)addr + len > ps;
+}
+
 size_t flash_sim_read(const FlashSim *sim, uint32_t addr, uint8_t *out, size_t len) {
     if (!sim || !out || len == 0) return 0;
     if (addr >= sim->cfg.mem_bytes) return 0;
@@ -59,7 +258,7 @@ size_t flash_sim_read(const FlashSim *sim, uint32_t addr, uint8_t *out, size_t l
 }
 
 size_t flash_sim_page_program(FlashSim *sim, uint32_t addr, const uint8_t *data, size_t len) {
-    if (!sim || !data || len == 0) return 0;
+    if (!flash_sim_awake(sim) || !data || len == 0) return 0;
     if (sim->status & FLASH_SIM_STATUS_WIP) return 0; // busy
     if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return 0; // not enabled
     if (addr >= sim->cfg.mem_bytes) return 0;
@@ -67,6 +266,15 @@ size_t flash_sim_page_program(FlashSim *sim, uint32_t addr, const uint8_t *data,
     uint32_t max_in_page = clamp_to_page(sim, addr, len);
     size_t max_bytes = sim->cfg.mem_bytes - addr;
     size_t n = max_in_page < max_bytes ? max_in_page : max_bytes;
+    if (overlaps_protected(sim, addr, n)) {
+        sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+        return 0;
+    }
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) {
+        // Only an erase suspend allows programming, and not inside that sector
+        uint32_t sec = (uint32_t)sim->cfg.sector_size;
+        if (sim->susp_op != FLASH_SIM_OP_ERASE || addr / sec == sim->susp_addr / sec) return 0;
+    }
     for (size_t i = 0; i < n; ++i) {
         sim->mem[addr + i] &= data[i]; // 1->0 only
     }
@@ -74,21 +282,30 @@ size_t flash_sim_page_program(FlashSim *sim, uint32_t addr, const uint8_t *data,
     sim->status |= FLASH_SIM_STATUS_WIP;
     sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
     sim->busy_ticks = sim->cfg.prog_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_PROGRAM;
+    sim->busy_addr = addr;
     return n;
 }
 
 int flash_sim_sector_erase(FlashSim *sim, uint32_t addr) {
-    if (!sim) return -1;
+    if (!flash_sim_awake(sim)) return -1;
     if (sim->status & FLASH_SIM_STATUS_WIP) return -2; // busy
     if ((sim->status & FLASH_SIM_STATUS_WEL) == 0) return -3; // not enabled
+    if (sim->status2 & FLASH_SIM_STATUS2_SUS) return -2; // no erase while suspended
     if (addr >= sim->cfg.mem_bytes) return -4;
     uint32_t base = (addr / (uint32_t)sim->cfg.sector_size) * (uint32_t)sim->cfg.sector_size;
     size_t n = sim->cfg.sector_size;
     if (base + n > sim->cfg.mem_bytes) n = sim->cfg.mem_bytes - base;
+    if (overlaps_protected(sim, base, n)) {
+        sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
+        return -5;
+    }
     memset(sim->mem + base, 0xFF, n);
     sim->status |= FLASH_SIM_STATUS_WIP;
     sim->status &= (uint8_t)~FLASH_SIM_STATUS_WEL;
     sim->busy_ticks = sim->cfg.erase_busy_ticks;
+    sim->busy_op = FLASH_SIM_OP_ERASE;
+    sim->busy_addr = base;
     return 0;
 }
 
@@ -6,6 +6,7 @@
 #include "axi_spi_sim.h"
 #include "sim_test.h"
 #include "flash_ll.h"
+#include "flash_ll_regs.h"
 #include "flash_ll_io_sim.h"
 
 static void write_bytes(AxiSpiSim *s, const uint8_t *data, size_t n) {
@@ -28,9 +29,9 @@ static void issue_cmd(AxiSpiSim *s, uint8_t cmd, uint32_t addr, uint32_t len) {
 }
 
 // Shared setup for tests
-static void setup(FlashSim *flash, AxiSpiSim *spi) {
+static void setup_sized(FlashSim *flash, AxiSpiSim *spi, size_t mem_bytes) {
     FlashSimConfig cfg = {
-        .mem_bytes = 4096,
+        .mem_bytes = mem_bytes,
         .page_size = 256,
         .sector_size = 4096,
         .prog_busy_ticks = 4,
@@ -42,6 +43,12 @@ static void setup(FlashSim *flash, AxiSpiSim *spi) {
     ASSERT_EQ_U32(r, 0);
 }
 
+static void setup(FlashSim *flash, AxiSpiSim *spi) {
+    setup_sized(flash, spi, 4096);
+}
+
+#define MEM_32M (32u * 1024u * 1024u)
+
 // Test 1: RDSR after reset
 TEST_CASE(test_rdsr_after_reset) {
     FlashSim f; AxiSpiSim s; setup(&f, &s);
@@ -178,6 +185,280 @@ TEST_CASE(test_erase_without_wren) {
     axi_spi_sim_free(&s); flash_sim_free(&f);
 }
 
+// Test 9: dedicated 4-byte opcodes reach past 16 MiB without aliasing
+TEST_CASE(test_4byte_opcodes_no_alias) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, MEM_32M);
+    const uint32_t hi = MEM_32M - 0x100; // 0x01FFFF00
+    const uint8_t data[2] = {0x5A, 0xA5};
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, data, 2);
+    issue_cmd(&s, SPI_CMD_PP4, hi, 2);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_READ4, hi, 2);
+    uint8_t out[2]; read_bytes(&s, out, 2);
+    ASSERT_MEMEQ(out, data, 2);
+    // 3-byte READ of the same address only sees the low 24 bits
+    issue_cmd(&s, SPI_CMD_READ, hi, 2);
+    read_bytes(&s, out, 2);
+    ASSERT_EQ_U8(out[0], 0xFF); ASSERT_EQ_U8(out[1], 0xFF);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 10: EN4B switches legacy opcodes to 32-bit addresses, EX4B restores
+TEST_CASE(test_en4b_ex4b_mode) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, MEM_32M);
+    const uint32_t hi = 0x01000010;
+    issue_cmd(&s, SPI_CMD_EN4B, 0, 0);
+    ASSERT_EQ_U8(f.addr4, 1);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    uint8_t v = 0x3C; write_bytes(&s, &v, 1);
+    issue_cmd(&s, SPI_CMD_PP, hi, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_READ, hi, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0x3C);
+    issue_cmd(&s, SPI_CMD_EX4B, 0, 0);
+    ASSERT_EQ_U8(f.addr4, 0);
+    issue_cmd(&s, SPI_CMD_READ, hi, 1); // aliases to 0x000010
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 11: FAST_READ needs the flash's dummy cycles; a short count skews the data
+TEST_CASE(test_fast_read_dummy_cycles) {
+    FlashSim f; AxiSpiSim s; setup(&f, &s);
+    const uint8_t data[3] = {0x12, 0x34, 0x56};
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, data, 3);
+    issue_cmd(&s, SPI_CMD_PP, 0x40, 3);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    uint8_t out[3];
+    axi_spi_write(&s, REG_SPI_FMT, 8); // 8 dummy cycles, x1
+    issue_cmd(&s, SPI_CMD_FAST_READ, 0x40, 3);
+    read_bytes(&s, out, 3);
+    ASSERT_MEMEQ(out, data, 3);
+    axi_spi_write(&s, REG_SPI_FMT, 0); // no dummy: first byte is the undriven bus
+    issue_cmd(&s, SPI_CMD_FAST_READ, 0x40, 3);
+    read_bytes(&s, out, 3);
+    ASSERT_EQ_U8(out[0], 0xFF); ASSERT_EQ_U8(out[1], 0x12); ASSERT_EQ_U8(out[2], 0x34);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 12: dual/quad output reads need a matching lane count and use fewer clocks
+TEST_CASE(test_multi_io_read_lanes) {
+    FlashSim f; AxiSpiSim s; setup(&f, &s);
+    const uint8_t data[4] = {0xC0, 0xFF, 0xEE, 0x01};
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, data, 4);
+    issue_cmd(&s, SPI_CMD_PP, 0x80, 4);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    uint8_t out[4];
+    uint64_t c0 = s.bus_cycles;
+    axi_spi_write(&s, REG_SPI_FMT, 8);
+    issue_cmd(&s, SPI_CMD_FAST_READ, 0x80, 4);
+    read_bytes(&s, out, 4);
+    uint64_t x1_cycles = s.bus_cycles - c0;
+    axi_spi_write(&s, REG_SPI_FMT, 8 | (2u << 8)); // quad
+    c0 = s.bus_cycles;
+    issue_cmd(&s, SPI_CMD_QREAD, 0x80, 4);
+    read_bytes(&s, out, 4);
+    ASSERT_MEMEQ(out, data, 4);
+    ASSERT_EQ_U32(x1_cycles - (s.bus_cycles - c0), 24); // 32 data bits: 32 clocks x1 vs 8 x4
+    axi_spi_write(&s, REG_SPI_FMT, 8 | (1u << 8)); // dual
+    issue_cmd(&s, SPI_CMD_DREAD, 0x80, 4);
+    read_bytes(&s, out, 4);
+    ASSERT_MEMEQ(out, data, 4);
+    issue_cmd(&s, SPI_CMD_QREAD, 0x80, 1); // lanes still x2
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 13: WRSR sets BP/TB and protected PP/SE are dropped
+TEST_CASE(test_wrsr_block_protect) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    uint32_t ps, pe;
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    uint8_t sr = FLASH_SIM_STATUS_BP2 | FLASH_SIM_STATUS_BP1 | FLASH_SIM_STATUS_TB; // BP=6: bottom half
+    write_bytes(&s, &sr, 1);
+    issue_cmd(&s, SPI_CMD_WRSR, 0, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_RDSR, 0, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), sr);
+    flash_sim_protected_range(&f, &ps, &pe);
+    ASSERT_EQ_U32(ps, 0); ASSERT_EQ_U32(pe, 4096);
+    // PP into the protected half: ignored, WEL dropped, no busy
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    uint8_t d = 0x00; write_bytes(&s, &d, 1);
+    issue_cmd(&s, SPI_CMD_PP, 0x10, 1);
+    ASSERT_EQ_U8(f.status & (FLASH_SIM_STATUS_WIP | FLASH_SIM_STATUS_WEL), 0);
+    issue_cmd(&s, SPI_CMD_READ, 0x10, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    // unprotected upper half still programs
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, &d, 1);
+    issue_cmd(&s, SPI_CMD_PP, 0x1010, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_READ, 0x1010, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0x00);
+    // WRSR without WREN has no effect
+    sr = 0; write_bytes(&s, &sr, 1);
+    issue_cmd(&s, SPI_CMD_WRSR, 0, 1);
+    ASSERT_EQ_U32(flash_sim_sector_erase(&f, 0), (uint32_t)-3);
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_sector_erase(&f, 0), (uint32_t)-5);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 14: suspend parks the erase (SUS, remaining ticks), reads work, resume finishes it
+TEST_CASE(test_erase_suspend_resume) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    uint8_t d = 0xA5;
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, &d, 1);
+    issue_cmd(&s, SPI_CMD_PP, 0x1000, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    issue_cmd(&s, SPI_CMD_SE, 0x0000, 0);
+    axi_spi_tick(&s, 10);
+    // busy: array reads are ignored
+    issue_cmd(&s, SPI_CMD_READ, 0x1000, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    issue_cmd(&s, SPI_CMD_SUSPEND, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, 0);
+    issue_cmd(&s, SPI_CMD_RDSR2, 0, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), FLASH_SIM_STATUS2_SUS);
+    ASSERT_EQ_U32(f.susp_ticks, 54);
+    // time does not advance the suspended erase
+    axi_spi_tick(&s, 100);
+    ASSERT_EQ_U32(f.susp_ticks, 54);
+    issue_cmd(&s, SPI_CMD_READ, 0x1000, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xA5);
+    // program outside the erased sector is allowed, inside it is dropped
+    d = 0x0F;
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, &d, 1);
+    issue_cmd(&s, SPI_CMD_PP, 0x1000, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    issue_cmd(&s, SPI_CMD_READ, 0x1000, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0x05);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, &d, 1);
+    issue_cmd(&s, SPI_CMD_PP, 0x0010, 1);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, 0);
+    // no second erase, no nested suspend
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_sector_erase(&f, 0x1000), (uint32_t)-2);
+    ASSERT_EQ_U32(flash_sim_suspend(&f), (uint32_t)-2);
+    issue_cmd(&s, SPI_CMD_RESUME, 0, 0);
+    ASSERT_EQ_U8(flash_sim_rdsr2(&f), 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, FLASH_SIM_STATUS_WIP);
+    ASSERT_EQ_U32(f.busy_ticks, 54);
+    axi_spi_tick(&s, 54);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, 0);
+    ASSERT_EQ_U32(flash_sim_resume(&f), (uint32_t)-2);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 15: deep power-down ignores commands until 0xAB plus the wake latency
+TEST_CASE(test_deep_power_down) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    f.cfg.wake_ticks = 5;
+    issue_cmd(&s, SPI_CMD_DP, 0, 0);
+    ASSERT_EQ_U8(f.powered_down, 1);
+    issue_cmd(&s, SPI_CMD_RDSR, 0, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF); // DO not driven
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WEL, 0);
+    issue_cmd(&s, SPI_CMD_READ, 0, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    issue_cmd(&s, SPI_CMD_RDP, 0, 0);
+    ASSERT_EQ_U8(f.powered_down, 0);
+    // still waking
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WEL, 0);
+    axi_spi_tick(&s, 5);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WEL, FLASH_SIM_STATUS_WEL);
+    // DP is ignored while busy
+    issue_cmd(&s, SPI_CMD_SE, 0, 0);
+    issue_cmd(&s, SPI_CMD_DP, 0, 0);
+    ASSERT_EQ_U8(f.powered_down, 0);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 16: 0x66/0x99 reset aborts the running erase and clears WEL; 0x99 alone is ignored
+TEST_CASE(test_software_reset) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    issue_cmd(&s, SPI_CMD_EN4B, 0, 0);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    issue_cmd(&s, SPI_CMD_SE, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, FLASH_SIM_STATUS_WIP);
+    issue_cmd(&s, SPI_CMD_RST, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, FLASH_SIM_STATUS_WIP);
+    issue_cmd(&s, SPI_CMD_RSTEN, 0, 0);
+    issue_cmd(&s, SPI_CMD_RDSR, 0, 1); // any command in between cancels the enable
+    (void)axi_spi_read(&s, REG_SPI_DOUT);
+    issue_cmd(&s, SPI_CMD_RST, 0, 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WIP, FLASH_SIM_STATUS_WIP);
+    issue_cmd(&s, SPI_CMD_RSTEN, 0, 0);
+    issue_cmd(&s, SPI_CMD_RST, 0, 0);
+    ASSERT_EQ_U8(f.status & (FLASH_SIM_STATUS_WIP | FLASH_SIM_STATUS_WEL), 0);
+    ASSERT_EQ_U32(f.busy_ticks, 0);
+    ASSERT_EQ_U8(f.addr4, 0);
+    // a suspended erase is dropped as well
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    issue_cmd(&s, SPI_CMD_SE, 0x1000, 0);
+    issue_cmd(&s, SPI_CMD_SUSPEND, 0, 0);
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    issue_cmd(&s, SPI_CMD_RSTEN, 0, 0);
+    issue_cmd(&s, SPI_CMD_RST, 0, 0);
+    ASSERT_EQ_U8(flash_sim_rdsr2(&f), 0);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WEL, 0);
+    ASSERT_EQ_U32(flash_sim_resume(&f), (uint32_t)-2);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+// Test 17: security registers: wrap inside a register, lock bits are set-only
+TEST_CASE(test_otp_registers) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 16384);
+    uint8_t id[4] = {0x12, 0x34, 0x56, 0x78}, out[4] = {0};
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    write_bytes(&s, id, 4);
+    issue_cmd(&s, SPI_CMD_OTP_PROG, 0x20FE, 4); // register 2, wraps to offset 0
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    ASSERT_EQ_U8(f.otp[1][0xFE], 0x12);
+    ASSERT_EQ_U8(f.otp[1][0x01], 0x78);
+    axi_spi_write(&s, REG_SPI_FMT, 8);
+    issue_cmd(&s, SPI_CMD_OTP_READ, 0x20FE, 4);
+    read_bytes(&s, out, 4);
+    ASSERT_MEMEQ(out, id, 4);
+    axi_spi_write(&s, REG_SPI_FMT, 0);
+    // main array untouched
+    issue_cmd(&s, SPI_CMD_READ, 0x20FE, 1);
+    ASSERT_EQ_U8(axi_spi_read(&s, REG_SPI_DOUT), 0xFF);
+    // lock register 2: program and erase are dropped, the bit cannot be cleared
+    issue_cmd(&s, SPI_CMD_WREN, 0, 0);
+    uint8_t sr2 = FLASH_SIM_STATUS2_LB2;
+    write_bytes(&s, &sr2, 1);
+    issue_cmd(&s, SPI_CMD_WRSR2, 0, 1);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_otp_erase(&f, 0x2000), (uint32_t)-5);
+    ASSERT_EQ_U8(f.status & FLASH_SIM_STATUS_WEL, 0);
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_wrsr2(&f, 0), 0);
+    for (int i = 0; i < 10; ++i) axi_spi_tick(&s, 1);
+    ASSERT_EQ_U8(flash_sim_rdsr2(&f), FLASH_SIM_STATUS2_LB2);
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_reset(&f), (uint32_t)-3);
+    flash_sim_reset_enable(&f);
+    ASSERT_EQ_U32(flash_sim_reset(&f), 0);
+    ASSERT_EQ_U8(flash_sim_rdsr2(&f), FLASH_SIM_STATUS2_LB2);
+    // other registers still work
+    flash_sim_wren(&f);
+    ASSERT_EQ_U32(flash_sim_otp_erase(&f, 0x3000), 0);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
 // Driver-based tests (top-level)
 TEST_CASE(drv_rdsr_after_reset) {
     FlashSim f; AxiSpiSim s; setup(&f, &s);
@@ -244,6 +525,212 @@ TEST_CASE(drv_sector_erase) {
     axi_spi_sim_free(&s); flash_sim_free(&f);
 }
 
+TEST_CASE(drv_4byte_addr_no_alias) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, MEM_32M);
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = MEM_32M; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    ASSERT_EQ_U8(ctx.cfg.addr_bytes, 4); // auto-selected for > 16 MiB
+    const uint32_t hi = MEM_32M - 0x1000 + 0x10;
+    const uint8_t data[4] = {0x01, 0x23, 0x45, 0x67};
+    uint8_t out[4] = {0};
+    ASSERT_EQ_U32(flash_ll_program(&ctx, hi, data, 4), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, hi, out, 4), 0);
+    ASSERT_MEMEQ(out, data, 4);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, hi & 0xFFFFFFu, out, 4), 0);
+    ASSERT_EQ_U8(out[0], 0xFF); ASSERT_EQ_U8(out[3], 0xFF);
+    ASSERT_EQ_U32(flash_ll_sector_erase(&ctx, hi), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, hi, out, 4), 0);
+    ASSERT_EQ_U8(out[0], 0xFF);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_3byte_addr_rejects_large_part) {
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = MEM_32M; cfg.page_size = 256; cfg.sector_size = 4096; cfg.addr_bytes = 3;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), NULL), (uint32_t)FLASH_LL_EINVAL);
+    cfg.addr_bytes = 5;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), NULL), (uint32_t)FLASH_LL_EINVAL);
+}
+
+TEST_CASE(drv_quad_read_mode) {
+    FlashSim f; AxiSpiSim s; setup(&f, &s);
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 4096; cfg.page_size = 256; cfg.sector_size = 4096;
+    cfg.read_cmd = FLASH_LL_CMD_QREAD; cfg.read_dummy_cycles = 8;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    const uint8_t data[5] = {1, 2, 3, 4, 5};
+    uint8_t out[5] = {0};
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x300, data, 5), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0x300, out, 5), 0);
+    ASSERT_MEMEQ(out, data, 5);
+    ASSERT_EQ_U32(s.fmt, 0); // restored after the read
+    ASSERT_EQ_U32(flash_ll_set_read_mode(&ctx, FLASH_LL_CMD_FAST_READ, 8), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0x300, out, 5), 0);
+    ASSERT_MEMEQ(out, data, 5);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_read_mode_validation) {
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 4096; cfg.page_size = 256; cfg.sector_size = 4096;
+    cfg.read_cmd = 0x99;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), NULL), (uint32_t)FLASH_LL_EINVAL);
+    cfg.read_cmd = 0;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), NULL), 0);
+    ASSERT_EQ_U8(ctx.cfg.read_cmd, FLASH_LL_CMD_READ);
+    ASSERT_EQ_U32(flash_ll_set_read_mode(&ctx, FLASH_LL_CMD_READ, 8), (uint32_t)FLASH_LL_EINVAL);
+    ASSERT_EQ_U32(flash_ll_set_read_mode(&ctx, FLASH_LL_CMD_PP, 0), (uint32_t)FLASH_LL_EINVAL);
+}
+
+TEST_CASE(drv_block_protect) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    uint8_t d = 0x42, out = 0;
+    uint32_t ps, pe;
+    ASSERT_EQ_U32(flash_ll_wrsr(&ctx, 6u << FLASH_LL_SR_BP_SHIFT), 0); // top half
+    flash_ll_protected_range(&ctx, 6u << FLASH_LL_SR_BP_SHIFT, &ps, &pe);
+    ASSERT_EQ_U32(ps, 4096); ASSERT_EQ_U32(pe, 8192);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x1000, &d, 1), (uint32_t)FLASH_LL_EPROT);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x0FFF, (const uint8_t*)"\0\0", 2), (uint32_t)FLASH_LL_EPROT);
+    ASSERT_EQ_U32(flash_ll_sector_erase(&ctx, 0x1800), (uint32_t)FLASH_LL_EPROT);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x0010, &d, 1), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0x0010, &out, 1), 0);
+    ASSERT_EQ_U8(out, 0x42);
+    ASSERT_EQ_U32(flash_ll_wrsr(&ctx, 0), 0);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x1000, &d, 1), 0);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_erase_suspend_resume) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    uint8_t d = 0x3C, out = 0, sr2 = 0;
+    ASSERT_EQ_U32(flash_ll_suspend(&ctx), 0); // idle: nothing to suspend
+    ASSERT_EQ_U32(flash_ll_sector_erase_start(&ctx, 0x0000), 0);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x1000, &d, 1), (uint32_t)FLASH_LL_EBUSY);
+    ASSERT_EQ_U32(flash_ll_suspend(&ctx), 1);
+    ASSERT_EQ_U32(flash_ll_rdsr2(&ctx, &sr2), 0);
+    ASSERT_EQ_U8(sr2 & FLASH_LL_SR2_SUS, FLASH_LL_SR2_SUS);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x1000, &d, 1), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0x1000, &out, 1), 0);
+    ASSERT_EQ_U8(out, 0x3C);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x0100, &d, 1), (uint32_t)FLASH_LL_EBUSY);
+    ASSERT_EQ_U32(flash_ll_sector_erase(&ctx, 0x1000), (uint32_t)FLASH_LL_EBUSY);
+    // the device would drop OTP writes: refused, WEL left clear
+    ASSERT_EQ_U32(flash_ll_otp_program(&ctx, 0, 0, &d, 1), (uint32_t)FLASH_LL_EBUSY);
+    ASSERT_EQ_U32(flash_ll_otp_erase(&ctx, 0), (uint32_t)FLASH_LL_EBUSY);
+    uint8_t st = 0;
+    ASSERT_EQ_U32(flash_ll_rdsr(&ctx, &st), 0);
+    ASSERT_EQ_U8(st & FLASH_LL_SR_WEL, 0);
+    ASSERT_EQ_U32(flash_ll_resume(&ctx), 0);
+    ASSERT_EQ_U32(flash_ll_wait_busy(&ctx, 1000), 0);
+    ASSERT_EQ_U32(flash_ll_rdsr2(&ctx, &sr2), 0);
+    ASSERT_EQ_U8(sr2, 0);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0x0100, &d, 1), 0);
+    ASSERT_EQ_U32(flash_ll_otp_program(&ctx, 0, 0, &d, 1), 0);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 0, 0, &out, 1), 0);
+    ASSERT_EQ_U8(out, 0x3C);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_power_down_and_reset) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    f.cfg.wake_ticks = 20;
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    uint8_t d = 0x77, out = 0, st = 0;
+    ASSERT_EQ_U32(flash_ll_deep_power_down(&ctx), 0);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0, &d, 1), (uint32_t)FLASH_LL_ESLEEP);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0, &out, 1), (uint32_t)FLASH_LL_ESLEEP);
+    ASSERT_EQ_U32(flash_ll_reset(&ctx), (uint32_t)FLASH_LL_ESLEEP);
+    ASSERT_EQ_U32(flash_ll_release_power_down(&ctx), 0);
+    ASSERT_EQ_U32(flash_sim_awake(&f), 1);
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0, &d, 1), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0, &out, 1), 0);
+    ASSERT_EQ_U8(out, 0x77);
+    // power-down is refused mid-erase, reset aborts the erase
+    ASSERT_EQ_U32(flash_ll_sector_erase_start(&ctx, 0x1000), 0);
+    ASSERT_EQ_U32(flash_ll_deep_power_down(&ctx), (uint32_t)FLASH_LL_EBUSY);
+    ASSERT_EQ_U32(flash_ll_wren(&ctx), 0);
+    ASSERT_EQ_U32(flash_ll_reset(&ctx), 0);
+    ASSERT_EQ_U32(flash_ll_rdsr(&ctx, &st), 0);
+    ASSERT_EQ_U8(st & (FLASH_LL_SR_WIP | FLASH_LL_SR_WEL), 0);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_otp_provisioning) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192);
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    const uint8_t serial[8] = {'S', 'N', '0', '0', '4', '2', 0, 1};
+    uint8_t out[8] = {0};
+    ASSERT_EQ_U32(flash_ll_otp_program(&ctx, 0, 0x10, serial, 8), 0);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 0, 0x10, out, 8), 0);
+    ASSERT_MEMEQ(out, serial, 8);
+    ASSERT_EQ_U32(flash_ll_otp_lock(&ctx, 0), 0);
+    ASSERT_EQ_U32(flash_ll_otp_program(&ctx, 0, 0x40, serial, 1), (uint32_t)FLASH_LL_EPROT);
+    ASSERT_EQ_U32(flash_ll_otp_erase(&ctx, 0), (uint32_t)FLASH_LL_EPROT);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 0, 0x10, out, 8), 0);
+    ASSERT_MEMEQ(out, serial, 8);
+    ASSERT_EQ_U32(flash_ll_otp_program(&ctx, 1, 0, serial, 8), 0);
+    ASSERT_EQ_U32(flash_ll_otp_erase(&ctx, 1), 0);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 1, 0, out, 1), 0);
+    ASSERT_EQ_U8(out[0], 0xFF);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 3, 0, out, 1), (uint32_t)FLASH_LL_EINVAL);
+    ASSERT_EQ_U32(flash_ll_otp_read(&ctx, 0, 0xF8, out, 9), (uint32_t)FLASH_LL_EOOB);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+TEST_CASE(drv_read_larger_than_fifo) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192); // RX FIFO is 1024 bytes
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    static uint8_t img[8192], out[8192];
+    for (size_t i = 0; i < sizeof(img); ++i) img[i] = (uint8_t)(i * 7 + (i >> 8));
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0, img, sizeof(img)), 0);
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 0, out, sizeof(out)), 0);
+    ASSERT_MEMEQ(out, img, sizeof(img));
+    // fast read streams the same way
+    ASSERT_EQ_U32(flash_ll_set_read_mode(&ctx, FLASH_LL_CMD_QREAD, 8), 0);
+    memset(out, 0, sizeof(out));
+    ASSERT_EQ_U32(flash_ll_read(&ctx, 100, out, 5000), 0);
+    ASSERT_MEMEQ(out, img + 100, 5000);
+    ASSERT_EQ_U32((uint32_t)s.rx.count, 0);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
+static void count_poll(void *arg) { ++*(uint32_t *)arg; }
+
+TEST_CASE(drv_timeouts_and_poll_yield) {
+    FlashSim f; AxiSpiSim s; setup_sized(&f, &s, 8192); // erase busy for 64 ticks
+    FlashLlCtx ctx; FlashLlConfig cfg = {0};
+    cfg.mem_size = 8192; cfg.page_size = 256; cfg.sector_size = 4096;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    ASSERT_EQ_U32(ctx.cfg.erase_timeout, FLASH_LL_DEFAULT_ERASE_TIMEOUT);
+    ASSERT_EQ_U32(ctx.cfg.fifo_slack, FLASH_LL_DEFAULT_FIFO_SLACK);
+
+    uint32_t polls = 0;
+    cfg.erase_timeout = 10;
+    cfg.poll_yield = count_poll; cfg.poll_yield_arg = &polls;
+    ASSERT_EQ_U32(flash_ll_init(&ctx, &cfg, flash_ll_axi_sim_ops(), &s), 0);
+    ASSERT_EQ_U32(ctx.cfg.program_timeout, FLASH_LL_DEFAULT_PROGRAM_TIMEOUT);
+    ASSERT_EQ_U32(flash_ll_sector_erase(&ctx, 0), (uint32_t)FLASH_LL_ETIME);
+    ASSERT_EQ_U32(polls, 10);
+    ASSERT_EQ_U32(flash_ll_wait_busy(&ctx, 100), 0);
+    uint8_t d = 0x5A;
+    ASSERT_EQ_U32(flash_ll_program(&ctx, 0, &d, 1), 0); // 4 ticks busy
+    ASSERT_EQ_U32(polls > 10 && polls <= 10 + 64 + 4, 1);
+    axi_spi_sim_free(&s); flash_sim_free(&f);
+}
+
 int main(int argc, char **argv) {
     (void)argc; (void)argv;
     RUN_TEST(test_rdsr_after_reset);
@@ -254,11 +741,30 @@ int main(int argc, char **argv) {
     RUN_TEST(test_reprogram_and);
     RUN_TEST(test_sector_erase);
     RUN_TEST(test_erase_without_wren);
+    RUN_TEST(test_4byte_opcodes_no_alias);
+    RUN_TEST(test_en4b_ex4b_mode);
+    RUN_TEST(test_fast_read_dummy_cycles);
+    RUN_TEST(test_multi_io_read_lanes);
+    RUN_TEST(test_wrsr_block_protect);
+    RUN_TEST(test_erase_suspend_resume);
+    RUN_TEST(test_deep_power_down);
+    RUN_TEST(test_software_reset);
+    RUN_TEST(test_otp_registers);
     RUN_TEST(drv_rdsr_after_reset);
     RUN_TEST(drv_pp_and_readback);
     RUN_TEST(drv_pp_without_wren_is_handled);
     RUN_TEST(drv_page_boundary_respected);
     RUN_TEST(drv_sector_erase);
+    RUN_TEST(drv_4byte_addr_no_alias);
+    RUN_TEST(drv_3byte_addr_rejects_large_part);
+    RUN_TEST(drv_quad_read_mode);
+    RUN_TEST(drv_read_mode_validation);
+    RUN_TEST(drv_block_protect);
+    RUN_TEST(drv_erase_suspend_resume);
+    RUN_TEST(drv_power_down_and_reset);
+    RUN_TEST(drv_otp_provisioning);
+    RUN_TEST(drv_read_larger_than_fifo);
+    RUN_TEST(drv_timeouts_and_poll_yield);
 
     if (sim_test_failures) {
         fprintf(stderr, "\nTOTAL FAILURES: %d\n", sim_test_failures);
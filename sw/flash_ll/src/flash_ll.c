@@ -4,51 +4,117 @@
 #include <string.h>
 
 #define BIT(x) (1u << (x))
+#define ADDR3_LIMIT (1u << 24) // 16 MiB reachable with 3 address bytes
 
 static inline uint32_t rd(FlashLlCtx *c, uint32_t off){ return c->io_ops->read(c->io, off);} 
 static inline void wr(FlashLlCtx *c, uint32_t off, uint32_t v){ c->io_ops->write(c->io, off, v);} 
 static inline void tk(FlashLlCtx *c, uint32_t t){ if (c->io_ops->tick) c->io_ops->tick(c->io, t);} 
 
+// Data lanes encoding of SPI_FMT for a (3-byte) read opcode; -1 if not a read
+static int read_lanes(uint8_t cmd) {
+    switch (cmd) {
+        case FLASH_LL_CMD_READ:
+        case FLASH_LL_CMD_FAST_READ: return 0; // x1
+        case FLASH_LL_CMD_DREAD:     return 1; // x2
+        case FLASH_LL_CMD_QREAD:     return 2; // x4
+        default: return -1;
+    }
+}
+
+static uint8_t read_cmd4(uint8_t cmd) {
+    switch (cmd) {
+        case FLASH_LL_CMD_FAST_READ: return FLASH_LL_CMD_FAST_READ4;
+        case FLASH_LL_CMD_DREAD:     return FLASH_LL_CMD_DREAD4;
+        case FLASH_LL_CMD_QREAD:     return FLASH_LL_CMD_QREAD4;
+        default:                     return FLASH_LL_CMD_READ4;
+    }
+}
+
+static int check_read_mode(uint8_t read_cmd, uint8_t dummy_cycles) {
+    if (read_lanes(read_cmd) < 0) return FLASH_LL_EINVAL;
+    if (read_cmd == FLASH_LL_CMD_READ && dummy_cycles != 0) return FLASH_LL_EINVAL;
+    return FLASH_LL_OK;
+}
+
 int flash_ll_init(FlashLlCtx *ctx, const FlashLlConfig *cfg, const FlashLlIo *ops, void *io_backend) {
     if (!ctx || !cfg || !ops) return FLASH_LL_EINVAL;
     if (cfg->page_size == 0 || cfg->sector_size == 0 || cfg->mem_size == 0) return FLASH_LL_EINVAL;
+    uint8_t addr_bytes = cfg->addr_bytes;
+    if (addr_bytes == 0) addr_bytes = (cfg->mem_size > ADDR3_LIMIT) ? 4 : 3;
+    if (addr_bytes != 3 && addr_bytes != 4) return FLASH_LL_EINVAL;
+    if (addr_bytes == 3 && cfg->mem_size > ADDR3_LIMIT) return FLASH_LL_EINVAL;
+    uint8_t read_cmd = cfg->read_cmd ? cfg->read_cmd : FLASH_LL_CMD_READ;
+    if (check_read_mode(read_cmd, cfg->read_dummy_cycles) != FLASH_LL_OK) return FLASH_LL_EINVAL;
     ctx->cfg = *cfg;
+    ctx->cfg.addr_bytes = addr_bytes;
+    ctx->cfg.read_cmd = read_cmd;
+    if (!cfg->program_timeout) ctx->cfg.program_timeout = FLASH_LL_DEFAULT_PROGRAM_TIMEOUT;
+    if (!cfg->erase_timeout) ctx->cfg.erase_timeout = FLASH_LL_DEFAULT_ERASE_TIMEOUT;
+    if (!cfg->status_timeout) ctx->cfg.status_timeout = FLASH_LL_DEFAULT_STATUS_TIMEOUT;
+    if (!cfg->suspend_timeout) ctx->cfg.suspend_timeout = FLASH_LL_DEFAULT_SUSPEND_TIMEOUT;
+    if (!cfg->reset_timeout) ctx->cfg.reset_timeout = FLASH_LL_DEFAULT_RESET_TIMEOUT;
+    if (!cfg->fifo_slack) ctx->cfg.fifo_slack = FLASH_LL_DEFAULT_FIFO_SLACK;
     ctx->io_ops = ops;
     ctx->io = io_backend;
+    ctx->suspended = 0;
+    ctx->erase_addr = 0;
+    ctx->powered_down = 0;
     return FLASH_LL_OK;
 }
 
 static int start_cmd(FlashLlCtx *ctx, uint8_t cmd, uint32_t addr, uint32_t len) {
     wr(ctx, FLASH_LL_REG_SPI_CMD, cmd);
-    wr(ctx, FLASH_LL_REG_SPI_ADDR, addr & 0xFFFFFFu);
+    wr(ctx, FLASH_LL_REG_SPI_ADDR, (ctx->cfg.addr_bytes == 4) ? addr : (addr & 0xFFFFFFu));
     wr(ctx, FLASH_LL_REG_SPI_LEN, len);
     wr(ctx, FLASH_LL_REG_SPI_CTRL, BIT(0) | BIT(1)); // CS_EN | START
     return FLASH_LL_OK;
 }
 
+// Pick the dedicated 4-byte opcode when the part needs 4 address bytes, so
+// the driver never depends on the volatile EN4B/EX4B mode of the device.
+static uint8_t addr_cmd(const FlashLlCtx *ctx, uint8_t cmd3, uint8_t cmd4) {
+    return (ctx->cfg.addr_bytes == 4) ? cmd4 : cmd3;
+}
+
 static uint8_t rdsr_once(FlashLlCtx *ctx) {
     start_cmd(ctx, FLASH_LL_CMD_RDSR, 0, 1);
     return (uint8_t)rd(ctx, FLASH_LL_REG_SPI_DOUT);
 }
 
+static uint8_t rdsr2_once(FlashLlCtx *ctx) {
+    start_cmd(ctx, FLASH_LL_CMD_RDSR2, 0, 1);
+    return (uint8_t)rd(ctx, FLASH_LL_REG_SPI_DOUT);
+}
+
 int flash_ll_wren(FlashLlCtx *ctx) {
     if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     return start_cmd(ctx, FLASH_LL_CMD_WREN, 0, 0);
 }
 
 int flash_ll_rdsr(FlashLlCtx *ctx, uint8_t *status_out) {
     if (!ctx || !status_out) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     *status_out = rdsr_once(ctx);
     return FLASH_LL_OK;
 }
 
+int flash_ll_rdsr2(FlashLlCtx *ctx, uint8_t *status_out) {
+    if (!ctx || !status_out) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    *status_out = rdsr2_once(ctx);
+    return FLASH_LL_OK;
+}
+
 int flash_ll_wait_busy(FlashLlCtx *ctx, uint32_t max_ticks) {
     if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     while (1) {
         uint8_t st = rdsr_once(ctx);
         if ((st & 0x1u) == 0) return FLASH_LL_OK; // WIP cleared
         if (max_ticks == 0) return FLASH_LL_ETIME;
         tk(ctx, 1);
+        if (ctx->cfg.poll_yield) ctx->cfg.poll_yield(ctx->cfg.poll_yield_arg);
         max_ticks--;
     }
 }
@@ -59,14 +125,35 @@ static int check_oob(FlashLlCtx *ctx, uint32_t addr, uint32_t len) {
     return FLASH_LL_OK;
 }
 
+int flash_ll_set_read_mode(FlashLlCtx *ctx, uint8_t read_cmd, uint8_t dummy_cycles) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    int rc = check_read_mode(read_cmd, dummy_cycles);
+    if (rc != FLASH_LL_OK) return rc;
+    ctx->cfg.read_cmd = read_cmd;
+    ctx->cfg.read_dummy_cycles = dummy_cycles;
+    return FLASH_LL_OK;
+}
+
+static int read_data(FlashLlCtx *ctx, uint8_t cmd, uint32_t addr, void *buf, size_t len, uint32_t fmt);
+
 int flash_ll_read(FlashLlCtx *ctx, uint32_t addr, void *buf, size_t len) {
     if (!ctx || !buf || len == 0) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     int rc = check_oob(ctx, addr, (uint32_t)len);
     if (rc != FLASH_LL_OK) return rc;
-    start_cmd(ctx, FLASH_LL_CMD_READ, addr, (uint32_t)len);
+    uint8_t cmd = ctx->cfg.read_cmd;
+    uint32_t fmt = (uint32_t)ctx->cfg.read_dummy_cycles | ((uint32_t)read_lanes(cmd) << 8);
+    return read_data(ctx, addr_cmd(ctx, cmd, read_cmd4(cmd)), addr, buf, len, fmt);
+}
+
+// Issue a read-type command and drain LEN bytes from DOUT. SPI_FMT only
+// applies to this transfer; plain READ keeps the reset value (x1, no dummy).
+static int read_data(FlashLlCtx *ctx, uint8_t cmd, uint32_t addr, void *buf, size_t len, uint32_t fmt) {
+    if (fmt) wr(ctx, FLASH_LL_REG_SPI_FMT, fmt);
+    start_cmd(ctx, cmd, addr, (uint32_t)len);
     uint8_t *out = (uint8_t*)buf;
     size_t read_cnt = 0;
-    uint32_t budget = (uint32_t)(len * 8 + 1024); // generous budget
+    uint32_t budget = (uint32_t)(len * 8 + ctx->cfg.fifo_slack);
     while (read_cnt < len && budget--) {
         uint32_t st = rd(ctx, FLASH_LL_REG_SPI_STATUS);
         if (st & BIT(1)) { // RX_AVAIL
@@ -75,12 +162,14 @@ int flash_ll_read(FlashLlCtx *ctx, uint32_t addr, void *buf, size_t len) {
             tk(ctx, 1);
         }
     }
-    return (read_cnt == len) ? FLASH_LL_OK : FLASH_LL_EIO;
+    if (fmt) wr(ctx, FLASH_LL_REG_SPI_FMT, 0);
+    return (read_cnt == len) ? FLASH_LL_OK : FLASH_LL_ETIME; // FIFO budget ran out
 }
 
-static size_t tx_write_all(FlashLlCtx *ctx, const uint8_t *data, size_t len) {
+// Push LEN bytes into TX; FLASH_LL_ETIME if the FIFO budget runs out first
+static int tx_write_all(FlashLlCtx *ctx, const uint8_t *data, size_t len) {
     size_t sent = 0;
-    uint32_t budget = (uint32_t)(len * 8 + 1024);
+    uint32_t budget = (uint32_t)(len * 8 + ctx->cfg.fifo_slack);
     while (sent < len && budget--) {
         uint32_t st = rd(ctx, FLASH_LL_REG_SPI_STATUS);
         if (st & BIT(2)) { // TX_SPACE
@@ -89,13 +178,59 @@ static size_t tx_write_all(FlashLlCtx *ctx, const uint8_t *data, size_t len) {
             tk(ctx, 1);
         }
     }
-    return sent;
+    return (sent == len) ? FLASH_LL_OK : FLASH_LL_ETIME;
+}
+
+void flash_ll_protected_range(const FlashLlCtx *ctx, uint8_t status, uint32_t *start, uint32_t *end) {
+    // Same fractions as common NOR parts: BP=1..6 -> 1/64..1/2, BP=7 -> all
+    uint32_t mem = ctx->cfg.mem_size;
+    uint32_t bp = (status & FLASH_LL_SR_BP_MASK) >> FLASH_LL_SR_BP_SHIFT;
+    uint32_t size = (bp == 0) ? 0 : (bp == 7) ? mem : (mem >> (7 - bp));
+    if (size == 0) { *start = 0; *end = 0; }
+    else if (status & FLASH_LL_SR_TB) { *start = 0; *end = size; }
+    else { *start = mem - size; *end = mem; }
+}
+
+static int overlaps(uint32_t a, uint32_t len, uint32_t start, uint32_t end) {
+    return start < end && a < end && (uint64_t)a + len > start;
+}
+
+// The device silently drops PP/SE while busy, into the sector of a suspended
+// erase, or into protected blocks, so refuse them up front
+static int check_ready(FlashLlCtx *ctx, uint32_t addr, uint32_t len) {
+    uint8_t st = rdsr_once(ctx);
+    if (st & FLASH_LL_SR_WIP) return FLASH_LL_EBUSY;
+    if (ctx->suspended) {
+        uint32_t es = ctx->erase_addr - (ctx->erase_addr % ctx->cfg.sector_size);
+        if (overlaps(addr, len, es, es + ctx->cfg.sector_size)) return FLASH_LL_EBUSY;
+    }
+    uint32_t ps, pe;
+    flash_ll_protected_range(ctx, st, &ps, &pe);
+    if (overlaps(addr, len, ps, pe)) return FLASH_LL_EPROT;
+    return FLASH_LL_OK;
+}
+
+int flash_ll_wrsr(FlashLlCtx *ctx, uint8_t status) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    int rc = flash_ll_wren(ctx);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = tx_write_all(ctx, &status, 1);
+    if (rc != FLASH_LL_OK) return rc;
+    start_cmd(ctx, FLASH_LL_CMD_WRSR, 0, 1);
+    rc = flash_ll_wait_busy(ctx, ctx->cfg.status_timeout);
+    if (rc != FLASH_LL_OK) return rc;
+    const uint8_t mask = FLASH_LL_SR_BP_MASK | FLASH_LL_SR_TB;
+    return ((rdsr_once(ctx) & mask) == (status & mask)) ? FLASH_LL_OK : FLASH_LL_EIO;
 }
 
 int flash_ll_program(FlashLlCtx *ctx, uint32_t addr, const void *data, size_t len) {
     if (!ctx || !data || len == 0) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     int rc = check_oob(ctx, addr, (uint32_t)len);
     if (rc != FLASH_LL_OK) return rc;
+    rc = check_ready(ctx, addr, (uint32_t)len);
+    if (rc != FLASH_LL_OK) return rc;
     const uint8_t *p = (const uint8_t*)data;
     size_t remaining = len;
     while (remaining > 0) {
@@ -106,11 +241,11 @@ int flash_ll_program(FlashLlCtx *ctx, uint32_t addr, const void *data, size_t le
         rc = flash_ll_wren(ctx);
         if (rc != FLASH_LL_OK) return rc;
 
-        size_t sent = tx_write_all(ctx, p, chunk);
-        if (sent != chunk) return FLASH_LL_EIO;
+        rc = tx_write_all(ctx, p, chunk);
+        if (rc != FLASH_LL_OK) return rc;
 
-        start_cmd(ctx, FLASH_LL_CMD_PP, addr, chunk);
-        rc = flash_ll_wait_busy(ctx, 100000);
+        start_cmd(ctx, addr_cmd(ctx, FLASH_LL_CMD_PP, FLASH_LL_CMD_PP4), addr, chunk);
+        rc = flash_ll_wait_busy(ctx, ctx->cfg.program_timeout);
         if (rc != FLASH_LL_OK) return rc;
 
         addr += chunk; p += chunk; remaining -= chunk;
@@ -118,14 +253,145 @@ int flash_ll_program(FlashLlCtx *ctx, uint32_t addr, const void *data, size_t le
     return FLASH_LL_OK;
 }
 
-int flash_ll_sector_erase(FlashLlCtx *ctx, uint32_t addr) {
+int flash_ll_sector_erase_start(FlashLlCtx *ctx, uint32_t addr) {
     if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
     // align to sector base inside
     if (addr >= ctx->cfg.mem_size) return FLASH_LL_EOOB;
-    int rc = flash_ll_wren(ctx);
+    if (ctx->suspended) return FLASH_LL_EBUSY; // no erase while one is suspended
+    uint32_t base = addr - (addr % ctx->cfg.sector_size);
+    uint32_t n = ctx->cfg.sector_size;
+    if (n > ctx->cfg.mem_size - base) n = ctx->cfg.mem_size - base;
+    int rc = check_ready(ctx, base, n);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = flash_ll_wren(ctx);
     if (rc != FLASH_LL_OK) return rc;
-    start_cmd(ctx, FLASH_LL_CMD_SE, addr, 0);
-    rc = flash_ll_wait_busy(ctx, 1000000);
-    return rc;
+    start_cmd(ctx, addr_cmd(ctx, FLASH_LL_CMD_SE, FLASH_LL_CMD_SE4), addr, 0);
+    ctx->erase_addr = base;
+    return FLASH_LL_OK;
 }
 
+int flash_ll_sector_erase(FlashLlCtx *ctx, uint32_t addr) {
+    int rc = flash_ll_sector_erase_start(ctx, addr);
+    if (rc != FLASH_LL_OK) return rc;
+    return flash_ll_wait_busy(ctx, ctx->cfg.erase_timeout);
+}
+
+int flash_ll_suspend(FlashLlCtx *ctx) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    if (ctx->suspended) return 1;
+    if ((rdsr_once(ctx) & FLASH_LL_SR_WIP) == 0) return 0; // nothing running
+    start_cmd(ctx, FLASH_LL_CMD_SUSPEND, 0, 0);
+    int rc = flash_ll_wait_busy(ctx, ctx->cfg.suspend_timeout); // suspend latency
+    if (rc != FLASH_LL_OK) return rc;
+    // The op may have finished just before the suspend landed
+    ctx->suspended = (rdsr2_once(ctx) & FLASH_LL_SR2_SUS) ? 1 : 0;
+    return ctx->suspended;
+}
+
+int flash_ll_resume(FlashLlCtx *ctx) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    if (!ctx->suspended) return FLASH_LL_OK;
+    start_cmd(ctx, FLASH_LL_CMD_RESUME, 0, 0);
+    ctx->suspended = 0;
+    return FLASH_LL_OK;
+}
+
+int flash_ll_deep_power_down(FlashLlCtx *ctx) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_OK;
+    if (rdsr_once(ctx) & FLASH_LL_SR_WIP) return FLASH_LL_EBUSY;
+    start_cmd(ctx, FLASH_LL_CMD_DP, 0, 0);
+    ctx->powered_down = 1;
+    return FLASH_LL_OK;
+}
+
+int flash_ll_release_power_down(FlashLlCtx *ctx) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    start_cmd(ctx, FLASH_LL_CMD_RDP, 0, 0);
+    ctx->powered_down = 0;
+    // Status reads as all ones (WIP set) until the part is awake again
+    return flash_ll_wait_busy(ctx, ctx->cfg.reset_timeout);
+}
+
+int flash_ll_reset(FlashLlCtx *ctx) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    start_cmd(ctx, FLASH_LL_CMD_RSTEN, 0, 0);
+    start_cmd(ctx, FLASH_LL_CMD_RST, 0, 0);
+    ctx->suspended = 0;
+    return flash_ll_wait_busy(ctx, ctx->cfg.reset_timeout);
+}
+
+// Security register n lives at (n + 1) << 12 on Winbond-style parts
+static int otp_addr(uint8_t reg, uint32_t off, size_t len, uint32_t *addr) {
+    if (reg >= FLASH_LL_OTP_REGS) return FLASH_LL_EINVAL;
+    if (off >= FLASH_LL_OTP_SIZE || len > FLASH_LL_OTP_SIZE - off) return FLASH_LL_EOOB;
+    *addr = ((uint32_t)(reg + 1) << 12) | off;
+    return FLASH_LL_OK;
+}
+
+// The device drops OTP program/erase while busy or while an erase is suspended
+static int otp_check_unlocked(FlashLlCtx *ctx, uint8_t reg) {
+    if (ctx->suspended || (rdsr_once(ctx) & FLASH_LL_SR_WIP)) return FLASH_LL_EBUSY;
+    return (rdsr2_once(ctx) & (FLASH_LL_SR2_LB1 << reg)) ? FLASH_LL_EPROT : FLASH_LL_OK;
+}
+
+int flash_ll_otp_read(FlashLlCtx *ctx, uint8_t reg, uint32_t off, void *buf, size_t len) {
+    if (!ctx || !buf || len == 0) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    uint32_t addr;
+    int rc = otp_addr(reg, off, len, &addr);
+    if (rc != FLASH_LL_OK) return rc;
+    return read_data(ctx, FLASH_LL_CMD_OTP_READ, addr, buf, len, 8u); // x1, 8 dummy
+}
+
+int flash_ll_otp_program(FlashLlCtx *ctx, uint8_t reg, uint32_t off, const void *data, size_t len) {
+    if (!ctx || !data || len == 0) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    uint32_t addr;
+    int rc = otp_addr(reg, off, len, &addr);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = otp_check_unlocked(ctx, reg);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = flash_ll_wren(ctx);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = tx_write_all(ctx, (const uint8_t*)data, len);
+    if (rc != FLASH_LL_OK) return rc;
+    start_cmd(ctx, FLASH_LL_CMD_OTP_PROG, addr, (uint32_t)len);
+    return flash_ll_wait_busy(ctx, ctx->cfg.program_timeout);
+}
+
+int flash_ll_otp_erase(FlashLlCtx *ctx, uint8_t reg) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    uint32_t addr;
+    int rc = otp_addr(reg, 0, 0, &addr);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = otp_check_unlocked(ctx, reg);
+    if (rc != FLASH_LL_OK) return rc;
+    rc = flash_ll_wren(ctx);
+    if (rc != FLASH_LL_OK) return rc;
+    start_cmd(ctx, FLASH_LL_CMD_OTP_ERASE, addr, 0);
+    return flash_ll_wait_busy(ctx, ctx->cfg.erase_timeout);
+}
+
+int flash_ll_otp_lock(FlashLlCtx *ctx, uint8_t reg) {
+    if (!ctx) return FLASH_LL_EINVAL;
+    if (ctx->powered_down) return FLASH_LL_ESLEEP;
+    if (reg >= FLASH_LL_OTP_REGS) return FLASH_LL_EINVAL;
+    uint8_t lb = (uint8_t)(FLASH_LL_SR2_LB1 << reg);
+    uint8_t sr2 = rdsr2_once(ctx);
+    if (sr2 & lb) return FLASH_LL_OK;
+    int rc = flash_ll_wren(ctx);
+    if (rc != FLASH_LL_OK) return rc;
+    sr2 |= lb;
+    rc = tx_write_all(ctx, &sr2, 1);
+    if (rc != FLASH_LL_OK) return rc;
+    start_cmd(ctx, FLASH_LL_CMD_WRSR2, 0, 1);
+    rc = flash_ll_wait_busy(ctx, ctx->cfg.status_timeout);
+    if (rc != FLASH_LL_OK) return rc;
+    return (rdsr2_once(ctx) & lb) ? FLASH_LL_OK : FLASH_LL_EIO;
+}
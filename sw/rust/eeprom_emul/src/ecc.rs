@@ -0,0 +1,66 @@
+//! SECDED over 8-byte blocks: a Hamming(71,64) syndrome in the low 7 bits of
+//! each check byte plus an overall parity bit. A short last block is
+//! zero-padded.
+
+/// Hamming position (never a power of two) of each data bit of a block.
+const DATA_POS: [u8; 64] = {
+    let mut t = [0u8; 64];
+    let (mut i, mut p) = (0, 3u8);
+    while i < 64 {
+        if p & (p - 1) != 0 {
+            t[i] = p;
+            i += 1;
+        }
+        p += 1;
+    }
+    t
+};
+
+fn block(bytes: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(b)
+}
+
+fn syndrome(v: u64) -> u8 {
+    (0..64).filter(|i| v >> i & 1 == 1).fold(0, |s, i| s ^ DATA_POS[i])
+}
+
+fn check_byte(v: u64) -> u8 {
+    let s = syndrome(v);
+    s | ((((v.count_ones() + s.count_ones()) & 1) as u8) << 7)
+}
+
+/// Check bytes for `data`, one per 8-byte block.
+pub(crate) fn encode(data: &[u8]) -> Vec<u8> {
+    data.chunks(8).map(|c| check_byte(block(c))).collect()
+}
+
+pub(crate) fn check_len(len: usize) -> usize {
+    len.div_ceil(8)
+}
+
+/// Correct `data` in place against its check bytes. Returns the number of
+/// flipped bits fixed (data or check), or `None` if a block has two or more.
+pub(crate) fn correct(data: &mut [u8], check: &[u8]) -> Option<u32> {
+    let mut fixed = 0;
+    for (chunk, &c) in data.chunks_mut(8).zip(check) {
+        let v = block(chunk);
+        let s = syndrome(v) ^ (c & 0x7F);
+        let odd = (v.count_ones() + c.count_ones()) & 1 == 1;
+        match (s, odd) {
+            (0, false) => continue,
+            // the error is in a check bit
+            (0, true) => {}
+            (s, true) if s.is_power_of_two() => {}
+            (s, true) => {
+                let i = DATA_POS.iter().position(|&p| p == s)?;
+                if i >= chunk.len() * 8 { return None; }
+                chunk[i / 8] ^= 1 << (i % 8);
+            }
+            (_, false) => return None,
+        }
+        fixed += 1;
+    }
+    Some(fixed)
+}
@@ -1,23 +1,33 @@
 use anyhow::{anyhow, Result};
-use flash_core::Flash;
+use flash_core::{verify, Flash, FlashError};
+
+mod ecc;
+mod mirror;
+
+pub use mirror::{Mirror, Side};
 
 const SECTOR_MAGIC: u32 = 0xEE5EC007; // arbitrary non-FF marker
-const REC_MAGIC: u32 = 0xEE4C0A11;    // arbitrary non-FF marker
+const REC_MAGIC: u32 = 0xEE4C0A12;    // arbitrary non-FF marker
+// Records from before generations, with the sector seq where the generation
+// now is; they replay without moving the generation
+const LEGACY_REC_MAGIC: u32 = 0xEE4C0A11;
+// Sector flags of an ECC sector; matched within one bit so a flip can't hide it
+const ECC_MARK: u32 = 0x0ECC_ECC0;
 
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 struct SectorHeader {
     magic: u32,
     seq: u32,
-    reserved0: u32,
-    reserved1: u32,
+    flags: u32,
+    reserved1: u32, // ECC sectors: check byte of magic+seq in the low byte
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 struct RecHeader {
     magic: u32,
-    seq: u32,
+    generation: u32, // writes since format; a snapshot carries the latest
     addr: u32,
     len: u32,
     crc32: u32,
@@ -38,110 +48,397 @@ fn crc32(bytes: &[u8]) -> u32 {
 
 fn pad4(x: usize) -> usize { (x + 3) & !3 }
 
+const SECTOR_HDR: u32 = core::mem::size_of::<SectorHeader>() as u32;
+const REC_HDR: usize = core::mem::size_of::<RecHeader>();
+// ECC sectors follow each record header with 3 check bytes and a pad byte
+const REC_HDR_ECC: usize = REC_HDR + 4;
+
+/// Flash bytes taken by a record of `len` data bytes.
+fn rec_len(ecc: bool, len: usize) -> usize {
+    if ecc { pad4(REC_HDR_ECC + len + ecc::check_len(len)) } else { pad4(REC_HDR + len) }
+}
+
+/// Header and body bytes of a record, check bytes included for `ecc`.
+fn encode_record(ecc: bool, generation: u32, addr: u32, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut hdr = RecHeader { magic: REC_MAGIC, generation, addr, len: data.len() as u32, crc32: 0 };
+    let mut hb = [0u8; REC_HDR];
+    write_rec_header_bytes(&hdr, &mut hb);
+    let mut check = vec![]; check.extend_from_slice(&hb); check.extend_from_slice(data);
+    hdr.crc32 = crc32(&check);
+    write_rec_header_bytes(&hdr, &mut hb);
+    let (mut head, mut body) = (hb.to_vec(), data.to_vec());
+    if ecc {
+        head.extend(ecc::encode(&hb));
+        head.push(0xFF);
+        body.extend(ecc::encode(data));
+    }
+    (head, body)
+}
+
+/// Settings for `Eeprom::new_with_options`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// SECDED check bytes on sector headers and records (from the next format
+    /// or compaction): a single-bit read error per 8 bytes is corrected at
+    /// replay instead of ending the log there.
+    pub ecc: bool,
+    /// Sectors in the pool from `base`; 0 means 2. Spare sectors keep the
+    /// log going when others are retired as bad.
+    pub sectors: u32,
+}
+
+/// Pool health, from `Eeprom::status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Status {
+    pub sectors: u32,
+    /// Base addresses of the sectors retired as bad.
+    pub retired: Vec<u32>,
+    /// Base address of the sector holding the log.
+    pub active: u32,
+}
+
+impl Status {
+    /// Running on fewer sectors than configured.
+    pub fn degraded(&self) -> bool { !self.retired.is_empty() }
+}
+
+/// Compaction found no usable sector besides the active one. Reads still
+/// work; writes fail once the active sector is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoSpareSector;
+
+impl std::fmt::Display for NoSpareSector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no spare sector left in the pool")
+    }
+}
+
+impl std::error::Error for NoSpareSector {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Slot {
+    /// Erased (or being erased) since it last held data.
+    Blank,
+    Used,
+    /// Failed an erase, blank check or program; the header is zeroed on flash.
+    Retired,
+}
+
+/// Errors that mean the sector can't be trusted, as opposed to e.g. power loss.
+fn is_sector_fault(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<FlashError>(), Some(FlashError::EraseFailed { .. } | FlashError::VerifyFailed { .. }))
+}
+
 pub struct Eeprom<F: Flash> {
     flash: F,
     base: u32,
     sector_size: u32,
     size: u32, // logical EEPROM size (bytes)
-    active_base: u32,
-    scratch_base: u32,
+    active: usize, // index of the log sector in `sectors`
+    sectors: Vec<Slot>,
     seq: u32,
+    generation: u32,
     state: Vec<u8>,
     wptr: u32, // write pointer within active sector
+    erase_pending: bool, // background erase of the previous sector may still be running
+    options: Options,
+    active_ecc: bool, // records in the active sector carry ECC
+    corrected: u32,
 }
 
 impl<F: Flash> Eeprom<F> {
     pub fn new_with_flash(flash: F, base: u32, sector_size: u32, size: u32) -> Result<Self> {
-        if size == 0 || sector_size == 0 || (size as u64) > (sector_size as u64 * 2) {
-            return Err(anyhow!("invalid sizes"));
+        Self::new_with_options(flash, base, sector_size, size, Options::default())
+    }
+
+    pub fn new_with_options(flash: F, base: u32, sector_size: u32, size: u32, options: Options) -> Result<Self> {
+        Self::open(flash, base, sector_size, size, options).map_err(|(_, e)| e)
+    }
+
+    /// `new_with_options` that hands the flash back if the open fails.
+    pub(crate) fn open(flash: F, base: u32, sector_size: u32, size: u32, options: Options) -> Result<Self, (F, anyhow::Error)> {
+        let mut ee = Self::unopened(flash, base, sector_size, size, options)?;
+        match ee.init_or_format() {
+            Ok(()) => Ok(ee),
+            Err(e) => Err((ee.flash, e)),
         }
-        let mut ee = Eeprom {
+    }
+
+    /// Format over whatever the pool holds and start the log with a snapshot
+    /// of `state` at `generation` (a mirror copy that would not open).
+    pub(crate) fn rebuild(flash: F, base: u32, sector_size: u32, size: u32, options: Options, state: &[u8], generation: u32) -> Result<Self, (F, anyhow::Error)> {
+        let mut ee = Self::unopened(flash, base, sector_size, size, options)?;
+        ee.state.copy_from_slice(state);
+        ee.generation = generation;
+        let (head, body) = encode_record(options.ecc, generation, 0, state);
+        match ee.format(&head, &body) {
+            Ok(()) => {
+                ee.wptr = SECTOR_HDR + rec_len(options.ecc, state.len()) as u32;
+                Ok(ee)
+            }
+            Err(e) => Err((ee.flash, e)),
+        }
+    }
+
+    fn unopened(flash: F, base: u32, sector_size: u32, size: u32, options: Options) -> Result<Self, (F, anyhow::Error)> {
+        let sectors = if options.sectors == 0 { 2 } else { options.sectors };
+        if size == 0 || sector_size == 0 || (size as u64) > (sector_size as u64 * 2) || sectors < 2 {
+            return Err((flash, anyhow!("invalid sizes")));
+        }
+        Ok(Eeprom {
             flash,
             base,
             sector_size,
             size,
-            active_base: base,
-            scratch_base: base + sector_size,
+            active: 0,
+            sectors: vec![Slot::Used; sectors as usize],
             seq: 0,
+            generation: 0,
             state: vec![0xFF; size as usize],
             wptr: 0,
-        };
-        ee.init_or_format()?;
-        Ok(ee)
+            erase_pending: false,
+            options,
+            active_ecc: false,
+            corrected: 0,
+        })
     }
 
     fn read_exact(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
         self.flash.read(addr, buf)
     }
 
+    /// One program per page, so backends that hand programs straight to the
+    /// device (no split in a driver) don't wrap within a page.
     fn write_all(&mut self, addr: u32, data: &[u8]) -> Result<()> {
-        self.flash.program(addr, data)
+        let page = self.flash.geometry().page_size;
+        let mut off = 0;
+        while off < data.len() {
+            let a = addr + off as u32;
+            let n = ((page - a % page) as usize).min(data.len() - off);
+            self.program_page(a, &data[off..off + n])?;
+            off += n;
+        }
+        Ok(())
+    }
+
+    fn program_page(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        if !self.erase_pending { return self.program_verified(addr, data); }
+        // Program around the background erase of the previous sector
+        if self.flash.suspend()? {
+            let r = self.program_verified(addr, data);
+            self.flash.resume()?;
+            return r;
+        }
+        self.wait_erase()?;
+        self.program_verified(addr, data)
+    }
+
+    /// Program and read back; a mismatch is `FlashError::VerifyFailed`.
+    fn program_verified(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.flash.program(addr, data)?;
+        self.read_back("program", addr, data)
+    }
+
+    /// Whole sector reads 0xFF, e.g. no bits stuck at 0 after an erase.
+    fn blank_check(&mut self, base: u32) -> Result<()> {
+        self.read_back("blank_check", base, &vec![0xFF; self.sector_size as usize])
+    }
+
+    /// A mismatch is read again before it counts: a sector that fails
+    /// verification is retired for good, a read flip is not a reason to.
+    fn read_back(&mut self, op: &'static str, addr: u32, expected: &[u8]) -> Result<()> {
+        match verify::read_back(&mut self.flash, op, addr, expected, 1)? {
+            None => Ok(()),
+            Some(e) => Err(e.into()),
+        }
+    }
+
+    fn sector_base(&self, i: usize) -> u32 {
+        self.base + i as u32 * self.sector_size
+    }
+
+    /// Drop sector `i` from the pool and zero its header, which programs
+    /// over anything and marks it bad for later opens. Best effort: if the
+    /// marker doesn't take, the sector fails again next time it is tried.
+    fn retire(&mut self, i: usize) -> Result<()> {
+        self.sectors[i] = Slot::Retired;
+        let base = self.sector_base(i);
+        match self.program_page(base, &[0; SECTOR_HDR as usize]) {
+            Err(e) if !is_sector_fault(&e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Next usable sector after `after` (round robin for wear), or the
+    /// first one of the pool with no log to move on from.
+    fn next_spare(&self, after: Option<usize>) -> Option<usize> {
+        let n = self.sectors.len();
+        let (first, count) = after.map_or((0, n), |a| (a + 1, n - 1));
+        (0..count).map(|k| (first + k) % n).find(|&i| self.sectors[i] != Slot::Retired)
+    }
+
+    /// Erase sector `i` unless it is known blank, blank-check it and start a
+    /// log there: `head` and `body` of a snapshot record (if any), then the header.
+    fn start_sector(&mut self, i: usize, seq: u32, head: &[u8], body: &[u8]) -> Result<()> {
+        let base = self.sector_base(i);
+        if self.sectors[i] != Slot::Blank { self.erase_sector(base)?; }
+        self.sectors[i] = Slot::Used;
+        self.blank_check(base)?;
+        self.write_all(base + SECTOR_HDR, head)?;
+        self.write_all(base + SECTOR_HDR + head.len() as u32, body)?;
+        // Header last, so power loss never leaves a valid header over an
+        // incomplete snapshot
+        self.write_sector_header(base, seq)
+    }
+
+    /// `start_sector` on the next spare, retiring the ones that fail.
+    fn start_spare(&mut self, after: Option<usize>, seq: u32, head: &[u8], body: &[u8]) -> Result<usize> {
+        loop {
+            let i = self.next_spare(after).ok_or(NoSpareSector)?;
+            match self.start_sector(i, seq, head, body) {
+                Ok(()) => return Ok(i),
+                Err(e) if is_sector_fault(&e) => self.retire(i)?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn wait_erase(&mut self) -> Result<()> {
+        while self.erase_pending {
+            self.erase_pending = self.flash.is_busy()?;
+        }
+        Ok(())
     }
 
     fn erase_sector(&mut self, base: u32) -> Result<()> {
+        self.invalidate_header(base)?;
         self.flash.sector_erase(base)
     }
 
+    /// Zero the magic of a valid header before its sector is erased. An
+    /// interrupted erase only sets bits, so it could otherwise leave the
+    /// magic intact over a seq raised past the active sector's.
+    fn invalidate_header(&mut self, base: u32) -> Result<()> {
+        let mut hb = [0u8; SECTOR_HDR as usize];
+        self.read_exact(base, &mut hb)?;
+        if parse_sector_header(&hb).is_none() { return Ok(()); }
+        self.program_page(base, &[0; 4])
+    }
+
+    /// Magic goes last: a header is only valid once its seq is complete.
+    fn write_sector_header(&mut self, base: u32, seq: u32) -> Result<()> {
+        let mut sh = SectorHeader { magic: SECTOR_MAGIC, seq, flags: 0, reserved1: 0 };
+        let mut hb = vec![0u8; SECTOR_HDR as usize];
+        if self.options.ecc {
+            write_sector_header_bytes(&sh, &mut hb);
+            sh.flags = ECC_MARK;
+            sh.reserved1 = ecc::encode(&hb[..8])[0] as u32;
+        }
+        write_sector_header_bytes(&sh, &mut hb);
+        self.write_all(base + 4, &hb[4..])?;
+        self.write_all(base, &hb[..4])
+    }
+
     fn init_or_format(&mut self) -> Result<()> {
-        // Read both sector headers
-        let mut buf = vec![0u8; core::mem::size_of::<SectorHeader>()];
-        self.read_exact(self.base, &mut buf)?;
-        let sh_a = parse_sector_header(&buf);
-        self.read_exact(self.base + self.sector_size, &mut buf)?;
-        let sh_b = parse_sector_header(&buf);
-
-        let (active_base, scratch_base, seq) = match (sh_a, sh_b) {
-            (Some(a), Some(b)) => {
-                if a.seq >= b.seq { (self.base, self.base + self.sector_size, a.seq) }
-                else { (self.base + self.sector_size, self.base, b.seq) }
-            }
-            (Some(a), None) => (self.base, self.base + self.sector_size, a.seq),
-            (None, Some(b)) => (self.base + self.sector_size, self.base, b.seq),
-            (None, None) => {
-                // Format: erase both, write header to A
-                self.erase_sector(self.base)?;
-                self.erase_sector(self.base + self.sector_size)?;
-                let hdr = SectorHeader { magic: SECTOR_MAGIC, seq: 1, reserved0: 0, reserved1: 0 };
-                let mut hb = vec![0u8; core::mem::size_of::<SectorHeader>()];
-                write_sector_header_bytes(&hdr, &mut hb);
-                self.write_all(self.base, &hb)?;
-                (self.base, self.base + self.sector_size, 1)
+        // Read every sector header: the valid one with the highest seq holds
+        // the log, zeroed ones are retired
+        let mut buf = vec![0u8; SECTOR_HDR as usize];
+        let mut found: Option<(usize, SectorHeader)> = None;
+        for i in 0..self.sectors.len() {
+            self.read_exact(self.sector_base(i), &mut buf)?;
+            if buf.iter().all(|&b| b == 0) {
+                self.sectors[i] = Slot::Retired;
+            } else if let Some(h) = parse_sector_header(&buf) {
+                if found.is_none_or(|(_, f)| h.seq > f.seq) { found = Some((i, h)); }
             }
-        };
+        }
 
-        self.active_base = active_base;
-        self.scratch_base = scratch_base;
-        self.seq = seq;
+        match found {
+            Some((i, h)) => {
+                self.active = i;
+                self.seq = h.seq;
+                self.active_ecc = h.flags == ECC_MARK;
+            }
+            None => self.format(&[], &[])?,
+        }
         self.replay_log()?;
+        // A record torn by power loss ends the log but its bytes are not
+        // blank; appending over them would corrupt the next record too
+        if !self.tail_blank()? { self.compact()?; }
+        Ok(())
+    }
+
+    /// Erase all, then the log with `head` and `body` of a snapshot record
+    /// (if any) and header to the first good sector.
+    fn format(&mut self, head: &[u8], body: &[u8]) -> Result<()> {
+        for i in 0..self.sectors.len() {
+            if self.sectors[i] == Slot::Retired { continue; }
+            match self.erase_sector(self.sector_base(i)) {
+                Ok(()) => self.sectors[i] = Slot::Blank,
+                Err(e) if is_sector_fault(&e) => self.retire(i)?,
+                Err(e) => return Err(e),
+            }
+        }
+        self.active = self.start_spare(None, 1, head, body)?;
+        self.seq = 1;
+        self.active_ecc = self.options.ecc;
         Ok(())
     }
 
+    fn tail_blank(&mut self) -> Result<bool> {
+        let mut buf = vec![0u8; (self.sector_size - self.wptr.min(self.sector_size)) as usize];
+        self.read_exact(self.sector_base(self.active) + self.wptr, &mut buf)?;
+        Ok(buf.iter().all(|&b| b == 0xFF))
+    }
+
     fn replay_log(&mut self) -> Result<()> {
+        let ecc = self.active_ecc;
+        let hdr_len = if ecc { REC_HDR_ECC } else { REC_HDR };
         // start after header
-        let mut off = core::mem::size_of::<SectorHeader>() as u32;
+        let mut off = SECTOR_HDR;
         self.state.fill(0xFF);
-        while (off as u64) < (self.sector_size as u64) {
-            let mut hb = [0u8; core::mem::size_of::<RecHeader>()];
-            self.read_exact(self.active_base + off, &mut hb)?;
+        self.generation = 0;
+        while off as usize + hdr_len <= self.sector_size as usize {
+            let mut hb = vec![0u8; hdr_len];
+            self.read_exact(self.sector_base(self.active) + off, &mut hb)?;
+            let mut fixed = 0;
+            if ecc {
+                let (h, c) = hb.split_at_mut(REC_HDR);
+                match ecc::correct(h, &c[..3]) { Some(n) => fixed += n, None => break }
+            }
             match parse_rec_header(&hb) {
                 None => { break; }, // hit blank or invalid
                 Some(h) => {
-                    if h.magic != REC_MAGIC || h.len == 0 { break; }
-                    let data_off = off + core::mem::size_of::<RecHeader>() as u32;
-                    let mut data = vec![0u8; h.len as usize];
-                    self.read_exact(self.active_base + data_off, &mut data)?;
+                    if (h.magic != REC_MAGIC && h.magic != LEGACY_REC_MAGIC) || h.len == 0 { break; }
+                    let len = h.len as usize;
+                    let data_off = off + hdr_len as u32;
+                    let body_len = if ecc { len + ecc::check_len(len) } else { len };
+                    // A torn length must not send the read past the sector
+                    if h.len > self.size || data_off as u64 + body_len as u64 > self.sector_size as u64 { break; }
+                    let mut body = vec![0u8; body_len];
+                    self.read_exact(self.sector_base(self.active) + data_off, &mut body)?;
+                    if ecc {
+                        let (d, c) = body.split_at_mut(len);
+                        match ecc::correct(d, c) { Some(n) => fixed += n, None => break }
+                    }
+                    let data = &body[..len];
+                    // CRC is computed with the crc32 field zeroed
+                    let mut hz = [0u8; REC_HDR];
+                    hz.copy_from_slice(&hb[..REC_HDR]);
+                    hz[16..20].fill(0);
                     let mut check = vec![];
-                    check.extend_from_slice(&hb);
-                    check.extend_from_slice(&data);
+                    check.extend_from_slice(&hz);
+                    check.extend_from_slice(data);
                     if crc32(&check) != h.crc32 { break; }
                     // apply
                     let start = h.addr as usize;
                     let end = (h.addr + h.len) as usize;
                     if end <= self.state.len() {
-                        self.state[start..end].copy_from_slice(&data);
+                        self.state[start..end].copy_from_slice(data);
                     }
-                    let consumed = pad4(core::mem::size_of::<RecHeader>() + h.len as usize) as u32;
-                    off = off + consumed;
+                    if h.magic == REC_MAGIC { self.generation = h.generation; }
+                    self.corrected += fixed;
+                    off += rec_len(ecc, len) as u32;
                 }
             }
         }
@@ -149,42 +446,89 @@ impl<F: Flash> Eeprom<F> {
         Ok(())
     }
 
-    fn ensure_space(&mut self, need: usize) -> Result<()> {
-        let avail = (self.sector_size as usize).saturating_sub(self.wptr as usize);
-        if avail >= pad4(need) { return Ok(()); }
-        self.compact()
+    /// A record of `len` data bytes fits in the active sector.
+    fn fits(&self, len: usize) -> bool {
+        (self.sector_size as usize).saturating_sub(self.wptr as usize) >= rec_len(self.active_ecc, len)
+    }
+
+    fn ensure_space(&mut self, len: usize) -> Result<()> {
+        if self.fits(len) { return Ok(()); }
+        self.compact()?;
+        if !self.fits(len) { return Err(anyhow!("record does not fit after compaction")); }
+        Ok(())
     }
 
     fn compact(&mut self) -> Result<()> {
-        // Write a fresh sector: header(seq+1) + snapshot record of full state
-        self.erase_sector(self.scratch_base)?;
+        // Start a fresh sector: snapshot record of full state, header(seq+1)
+        self.wait_erase()?;
         let new_seq = self.seq + 1;
-        let sh = SectorHeader { magic: SECTOR_MAGIC, seq: new_seq, reserved0: 0, reserved1: 0 };
-        let mut hb = vec![0u8; core::mem::size_of::<SectorHeader>()];
-        write_sector_header_bytes(&sh, &mut hb);
-        self.write_all(self.scratch_base, &hb)?;
-
-        // snapshot record
-        let rec = RecHeader { magic: REC_MAGIC, seq: new_seq, addr: 0, len: self.size, crc32: 0 };
-        let mut rhb = [0u8; core::mem::size_of::<RecHeader>()];
-        write_rec_header_bytes(&rec, &mut rhb);
-        let mut check = vec![]; check.extend_from_slice(&rhb); check.extend_from_slice(&self.state);
-        let crc = crc32(&check);
-        let rec = RecHeader { crc32: crc, ..rec };
-        write_rec_header_bytes(&rec, &mut rhb);
-        let data_off = self.scratch_base + core::mem::size_of::<SectorHeader>() as u32 + 0;
-        self.write_all(self.scratch_base + core::mem::size_of::<SectorHeader>() as u32, &rhb)?;
-        // Avoid overlapping borrows of &mut self and &self.state by cloning snapshot
-        let snapshot = self.state.clone();
-        self.write_all(data_off + core::mem::size_of::<RecHeader>() as u32, &snapshot)?;
-
-        // swap roles
-        core::mem::swap(&mut self.active_base, &mut self.scratch_base);
+        let ecc = self.options.ecc;
+        let (head, body) = encode_record(ecc, self.generation, 0, &self.state);
+        let old = self.active;
+        self.active = self.start_spare(Some(old), new_seq, &head, &body)?;
         self.seq = new_seq;
-        self.wptr = core::mem::size_of::<SectorHeader>() as u32 + pad4(core::mem::size_of::<RecHeader>() + self.state.len()) as u32;
+        self.active_ecc = ecc;
+        self.wptr = SECTOR_HDR + rec_len(ecc, self.state.len()) as u32;
+        // corrected records were rewritten clean in the snapshot
+        self.corrected = 0;
+
+        // Erase the old copy in the background; reads come from RAM and
+        // appends suspend the erase while they program
+        let old_base = self.sector_base(old);
+        match self.invalidate_header(old_base).and_then(|_| self.flash.sector_erase_start(old_base)) {
+            Ok(()) => {
+                self.sectors[old] = Slot::Blank;
+                self.erase_pending = true;
+            }
+            Err(e) if is_sector_fault(&e) => self.retire(old)?,
+            Err(e) => return Err(e),
+        }
         Ok(())
     }
 
+    /// Poll the background erase started by compaction; `true` while it runs.
+    pub fn poll(&mut self) -> Result<bool> {
+        if self.erase_pending { self.erase_pending = self.flash.is_busy()?; }
+        Ok(self.erase_pending)
+    }
+
+    /// Bit errors ECC corrected while replaying records still in the active
+    /// sector; the next compaction rewrites them and resets this.
+    pub fn corrected(&self) -> u32 {
+        self.corrected
+    }
+
+    pub fn status(&self) -> Status {
+        let retired = (0..self.sectors.len()).filter(|&i| self.sectors[i] == Slot::Retired);
+        Status {
+            sectors: self.sectors.len() as u32,
+            retired: retired.map(|i| self.sector_base(i)).collect(),
+            active: self.sector_base(self.active),
+        }
+    }
+
+    /// The flash underneath, e.g. to inspect a mock's clock or counters.
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    pub fn flash_mut(&mut self) -> &mut F {
+        &mut self.flash
+    }
+
+    /// Finish any background erase and hand back the flash.
+    pub fn into_flash(mut self) -> Result<F> {
+        self.wait_erase()?;
+        Ok(self.flash)
+    }
+
+    /// Hand back the flash as it is, without finishing a background erase
+    /// (e.g. after the flash failed mid-operation).
+    pub fn into_flash_now(self) -> F {
+        self.flash
+    }
+
+    /// Served from the RAM image, so it never waits on a background erase.
     pub fn read(&self, addr: u32, out: &mut [u8]) -> Result<()> {
         let end = addr as usize + out.len();
         if end > self.state.len() { return Err(anyhow!("oob")); }
@@ -195,56 +539,90 @@ impl<F: Flash> Eeprom<F> {
     pub fn write(&mut self, addr: u32, data: &[u8]) -> Result<()> {
         let end = addr as usize + data.len();
         if end > self.state.len() { return Err(anyhow!("oob")); }
-        // Prepare record
-        let mut hdr = RecHeader { magic: REC_MAGIC, seq: self.seq, addr, len: data.len() as u32, crc32: 0 };
-        let mut hb = [0u8; core::mem::size_of::<RecHeader>()];
-        write_rec_header_bytes(&hdr, &mut hb);
-        let mut check = vec![]; check.extend_from_slice(&hb); check.extend_from_slice(data);
-        hdr.crc32 = crc32(&check);
-        write_rec_header_bytes(&hdr, &mut hb);
-        // Ensure space
-        let need = pad4(core::mem::size_of::<RecHeader>() + data.len());
-        self.ensure_space(need)?;
+        self.ensure_space(data.len())?;
         // Append to active
-        let off = self.active_base + self.wptr;
-        self.write_all(off, &hb)?;
-        self.write_all(off + core::mem::size_of::<RecHeader>() as u32, data)?;
+        let (head, body) = encode_record(self.active_ecc, self.generation + 1, addr, data);
+        let off = self.sector_base(self.active) + self.wptr;
+        if let Err(e) = self.write_all(off, &head).and_then(|_| self.write_all(off + head.len() as u32, &body)) {
+            if !is_sector_fault(&e) { return Err(e); }
+            // The record didn't take; compaction carries it over from RAM
+            let old = self.state[addr as usize..end].to_vec();
+            self.state[addr as usize..end].copy_from_slice(data);
+            self.generation += 1;
+            if let Err(e) = self.compact() {
+                self.state[addr as usize..end].copy_from_slice(&old);
+                self.generation -= 1;
+                return Err(e);
+            }
+            return Ok(());
+        }
         // Advance write pointer (4-byte aligned)
-        self.wptr += need as u32;
+        self.wptr += rec_len(self.active_ecc, data.len()) as u32;
         // Update in-memory state
         self.state[addr as usize..end].copy_from_slice(data);
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Writes since format, as of the last record replayed or written.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Replace the whole contents with `state` at `generation`, in one
+    /// compaction (repair from a mirror copy).
+    pub(crate) fn rewrite(&mut self, state: &[u8], generation: u32) -> Result<()> {
+        let (old, old_gen) = (self.state.clone(), self.generation);
+        self.state.copy_from_slice(state);
+        self.generation = generation;
+        if let Err(e) = self.compact() {
+            self.state = old;
+            self.generation = old_gen;
+            return Err(e);
+        }
         Ok(())
     }
 }
 
+/// An ECC sector's header is corrected first; `flags` comes back as exactly
+/// `ECC_MARK` for those and 0 otherwise.
 fn parse_sector_header(buf: &[u8]) -> Option<SectorHeader> {
-    if buf.len() < core::mem::size_of::<SectorHeader>() { return None; }
-    let magic = u32::from_le_bytes(buf[0..4].try_into().unwrap());
-    let seq = u32::from_le_bytes(buf[4..8].try_into().unwrap());
-    if magic == SECTOR_MAGIC && seq != 0xFFFF_FFFF { Some(SectorHeader { magic, seq, reserved0: 0, reserved1: 0 }) } else { None }
+    if buf.len() < SECTOR_HDR as usize { return None; }
+    let mut b = [0u8; SECTOR_HDR as usize];
+    b.copy_from_slice(&buf[..SECTOR_HDR as usize]);
+    let flags = u32::from_le_bytes(b[8..12].try_into().unwrap());
+    let ecc = (flags ^ ECC_MARK).count_ones() <= 1;
+    if ecc {
+        let check = b[12];
+        ecc::correct(&mut b[..8], &[check])?;
+    }
+    let magic = u32::from_le_bytes(b[0..4].try_into().unwrap());
+    let seq = u32::from_le_bytes(b[4..8].try_into().unwrap());
+    let flags = if ecc { ECC_MARK } else { 0 };
+    if magic == SECTOR_MAGIC && seq != 0xFFFF_FFFF { Some(SectorHeader { magic, seq, flags, reserved1: 0 }) } else { None }
 }
 
 fn write_sector_header_bytes(h: &SectorHeader, out: &mut [u8]) {
     out[0..4].copy_from_slice(&h.magic.to_le_bytes());
     out[4..8].copy_from_slice(&h.seq.to_le_bytes());
-    out[8..12].copy_from_slice(&0u32.to_le_bytes());
-    out[12..16].copy_from_slice(&0u32.to_le_bytes());
+    out[8..12].copy_from_slice(&h.flags.to_le_bytes());
+    out[12..16].copy_from_slice(&h.reserved1.to_le_bytes());
 }
 
 fn parse_rec_header(buf: &[u8]) -> Option<RecHeader> {
     if buf.len() < core::mem::size_of::<RecHeader>() { return None; }
     let magic = u32::from_le_bytes(buf[0..4].try_into().unwrap());
     if magic == 0xFFFF_FFFF || magic == 0 { return None; }
-    let seq = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+    let generation = u32::from_le_bytes(buf[4..8].try_into().unwrap());
     let addr = u32::from_le_bytes(buf[8..12].try_into().unwrap());
     let len = u32::from_le_bytes(buf[12..16].try_into().unwrap());
     let crc32 = u32::from_le_bytes(buf[16..20].try_into().unwrap());
-    Some(RecHeader { magic, seq, addr, len, crc32 })
+    Some(RecHeader { magic, generation, addr, len, crc32 })
 }
 
 fn write_rec_header_bytes(h: &RecHeader, out: &mut [u8]) {
     out[0..4].copy_from_slice(&h.magic.to_le_bytes());
-    out[4..8].copy_from_slice(&h.seq.to_le_bytes());
+    out[4..8].copy_from_slice(&h.generation.to_le_bytes());
     out[8..12].copy_from_slice(&h.addr.to_le_bytes());
     out[12..16].copy_from_slice(&h.len.to_le_bytes());
     out[16..20].copy_from_slice(&h.crc32.to_le_bytes());
@@ -253,10 +631,30 @@ fn write_rec_header_bytes(h: &RecHeader, out: &mut [u8]) {
 #[cfg(feature = "mock")]
 pub mod mock {
     use super::*;
-    use flash_mock::MockFlash;
+    pub use flash_mock::MockFlash;
+    use flash_core::FlashGeometry;
+    use flash_mock::{OnViolation, Strict};
+
+    /// `MockFlash` that fails any call breaking a NOR rule (see `Strict`);
+    /// the EEPROM tests all run on it. Panics on a bad geometry.
+    pub fn strict_flash(mem_size: u32, page_size: u32, sector_size: u32) -> MockFlash {
+        let mut flash = MockFlash::new(mem_size, page_size, sector_size);
+        flash.set_strict(Strict::new(OnViolation::Error)).unwrap();
+        flash
+    }
 
     pub fn new_mock(base: u32, sector_size: u32, size: u32) -> Result<Eeprom<MockFlash>> {
-        let flash = MockFlash::new(sector_size * 2, 256, sector_size);
+        let mut flash = MockFlash::try_new(sector_size * 2, 256, sector_size)?;
+        flash.set_strict(Strict::new(OnViolation::Error))?;
+        Eeprom::new_with_flash(flash, base, sector_size, size)
+    }
+
+    /// Like `new_mock`, but on a flash holding `image` (e.g. a saved
+    /// `MockFlash::image()`), which sets the flash size.
+    pub fn new_mock_from_image(base: u32, sector_size: u32, size: u32, image: Vec<u8>) -> Result<Eeprom<MockFlash>> {
+        let geom = FlashGeometry { mem_size: image.len() as u32, page_size: 256, sector_size };
+        let mut flash = MockFlash::from_image(geom, image)?;
+        flash.set_strict(Strict::new(OnViolation::Error))?;
         Eeprom::new_with_flash(flash, base, sector_size, size)
     }
 }
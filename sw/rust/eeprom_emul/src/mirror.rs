@@ -0,0 +1,93 @@
+//! RAID-1 style redundancy: one logical EEPROM kept as two `Eeprom` copies,
+//! e.g. on two devices.
+
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use flash_core::Flash;
+
+use crate::{Eeprom, Options};
+
+/// One of the two copies of a `Mirror`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Two `Eeprom` copies of the same contents. Writes go to A, then B, so a
+/// power loss leaves B at most one write behind A; `new` reconciles the
+/// copies and rewrites the weaker one from the other.
+pub struct Mirror<A: Flash, B: Flash> {
+    a: Eeprom<A>,
+    b: Eeprom<B>,
+    repaired: Option<Side>,
+}
+
+impl<A: Flash, B: Flash> Mirror<A, B> {
+    /// Open a copy on each flash, both with the same layout. A copy that
+    /// fails to open is rebuilt from the other; if both fail, A's error is
+    /// returned. Otherwise the higher generation wins: a copy's log ends
+    /// early at a record that was torn or fails its CRC. At equal
+    /// generations with different contents, the copy with fewer ECC
+    /// corrections wins, A on a tie.
+    pub fn new(a: A, b: B, base: u32, sector_size: u32, size: u32, options: Options) -> Result<Self> {
+        let opened = (Eeprom::open(a, base, sector_size, size, options), Eeprom::open(b, base, sector_size, size, options));
+        match opened {
+            (Ok(a), Ok(b)) => Self::reconcile(a, b),
+            (Err((a, _)), Ok(b)) => {
+                let a = Eeprom::rebuild(a, base, sector_size, size, options, &b.state, b.generation).map_err(|(_, e)| e)?;
+                Ok(Mirror { a, b, repaired: Some(Side::A) })
+            }
+            (Ok(a), Err((b, _))) => {
+                let b = Eeprom::rebuild(b, base, sector_size, size, options, &a.state, a.generation).map_err(|(_, e)| e)?;
+                Ok(Mirror { a, b, repaired: Some(Side::B) })
+            }
+            (Err((_, e)), Err(_)) => Err(e),
+        }
+    }
+
+    fn reconcile(a: Eeprom<A>, b: Eeprom<B>) -> Result<Self> {
+        let weaker = match a.generation().cmp(&b.generation()) {
+            Ordering::Less => Some(Side::A),
+            Ordering::Greater => Some(Side::B),
+            Ordering::Equal if a.state == b.state => None,
+            Ordering::Equal if b.corrected() < a.corrected() => Some(Side::A),
+            Ordering::Equal => Some(Side::B),
+        };
+        let mut m = Mirror { a, b, repaired: weaker };
+        match weaker {
+            Some(Side::A) => m.a.rewrite(&m.b.state, m.b.generation)?,
+            Some(Side::B) => m.b.rewrite(&m.a.state, m.a.generation)?,
+            None => {}
+        }
+        Ok(m)
+    }
+
+    /// The copy `new` rebuilt or rewrote from the other, if any.
+    pub fn repaired(&self) -> Option<Side> {
+        self.repaired
+    }
+
+    pub fn read(&self, addr: u32, out: &mut [u8]) -> Result<()> {
+        self.a.read(addr, out)
+    }
+
+    /// If B fails after A took the write, the copies differ until the next `new`.
+    pub fn write(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.a.write(addr, data)?;
+        self.b.write(addr, data)
+    }
+
+    pub fn a(&self) -> &Eeprom<A> {
+        &self.a
+    }
+
+    pub fn b(&self) -> &Eeprom<B> {
+        &self.b
+    }
+
+    pub fn into_inner(self) -> (Eeprom<A>, Eeprom<B>) {
+        (self.a, self.b)
+    }
+}
@@ -0,0 +1,53 @@
+//! Fixtures shared by the mock-backed EEPROM tests.
+#![allow(dead_code)] // each test crate uses its own subset
+
+use eeprom_emul::Eeprom;
+use flash_core::Flash;
+use flash_mock::PowerLost;
+
+pub const SECTOR: u32 = 512;
+pub const SIZE: u32 = 64;
+
+/// Small writes that wrap the log and force several compactions.
+pub fn workload() -> Vec<(u32, Vec<u8>)> {
+    (0..40u32).map(|i| ((i * 12) % (SIZE - 8), vec![i as u8; 1 + (i % 7) as usize])).collect()
+}
+
+/// Logical contents after `writes` from blank.
+pub fn image_after(writes: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut img = vec![0xFF; SIZE as usize];
+    for (addr, data) in writes {
+        img[*addr as usize..*addr as usize + data.len()].copy_from_slice(data);
+    }
+    img
+}
+
+/// Take what `open` returned and apply `writes` with `write` until the first
+/// power loss; returns how many completed. Any other error fails the test.
+pub fn run_until_lost<T>(
+    open: anyhow::Result<T>,
+    writes: &[(u32, Vec<u8>)],
+    mut write: impl FnMut(&mut T, u32, &[u8]) -> anyhow::Result<()>,
+) -> usize {
+    let lost = |e: anyhow::Error| assert!(e.downcast_ref::<PowerLost>().is_some(), "unexpected error: {:#}", e);
+    let mut target = match open {
+        Ok(t) => t,
+        Err(e) => {
+            lost(e);
+            return 0;
+        }
+    };
+    for (i, (addr, data)) in writes.iter().enumerate() {
+        if let Err(e) = write(&mut target, *addr, data) {
+            lost(e);
+            return i;
+        }
+    }
+    writes.len()
+}
+
+pub fn contents<F: Flash>(ee: &Eeprom<F>) -> Vec<u8> {
+    let mut got = vec![0u8; SIZE as usize];
+    ee.read(0, &mut got).unwrap();
+    got
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "mock")]
+//! Per-record ECC: single-bit read errors are corrected at replay and the
+//! records rewritten clean by the next compaction.
+
+mod common;
+
+use common::{contents, SECTOR, SIZE};
+use eeprom_emul::mock::strict_flash;
+use eeprom_emul::{Eeprom, Options};
+use flash_core::Flash;
+use flash_mock::{BitFlips, MockFlash};
+
+const ECC: Options = Options { ecc: true, sectors: 2 };
+
+/// A few records after the format's; returns the flash and the contents.
+fn written(opts: Options) -> (MockFlash, Vec<u8>) {
+    let mut ee = Eeprom::new_with_options(strict_flash(SECTOR * 2, 256, SECTOR), 0, SECTOR, SIZE, opts).unwrap();
+    let mut model = vec![0xFF; SIZE as usize];
+    for i in 0..6u32 {
+        let (addr, data) = (i * 10, vec![i as u8 + 1; 4 + i as usize]);
+        ee.write(addr, &data).unwrap();
+        model[addr as usize..addr as usize + data.len()].copy_from_slice(&data);
+    }
+    (ee.into_flash().unwrap(), model)
+}
+
+/// Offset of the first record's data: sector header, record header, and
+/// with ECC its check bytes.
+fn first_data(opts: Options) -> u32 {
+    16 + 20 + if opts.ecc { 4 } else { 0 }
+}
+
+#[test]
+fn without_ecc_a_flipped_bit_loses_the_rest_of_the_log() {
+    let (flash, model) = written(Options::default());
+    let mut f = BitFlips::new(flash, 0);
+    f.flip_next(first_data(Options::default()), 2);
+    let ee = Eeprom::new_with_flash(f, 0, SECTOR, SIZE).unwrap();
+    assert_ne!(contents(&ee), model);
+    assert_eq!(ee.corrected(), 0);
+}
+
+#[test]
+fn ecc_corrects_a_flipped_bit_on_replay() {
+    let (flash, model) = written(ECC);
+    let mut f = BitFlips::new(flash, 0);
+    f.flip_next(first_data(ECC), 2);
+    let ee = Eeprom::new_with_options(f, 0, SECTOR, SIZE, ECC).unwrap();
+    assert_eq!(ee.flash().flipped(), 1);
+    assert_eq!(contents(&ee), model);
+    assert_eq!(ee.corrected(), 1);
+}
+
+#[test]
+fn compaction_rewrites_corrected_records() {
+    let (flash, mut model) = written(ECC);
+    // A bit decayed in the array itself, so every replay sees it
+    let mut img = flash.image().to_vec();
+    img[first_data(ECC) as usize + 1] ^= 0x10;
+    let flash = MockFlash::from_image(flash.geometry(), img).unwrap();
+    let mut ee = Eeprom::new_with_options(flash, 0, SECTOR, SIZE, ECC).unwrap();
+    assert_eq!(ee.corrected(), 1);
+
+    // Fill the sector until the log compacts
+    let mut i = 0u8;
+    while ee.corrected() != 0 {
+        ee.write(60, &[i; 4]).unwrap();
+        model[60..].fill(i);
+        i += 1;
+    }
+    assert_eq!(contents(&ee), model);
+    let ee = Eeprom::new_with_options(ee.into_flash().unwrap(), 0, SECTOR, SIZE, ECC).unwrap();
+    assert_eq!(ee.corrected(), 0);
+    assert_eq!(contents(&ee), model);
+}
+
+#[test]
+fn any_single_flip_in_the_log_is_survived() {
+    let (flash, model) = written(ECC);
+    for addr in 0..SECTOR * 2 {
+        let mut f = BitFlips::new(flash.clone(), 0);
+        f.flip_next(addr, (addr % 8) as u8);
+        let mut ee = Eeprom::new_with_options(f, 0, SECTOR, SIZE, ECC)
+            .unwrap_or_else(|e| panic!("flip at {:#x}: reopen failed: {:#}", addr, e));
+        assert_eq!(contents(&ee), model, "flip at {:#x}", addr);
+        ee.write(SIZE - 4, &[0xA5; 4]).unwrap();
+    }
+}
+
+#[test]
+fn switching_ecc_on_and_off_keeps_the_data() {
+    let (flash, mut model) = written(Options::default());
+    let mut ee = Eeprom::new_with_options(flash, 0, SECTOR, SIZE, ECC).unwrap();
+    assert_eq!(contents(&ee), model);
+    // Enough writes to compact into the ECC format and then some
+    for i in 0..40u8 {
+        ee.write(56, &[i; 8]).unwrap();
+        model[56..].fill(i);
+    }
+    let ee = Eeprom::new_with_options(ee.into_flash().unwrap(), 0, SECTOR, SIZE, Options::default()).unwrap();
+    assert_eq!(contents(&ee), model);
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "ffi")]
+use eeprom_emul::ffi::new_with_driver;
+use flash_ll::sim::*;
+
+#[test]
+fn large_snapshot_survives_reopen() {
+    let drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    // snapshot records bigger than the AXI RX FIFO
+    let mut ee = new_with_driver(drv, 0, 4096, 3000).unwrap();
+    let pattern: Vec<u8> = (0..3000u32).map(|i| (i * 13) as u8).collect();
+    for (i, chunk) in pattern.chunks(100).enumerate() {
+        ee.write(i as u32 * 100, chunk).unwrap();
+    }
+    // too big to sit next to a snapshot: compacts, then fails cleanly
+    assert!(ee.write(0, &pattern).is_err());
+    let drv = ee.into_flash().unwrap();
+    let ee = new_with_driver(drv, 0, 4096, 3000).unwrap();
+    let mut out = vec![0u8; 3000];
+    ee.read(0, &mut out).unwrap();
+    assert_eq!(out, pattern);
+}
+
+#[test]
+fn eeprom_moves_to_worker_thread() {
+    let drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    let mut ee = new_with_driver(drv, 0, 4096, 64).unwrap();
+    ee.write(0, b"main").unwrap();
+    let worker = std::thread::spawn(move || {
+        ee.write(4, b"work").unwrap();
+        ee
+    });
+    let ee = worker.join().unwrap();
+    let mut out = [0u8; 8];
+    ee.read(0, &mut out).unwrap();
+    assert_eq!(&out, b"mainwork");
+}
@@ -0,0 +1,204 @@
+#![cfg(feature = "mock")]
+//! Lifetime runs on a flash that wears out: how many writes the emulation
+//! takes before worn sectors are retired, and what the caller sees then.
+
+mod common;
+
+use common::{contents, SECTOR, SIZE};
+use eeprom_emul::mock::strict_flash;
+use eeprom_emul::{Eeprom, NoSpareSector, Options, Status};
+use flash_core::{FlashGeometry, Verify};
+use flash_mock::{BitFlips, Endurance, MockFlash, Wear};
+
+const LIMIT: u32 = 10;
+
+fn worn_flash(wear: Wear) -> MockFlash {
+    let mut f = strict_flash(SECTOR * 2, 256, SECTOR);
+    f.set_endurance(Endurance { limit: LIMIT, wear });
+    f
+}
+
+/// 8-byte writes round-robin over the EEPROM until one fails; returns the
+/// number that succeeded, the error and the expected contents.
+fn write_until_failure<F: flash_core::Flash>(ee: &mut Eeprom<F>) -> (u32, anyhow::Error, Vec<u8>) {
+    let mut model = vec![0xFF; SIZE as usize];
+    for i in 0u32.. {
+        let addr = (i * 8) % SIZE;
+        let data = [i as u8; 8];
+        if let Err(e) = ee.write(addr, &data) {
+            return (i, e, model);
+        }
+        model[addr as usize..addr as usize + 8].copy_from_slice(&data);
+    }
+    unreachable!()
+}
+
+/// 28-byte records: after the 16-byte header when formatted, after the
+/// header and 84-byte snapshot once compacted.
+fn writes_for(compactions: u32) -> u32 {
+    (SECTOR - 16) / 28 + compactions * ((SECTOR - 100) / 28)
+}
+
+#[test]
+fn erase_failure_retires_the_sector_and_keeps_data() {
+    let mut ee = Eeprom::new_with_flash(worn_flash(Wear::EraseFails), 0, SECTOR, SIZE).unwrap();
+    let (writes, err, model) = write_until_failure(&mut ee);
+    assert!(err.downcast_ref::<NoSpareSector>().is_some(), "{:#}", err);
+    let status = ee.status();
+    assert!(status.degraded());
+    assert_eq!(status.retired.len(), 1);
+    assert_ne!(status.retired[0], status.active);
+
+    // Format erases both sectors once, then every compaction erases the
+    // sector it left; the (LIMIT + 1)th erase of one fails and retires it,
+    // and the other fills up with no spare left
+    let flash = ee.into_flash_now();
+    let counts = flash.erase_counts().to_vec();
+    assert_eq!(counts.iter().max(), Some(&(LIMIT + 1)));
+    assert_eq!(counts.iter().sum::<u32>(), 2 * LIMIT + 1);
+    assert_eq!(writes, writes_for(2 * LIMIT - 1));
+
+    // Everything written before the failure is still there, and the
+    // retirement survives a reopen
+    let ee = Eeprom::new_with_flash(flash, 0, SECTOR, SIZE).unwrap();
+    assert_eq!(contents(&ee), model);
+    assert_eq!(ee.status(), status);
+}
+
+#[test]
+fn stuck_bits_are_caught_by_verify() {
+    let flash = Verify::new(worn_flash(Wear::StuckBits { per_erase: 8, seed: 1 }));
+    let mut ee = Eeprom::new_with_flash(flash, 0, SECTOR, SIZE).unwrap();
+    let (_, err, model) = write_until_failure(&mut ee);
+    assert!(err.downcast_ref::<NoSpareSector>().is_some(), "{:#}", err);
+    assert_eq!(ee.status().retired.len(), 1);
+    let flash = ee.into_flash_now().into_inner();
+    assert!(flash.stuck_bits() > 0);
+    // Verify retried the worn erase once before giving up
+    assert_eq!(flash.erase_counts().iter().max(), Some(&(LIMIT + 2)));
+
+    let ee = Eeprom::new_with_flash(flash, 0, SECTOR, SIZE).unwrap();
+    assert_eq!(contents(&ee), model);
+}
+
+#[test]
+fn stuck_bits_are_caught_by_blank_check() {
+    let mut ee = Eeprom::new_with_flash(worn_flash(Wear::StuckBits { per_erase: 8, seed: 1 }), 0, SECTOR, SIZE).unwrap();
+    let (_, err, model) = write_until_failure(&mut ee);
+    assert!(err.downcast_ref::<NoSpareSector>().is_some(), "{:#}", err);
+    let flash = ee.into_flash_now();
+    // The first worn erase went through, the blank check before reuse
+    // found its stuck bits
+    assert_eq!(flash.erase_counts().iter().max(), Some(&(LIMIT + 1)));
+
+    let ee = Eeprom::new_with_flash(flash, 0, SECTOR, SIZE).unwrap();
+    assert_eq!(ee.status().retired.len(), 1);
+    assert_eq!(contents(&ee), model);
+}
+
+#[test]
+fn pool_runs_degraded_until_one_sector_is_left() {
+    const SECTORS: u32 = 4;
+    let mut f = strict_flash(SECTOR * SECTORS, 256, SECTOR);
+    f.set_endurance(Endurance { limit: LIMIT, wear: Wear::EraseFails });
+    let opts = Options { sectors: SECTORS, ..Options::default() };
+    let mut ee = Eeprom::new_with_options(f, 0, SECTOR, SIZE, opts).unwrap();
+
+    let mut retired = 0;
+    let mut model = vec![0xFF; SIZE as usize];
+    let mut writes = 0;
+    let err = loop {
+        let addr = (writes * 8) % SIZE;
+        let data = [writes as u8; 8];
+        if let Err(e) = ee.write(addr, &data) { break e; }
+        model[addr as usize..addr as usize + 8].copy_from_slice(&data);
+        writes += 1;
+        // Writes go on as sectors drop out
+        let n = ee.status().retired.len();
+        assert!(n >= retired);
+        retired = n;
+    };
+    assert!(err.downcast_ref::<NoSpareSector>().is_some(), "{:#}", err);
+    assert_eq!(retired as u32, SECTORS - 1);
+
+    // One compaction per erase after the format's
+    let flash = ee.into_flash_now();
+    let erases: u32 = flash.erase_counts().iter().sum();
+    assert_eq!(writes, writes_for(erases - SECTORS));
+    assert!(writes > 2 * writes_for(2 * LIMIT - 1) - writes_for(0));
+
+    let ee = Eeprom::new_with_options(flash, 0, SECTOR, SIZE, opts).unwrap();
+    assert_eq!(ee.status().retired.len() as u32, SECTORS - 1);
+    assert_eq!(contents(&ee), model);
+}
+
+#[test]
+fn failed_append_moves_the_log_to_a_spare() {
+    let mut ee = Eeprom::new_with_flash(BitFlips::new(strict_flash(SECTOR * 2, 256, SECTOR), 0), 0, SECTOR, SIZE).unwrap();
+    ee.write(0, &[1; 8]).unwrap();
+    let active = ee.status().active;
+    // The read-back of the next record, and its re-read, see a bit that
+    // didn't program
+    let next = active + 16 + 28 + 20;
+    ee.flash_mut().flip_next(next, 0);
+    ee.flash_mut().flip_next(next, 0);
+    ee.write(8, &[2; 8]).unwrap();
+    assert_eq!(ee.flash().flipped(), 2);
+    let status = ee.status();
+    assert_ne!(status.active, active);
+    assert!(!status.degraded(), "the old sector erased fine");
+
+    let ee = Eeprom::new_with_flash(ee.into_flash().unwrap().into_inner(), 0, SECTOR, SIZE).unwrap();
+    let mut got = [0u8; 16];
+    ee.read(0, &mut got).unwrap();
+    assert_eq!(got, [[1; 8], [2; 8]].concat()[..]);
+}
+
+#[test]
+fn read_flips_do_not_retire_sectors() {
+    const SECTORS: u32 = 4;
+    let opts = Options { ecc: true, sectors: SECTORS };
+    let mut f = BitFlips::new(strict_flash(SECTOR * SECTORS, 256, SECTOR), 3);
+    f.set_rate(1e-4);
+    let mut ee = Eeprom::new_with_options(f, 0, SECTOR, SIZE, opts).unwrap();
+    let mut model = vec![0xFF; SIZE as usize];
+    for i in 0..5000u32 {
+        let addr = (i * 8) % SIZE;
+        let data = [i as u8; 8];
+        ee.write(addr, &data).unwrap_or_else(|e| panic!("write {}: {:#}", i, e));
+        model[addr as usize..addr as usize + 8].copy_from_slice(&data);
+    }
+    assert!(ee.flash().flipped() > 0);
+    assert_eq!(ee.status().retired, Vec::<u32>::new());
+
+    let ee = Eeprom::new_with_options(ee.into_flash().unwrap().into_inner(), 0, SECTOR, SIZE, opts).unwrap();
+    assert_eq!(contents(&ee), model);
+}
+
+#[test]
+fn format_skips_a_retired_sector() {
+    let mut image = vec![0xFF; SECTOR as usize * 3];
+    image[..16].fill(0);
+    let geom = FlashGeometry { mem_size: SECTOR * 3, page_size: 256, sector_size: SECTOR };
+    let opts = Options { sectors: 3, ..Options::default() };
+    let mut ee = Eeprom::new_with_options(MockFlash::from_image(geom, image).unwrap(), 0, SECTOR, SIZE, opts).unwrap();
+    assert_eq!(ee.status(), Status { sectors: 3, retired: vec![0], active: SECTOR });
+    ee.write(0, &[7; 4]).unwrap();
+    let flash = ee.into_flash().unwrap();
+    assert_eq!(flash.erase_count(0), 0);
+    let ee = Eeprom::new_with_options(flash, 0, SECTOR, SIZE, opts).unwrap();
+    assert_eq!(ee.status().retired, vec![0]);
+    assert_eq!(contents(&ee)[..4], [7; 4]);
+}
+
+#[test]
+fn format_uses_the_last_sector() {
+    let mut image = vec![0xFF; SECTOR as usize * 2];
+    image[..16].fill(0);
+    let geom = FlashGeometry { mem_size: SECTOR * 2, page_size: 256, sector_size: SECTOR };
+    let mut ee = Eeprom::new_with_flash(MockFlash::from_image(geom, image).unwrap(), 0, SECTOR, SIZE).unwrap();
+    assert_eq!(ee.status(), Status { sectors: 2, retired: vec![0], active: SECTOR });
+    ee.write(0, &[7; 4]).unwrap();
+    let ee = Eeprom::new_with_flash(ee.into_flash().unwrap(), 0, SECTOR, SIZE).unwrap();
+    assert_eq!(contents(&ee)[..4], [7; 4]);
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "mock")]
+//! Mirrored EEPROM over two mock devices, each with its own faults: after
+//! reopening, both copies agree and hold either the value before or after
+//! the interrupted write.
+
+mod common;
+
+use common::{image_after, run_until_lost, workload, SECTOR, SIZE};
+use eeprom_emul::mock::strict_flash;
+use eeprom_emul::{Mirror, NoSpareSector, Options, Side};
+use flash_core::Flash;
+use flash_mock::{BitFlips, Cut, MockFlash, PowerCut};
+
+fn blank() -> MockFlash {
+    strict_flash(SECTOR * 2, 256, SECTOR)
+}
+
+fn open<A: Flash, B: Flash>(a: A, b: B) -> anyhow::Result<Mirror<A, B>> {
+    Mirror::new(a, b, 0, SECTOR, SIZE, Options::default())
+}
+
+/// Open and run `writes` until the first power loss; returns how many completed.
+fn run<A: Flash, B: Flash>(a: A, b: B, writes: &[(u32, Vec<u8>)]) -> usize {
+    run_until_lost(open(a, b), writes, |m, addr, data| m.write(addr, data))
+}
+
+/// Both copies agree after the reopen and stay writable; returns the contents.
+fn reopen_and_check<A: Flash, B: Flash>(a: A, b: B) -> (Vec<u8>, Option<Side>) {
+    let mut m = open(a, b).unwrap();
+    assert_eq!(m.a().generation(), m.b().generation());
+    let mut got = vec![0u8; SIZE as usize];
+    let mut other = vec![0u8; SIZE as usize];
+    m.a().read(0, &mut got).unwrap();
+    m.b().read(0, &mut other).unwrap();
+    assert_eq!(got, other);
+    m.write(SIZE - 1, &[0x5A]).unwrap();
+    (got, m.repaired())
+}
+
+#[test]
+fn power_loss_on_b_is_repaired_from_a() {
+    let writes = workload();
+    let mut dry = PowerCut::new(blank(), Cut::Never);
+    assert_eq!(run(blank(), &mut dry, &writes), writes.len());
+
+    let mut repairs = 0;
+    for n in 0..dry.bytes() {
+        let mut a = blank();
+        let mut b = PowerCut::new(blank(), Cut::Byte(n));
+        let done = run(&mut a, &mut b, &writes);
+        // A took write `done` before B lost power
+        let (pre, post) = (image_after(&writes[..done]), image_after(&writes[..(done + 1).min(writes.len())]));
+        let (got, repaired) = reopen_and_check(&mut a, b.reset());
+        assert!(got == pre || got == post, "cut at byte {} during write {}", n, done);
+        assert_ne!(repaired, Some(Side::A), "cut at byte {}", n);
+        if repaired == Some(Side::B) {
+            assert_eq!(got, post, "cut at byte {}", n);
+            repairs += 1;
+        }
+    }
+    assert!(repairs > 0);
+}
+
+#[test]
+fn power_loss_on_a_leaves_both_at_the_previous_write() {
+    let writes = workload();
+    let mut dry = PowerCut::new(blank(), Cut::Never);
+    assert_eq!(run(&mut dry, blank(), &writes), writes.len());
+
+    for n in 0..dry.bytes() {
+        let mut a = PowerCut::new(blank(), Cut::Byte(n));
+        let mut b = blank();
+        let done = run(&mut a, &mut b, &writes);
+        let (got, repaired) = reopen_and_check(a.reset(), &mut b);
+        assert_eq!(got, image_after(&writes[..done]), "cut at byte {}", n);
+        assert_eq!(repaired, None, "cut at byte {}", n);
+    }
+}
+
+#[test]
+fn corrupt_record_in_a_is_repaired_from_b() {
+    let (mut a, mut b) = (blank(), blank());
+    let writes = workload();
+    assert_eq!(run(&mut a, &mut b, &writes[..8]), 8);
+
+    // A bit decays in A's first record; its log ends there
+    let mut img = a.image().to_vec();
+    img[16 + 20] ^= 0x01;
+    let mut a = MockFlash::from_image(a.geometry(), img).unwrap();
+    let (got, repaired) = reopen_and_check(&mut a, &mut b);
+    assert_eq!(repaired, Some(Side::A));
+    assert_eq!(got, image_after(&writes[..8]));
+
+    let m = open(&mut a, &mut b).unwrap();
+    assert_eq!(m.repaired(), None);
+}
+
+#[test]
+fn read_error_on_b_at_boot_is_repaired_from_a() {
+    let (mut a, mut b) = (blank(), blank());
+    let writes = workload();
+    assert_eq!(run(&mut a, &mut b, &writes[..8]), 8);
+
+    // B reads a flipped bit in its fourth record at boot, so only A has the rest
+    let mut b = BitFlips::new(b, 0);
+    b.flip_next(16 + 3 * 24 + 20, 4);
+    let (got, repaired) = reopen_and_check(&mut a, &mut b);
+    assert_eq!(b.flipped(), 1);
+    assert_eq!(repaired, Some(Side::B));
+    assert_eq!(got, image_after(&writes[..8]));
+}
+
+/// Both sector headers zeroed, as if each sector had been retired: the
+/// copy no longer opens.
+fn retire_all(flash: &MockFlash) -> MockFlash {
+    let mut img = flash.image().to_vec();
+    img[..16].fill(0);
+    img[SECTOR as usize..SECTOR as usize + 16].fill(0);
+    MockFlash::from_image(flash.geometry(), img).unwrap()
+}
+
+#[test]
+fn copy_that_fails_to_open_is_rebuilt() {
+    let (mut a, mut b) = (blank(), blank());
+    let writes = workload();
+    assert_eq!(run(&mut a, &mut b, &writes[..8]), 8);
+
+    for side in [Side::A, Side::B] {
+        let (mut a, mut b) = match side {
+            Side::A => (retire_all(&a), b.clone()),
+            Side::B => (a.clone(), retire_all(&b)),
+        };
+        let (got, repaired) = reopen_and_check(&mut a, &mut b);
+        assert_eq!(repaired, Some(side));
+        assert_eq!(got, image_after(&writes[..8]));
+        let m = open(&mut a, &mut b).unwrap();
+        assert_eq!(m.repaired(), None);
+        assert_eq!(m.a().generation(), 9);
+    }
+
+    let err = open(retire_all(&a), retire_all(&b)).err().unwrap();
+    assert!(err.downcast_ref::<NoSpareSector>().is_some(), "{:#}", err);
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// A log as written before records carried a generation: each record has
+/// the sector's `seq` where the generation is now.
+fn legacy_log(seq: u32, writes: &[(u32, Vec<u8>)]) -> MockFlash {
+    let mut img = vec![0xFF; SECTOR as usize * 2];
+    let words = |w: &[u32]| w.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+    img[..16].copy_from_slice(&words(&[0xEE5E_C007, seq, 0, 0]));
+    let mut off = 16;
+    for (addr, data) in writes {
+        let mut rec = words(&[0xEE4C_0A11, seq, *addr, data.len() as u32, 0]);
+        rec.extend_from_slice(data);
+        let crc = crc32(&rec);
+        rec[16..20].copy_from_slice(&crc.to_le_bytes());
+        img[off..off + rec.len()].copy_from_slice(&rec);
+        off += (rec.len() + 3) & !3;
+    }
+    MockFlash::from_image(blank().geometry(), img).unwrap()
+}
+
+#[test]
+fn legacy_logs_open_at_generation_zero() {
+    let writes = &workload()[..3];
+    // A saw more compactions, which says nothing about which copy is newer
+    let (mut a, mut b) = (legacy_log(7, writes), legacy_log(2, writes));
+    let (got, repaired) = reopen_and_check(&mut a, &mut b);
+    assert_eq!(repaired, None);
+    assert_eq!(got, image_after(writes));
+
+    let m = open(&mut a, &mut b).unwrap();
+    assert_eq!((m.a().generation(), m.b().generation()), (1, 1));
+    assert_eq!(m.repaired(), None);
+}
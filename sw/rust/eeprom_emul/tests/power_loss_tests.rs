@@ -0,0 +1,126 @@
+#![cfg(feature = "mock")]
+//! Power loss at every program/erase and every byte of them: after each cut
+//! the EEPROM is reopened and every logical byte must hold either the value
+//! before or after the interrupted write, and the log must accept new writes.
+
+mod common;
+
+use common::{image_after, run_until_lost, workload, SECTOR, SIZE};
+use eeprom_emul::mock::strict_flash;
+use eeprom_emul::Eeprom;
+use flash_mock::{Cut, MockFlash, Physics, PowerCut};
+
+/// Flash setup for every run of a sweep.
+#[derive(Clone, Copy)]
+struct Setup {
+    erase_polls: u32,
+    physics: Physics,
+}
+
+const ORDERED: Setup = Setup { erase_polls: 0, physics: Physics::Ordered };
+
+impl Setup {
+    /// A blank flash; `Physics::Random` is reseeded per cut so that each
+    /// one tears differently.
+    fn fresh(&self, cut: u64) -> MockFlash {
+        let mut f = strict_flash(SECTOR * 2, 256, SECTOR);
+        f.set_erase_polls(self.erase_polls);
+        f.set_physics(match self.physics {
+            Physics::Random { seed, flip, weak } => Physics::Random { seed: seed ^ cut, flip, weak },
+            p => p,
+        });
+        f
+    }
+}
+
+/// Open (formatting a blank flash) and run `writes` until the first power
+/// loss; returns how many writes completed.
+fn run(flash: &mut PowerCut, writes: &[(u32, Vec<u8>)]) -> usize {
+    run_until_lost(Eeprom::new_with_flash(flash, 0, SECTOR, SIZE), writes, |ee, addr, data| ee.write(addr, data))
+}
+
+fn check(cut: Cut, writes: &[(u32, Vec<u8>)], setup: Setup) {
+    let seed = match cut {
+        Cut::Op { op, .. } => op << 32,
+        Cut::Byte(n) => n,
+        Cut::Never => 0,
+    };
+    let mut pc = PowerCut::new(setup.fresh(seed), cut);
+    let done = run(&mut pc, writes);
+    let pre = image_after(&writes[..done]);
+    let post = image_after(&writes[..(done + 1).min(writes.len())]);
+
+    let mut ee = Eeprom::new_with_flash(pc.reset(), 0, SECTOR, SIZE)
+        .unwrap_or_else(|e| panic!("{:?}: reopen failed: {:#}", cut, e));
+    let mut got = vec![0u8; SIZE as usize];
+    ee.read(0, &mut got).unwrap();
+    for i in 0..SIZE as usize {
+        assert!(
+            got[i] == pre[i] || got[i] == post[i],
+            "{:?} during write {}: byte {} is {:#04x}, expected {:#04x} or {:#04x}",
+            cut, done, i, got[i], pre[i], post[i]
+        );
+    }
+
+    // The recovered log must stay writable and survive another reopen
+    ee.write(SIZE - 4, &[0xA5; 4]).unwrap_or_else(|e| panic!("{:?}: write after reopen: {:#}", cut, e));
+    got[SIZE as usize - 4..].fill(0xA5);
+    let ee = Eeprom::new_with_flash(ee.into_flash().unwrap(), 0, SECTOR, SIZE).unwrap();
+    let mut again = vec![0u8; SIZE as usize];
+    ee.read(0, &mut again).unwrap();
+    assert_eq!(again, got, "{:?}: state lost after write following recovery", cut);
+}
+
+/// Dry-run the workload from a blank flash to count its operations and
+/// bytes, then cut at each of them.
+fn exhaust(writes: &[(u32, Vec<u8>)], setup: Setup) -> u64 {
+    let mut dry = PowerCut::new(setup.fresh(0), Cut::Never);
+    assert_eq!(run(&mut dry, writes), writes.len());
+    let (ops, bytes) = (dry.ops(), dry.bytes());
+    for op in 0..ops {
+        check(Cut::Op { op, applied: 0 }, writes, setup);
+    }
+    for n in 0..bytes {
+        check(Cut::Byte(n), writes, setup);
+    }
+    bytes
+}
+
+#[test]
+fn power_loss_during_format() {
+    let bytes = exhaust(&[(0, vec![1, 2, 3])], ORDERED);
+    assert!(bytes > 2 * SECTOR as u64);
+}
+
+#[test]
+fn power_loss_at_every_byte() {
+    exhaust(&workload(), ORDERED);
+}
+
+#[test]
+fn power_loss_with_background_erase() {
+    exhaust(&workload(), Setup { erase_polls: 4, ..ORDERED });
+}
+
+#[test]
+fn power_loss_with_random_bit_physics() {
+    let physics = Physics::Random { seed: 0x5EED, flip: 0.5, weak: 0.0 };
+    exhaust(&workload(), Setup { physics, ..ORDERED });
+    exhaust(&workload(), Setup { erase_polls: 4, physics });
+}
+
+#[test]
+fn power_loss_with_weak_bits() {
+    let physics = Physics::Random { seed: 0xB17, flip: 0.5, weak: 0.1 };
+    exhaust(&workload(), Setup { physics, ..ORDERED });
+}
+
+/// Few flips per interrupted erase: an old header can keep its magic while
+/// its seq bits creep up past the active sector's.
+#[test]
+fn power_loss_mid_erase_with_random_physics() {
+    for seed in 0..4 {
+        let physics = Physics::Random { seed, flip: 0.02, weak: 0.0 };
+        exhaust(&workload(), Setup { physics, ..ORDERED });
+    }
+}
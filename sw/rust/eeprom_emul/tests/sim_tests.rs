@@ -16,6 +16,40 @@ fn basic_read_write() {
 }
 
 #[test]
+fn zero_sector_size_is_an_error() {
+    assert!(new_mock(0, 0, 64).is_err());
+}
+
+#[test]
+fn records_survive_reopen() {
+    use eeprom_emul::Eeprom;
+    let mut ee = new_mock(0, 4096, 64).unwrap();
+    ee.write(0, &[1, 2, 3, 4]).unwrap();
+    ee.write(8, &[5, 6]).unwrap();
+    let ee = Eeprom::new_with_flash(ee.into_flash().unwrap(), 0, 4096, 64).unwrap();
+    let mut out = [0u8; 10];
+    ee.read(0, &mut out).unwrap();
+    assert_eq!(out, [1, 2, 3, 4, 0xFF, 0xFF, 0xFF, 0xFF, 5, 6]);
+}
+
+#[test]
+fn record_too_big_for_a_compacted_sector_fails() {
+    let mut ee = new_mock(0, 4096, 3000).unwrap();
+    let mut model = vec![0x11; 3000];
+    ee.write(0, &model).unwrap();
+    ee.write(0, &[0x22; 1000]).unwrap();
+    // Compacts to the second sector
+    ee.write(0, &[0x33; 1000]).unwrap();
+    model[..1000].fill(0x33);
+    // Compacts back to the first and still doesn't fit next to the snapshot
+    assert!(ee.write(0, &[0x44; 3000]).is_err());
+    let mut out = vec![0u8; 3000];
+    ee.read(0, &mut out).unwrap();
+    assert_eq!(out, model);
+}
+
+#[test]
+#[allow(clippy::unnecessary_cast)] // kept as first written
 fn compaction_path() {
     let mut ee = new_mock(0, 4096, 64).unwrap();
     // perform many small writes to force compaction
@@ -29,3 +63,112 @@ fn compaction_path() {
     // Relaxed check: value is not 0xFF and was written
     assert_ne!(out, [0xFF,0xFF,0xFF,0xFF]);
 }
+
+#[test]
+fn writes_suspend_background_erase() {
+    use eeprom_emul::Eeprom;
+    let mut flash = strict_flash(8192, 256, 4096);
+    flash.set_erase_polls(1000);
+    let mut ee = Eeprom::new_with_flash(flash, 0, 4096, 64).unwrap();
+    // fill the active sector until compaction kicks off the erase of the old copy
+    let mut i = 0u32;
+    while !ee.poll().unwrap() {
+        ee.write(i % 60, &[i as u8; 4]).unwrap();
+        i += 1;
+    }
+    // appends go through suspend/program/resume, reads come from RAM
+    for j in 0..8u32 {
+        ee.write(j * 4, &[0xA0 + j as u8; 4]).unwrap();
+        let mut out = [0u8; 4];
+        ee.read(j * 4, &mut out).unwrap();
+        assert_eq!(out, [0xA0 + j as u8; 4]);
+    }
+    assert!(ee.poll().unwrap());
+    while ee.poll().unwrap() {}
+    let flash = ee.into_flash().unwrap();
+    let ee = Eeprom::new_with_flash(flash, 0, 4096, 64).unwrap();
+    let mut out = [0u8; 4];
+    ee.read(28, &mut out).unwrap();
+    assert_eq!(out, [0xA7; 4]);
+}
+
+#[test]
+fn reopen_after_power_cut_mid_record() {
+    use eeprom_emul::Eeprom;
+    use flash_mock::{Cut, PowerCut};
+
+    let mut ee = new_mock(0, 4096, 64).unwrap();
+    ee.write(8, &[1, 2, 3, 4]).unwrap();
+    let flash = ee.into_flash().unwrap();
+
+    // The new record's header lands, its data only half way
+    let mut ee = Eeprom::new_with_flash(PowerCut::new(flash, Cut::Op { op: 1, applied: 2 }), 0, 4096, 64).unwrap();
+    let err = ee.write(8, &[9, 9, 9, 9]).unwrap_err();
+    assert!(err.downcast_ref::<flash_mock::PowerLost>().is_some());
+    let flash = ee.into_flash_now().reset();
+
+    let ee = Eeprom::new_with_flash(flash, 0, 4096, 64).unwrap();
+    let mut out = [0u8; 4];
+    ee.read(8, &mut out).unwrap();
+    assert_eq!(out, [1, 2, 3, 4]);
+}
+
+#[test]
+fn worst_case_write_latency_includes_compaction() {
+    use eeprom_emul::Eeprom;
+    use flash_mock::Timing;
+    let t = Timing::default();
+    // Latency of each of 400 8-byte writes (two compactions), with `idle`
+    // ns of other work between them
+    let run = |idle: u64| {
+        let mut flash = strict_flash(8192, 256, 4096);
+        flash.set_timing(t);
+        let mut ee = Eeprom::new_with_flash(flash, 0, 4096, 256).unwrap();
+        (0..400u32)
+            .map(|i| {
+                let start = ee.flash().now();
+                ee.write((i * 8) % 256, &[i as u8; 8]).unwrap();
+                let took = ee.flash().now() - start;
+                ee.flash_mut().advance(idle);
+                took
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // A plain append is a header and a data program, each maybe split by a page
+    let back_to_back = run(0);
+    let appends = back_to_back.iter().filter(|&&l| l <= 4 * t.page_program).count();
+    assert!(appends >= 395, "{} plain appends", appends);
+    // Compaction programs the snapshot (header in two, record header, two
+    // data pages) before the append; back to back, it first has to wait out
+    // the previous compaction's erase, which appends kept suspended
+    let worst = *back_to_back.iter().max().unwrap();
+    assert!(worst > t.sector_erase && worst < t.sector_erase + 10 * t.page_program, "worst {} ns", worst);
+
+    // With 1 ms between writes the erase finishes in the background
+    let worst = *run(1_000_000).iter().max().unwrap();
+    assert!(worst < 10 * t.page_program, "worst {} ns", worst);
+}
+
+#[test]
+fn reopen_from_saved_image_and_fork() {
+    let mut ee = new_mock(0, 4096, 64).unwrap();
+    ee.write(0, &[1, 2, 3, 4]).unwrap();
+    let image = ee.into_flash().unwrap().image().to_vec();
+
+    // two branches from the same image go their own ways
+    let mut a = new_mock_from_image(0, 4096, 64, image.clone()).unwrap();
+    let mut b = new_mock_from_image(0, 4096, 64, image.clone()).unwrap();
+    a.write(0, &[9]).unwrap();
+    b.write(2, &[8]).unwrap();
+    let mut out = [0u8; 4];
+    a.read(0, &mut out).unwrap();
+    assert_eq!(out, [9, 2, 3, 4]);
+    b.read(0, &mut out).unwrap();
+    assert_eq!(out, [1, 2, 8, 4]);
+
+    let a = a.into_flash().unwrap();
+    let changed = a.diff(&image);
+    assert_eq!(changed.len(), 1, "one appended record: {:?}", changed);
+    assert!(new_mock_from_image(0, 4096, 64, vec![0xFF; 100]).is_err());
+}
@@ -0,0 +1,79 @@
+//! flash_trace decode <trace>
+//! flash_trace diff <trace> [mem_size] [--option value ...]
+//!
+//! `diff` replays a captured trace against `AxiSpiModel` (the Rust port of
+//! the C sim) and reports the first read that answers differently. The
+//! model must match the capture: options set the `NorConfig` fields
+//! (`--mem-size`, `--page-size`, `--sector-size`, `--prog-ticks`,
+//! `--erase-ticks`, `--dummy-cycles`, `--wake-ticks`) and the FIFO depth
+//! (`--fifo`); the rest keep their defaults.
+
+use anyhow::{bail, Context, Result};
+use flash_axi::model::{AxiSpiModel, NorConfig};
+use flash_axi::trace::{decode, diff, replay, transaction_at, Trace};
+
+const USAGE: &str = "usage: flash_trace decode <trace> | flash_trace diff <trace> [mem_size] [--option value ...]";
+
+fn load(path: &str) -> Result<Trace> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+    Trace::parse(&text)
+}
+
+/// Model config and FIFO depth from the `diff` arguments after the trace.
+fn model_config(args: &[&str]) -> Result<(NorConfig, usize)> {
+    let mut cfg = NorConfig::default();
+    let mut fifo = 1024;
+    let mut args = args;
+    if let [size, rest @ ..] = args {
+        if !size.starts_with("--") {
+            cfg.mem_size = size.parse().context("mem_size")?;
+            args = rest;
+        }
+    }
+    for pair in args.chunks(2) {
+        let [name, value] = pair else { bail!("{} needs a value", pair[0]) };
+        let v: u32 = value.parse().with_context(|| format!("{} {:?}", name, value))?;
+        match *name {
+            "--mem-size" => cfg.mem_size = v,
+            "--page-size" => cfg.page_size = v,
+            "--sector-size" => cfg.sector_size = v,
+            "--prog-ticks" => cfg.prog_busy_ticks = v,
+            "--erase-ticks" => cfg.erase_busy_ticks = v,
+            "--dummy-cycles" => cfg.read_dummy_cycles = v,
+            "--wake-ticks" => cfg.wake_ticks = v,
+            "--fifo" => fifo = v as usize,
+            _ => bail!("unknown option {}\n{}", name, USAGE),
+        }
+    }
+    Ok((cfg, fifo))
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["decode", path] => {
+            for t in decode(&load(path)?) {
+                println!("{:>8} {}", t.tick, t);
+            }
+        }
+        ["diff", path, ref rest @ ..] => {
+            let captured = load(path)?;
+            let (cfg, fifo) = model_config(rest)?;
+            let mut sim = AxiSpiModel::with_config(cfg, fifo)?;
+            let replayed = replay(&captured, &mut sim);
+            match diff(&captured, &replayed) {
+                None => println!("{} accesses match", captured.accesses.len()),
+                Some(d) => {
+                    let txns = decode(&captured);
+                    match transaction_at(&txns, d.index) {
+                        Some(t) => println!("{} (in {} at tick {})", d, t, t.tick),
+                        None => println!("{}", d),
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
@@ -0,0 +1,534 @@
+//! Pure-Rust port of `sw/flash_ll/src/flash_ll.c`: the same register
+//! sequences and error codes over any `RegisterIo`, without C or bindgen.
+//! Keep the two in step; `flash_ll/tests/diff_tests.rs` compares them
+//! access by access on the C simulator. `model` is the matching port of
+//! the simulator itself, and `trace` records, replays and decodes register
+//! traffic.
+
+pub mod model;
+pub mod trace;
+
+use std::ops::Range;
+
+use anyhow::Result;
+use flash_core::regs::*;
+use flash_core::{Flash, FlashError, FlashGeometry, Otp, PowerState, ReadMode, RegisterIo, Timeouts};
+
+// Same values as `FlashLlErr`, so errors read the same as from `flash_ll::Driver`.
+const EINVAL: i32 = -1;
+const EIO: i32 = -2;
+const EBUSY: i32 = -3;
+const ETIME: i32 = -4;
+const EOOB: i32 = -5;
+const EPROT: i32 = -6;
+const ESLEEP: i32 = -7;
+
+/// 16 MiB reachable with 3 address bytes.
+const ADDR3_LIMIT: u32 = 1 << 24;
+
+type Rc<T = ()> = std::result::Result<T, i32>;
+
+pub struct AxiDriver<R: RegisterIo> {
+    io: R,
+    mem_size: u32,
+    page_size: u32,
+    sector_size: u32,
+    addr_bytes: u8,
+    read_cmd: u8,
+    read_dummy_cycles: u8,
+    /// Set by `suspend` until `resume`.
+    suspended: bool,
+    /// Sector of the last `sector_erase_start`.
+    erase_addr: u32,
+    powered_down: bool,
+    timeouts: Timeouts,
+    poll_yield: Option<Box<dyn FnMut() + Send>>,
+}
+
+// Data lanes encoding of SPI_FMT for a (3-byte) read opcode
+fn read_lanes(cmd: u8) -> Option<u32> {
+    match cmd {
+        CMD_READ | CMD_FAST_READ => Some(0),
+        CMD_DREAD => Some(1),
+        CMD_QREAD => Some(2),
+        _ => None,
+    }
+}
+
+fn read_cmd4(cmd: u8) -> u8 {
+    match cmd {
+        CMD_FAST_READ => CMD_FAST_READ4,
+        CMD_DREAD => CMD_DREAD4,
+        CMD_QREAD => CMD_QREAD4,
+        _ => CMD_READ4,
+    }
+}
+
+fn overlaps(a: u32, len: u32, r: Range<u32>) -> bool {
+    r.start < r.end && a < r.end && a as u64 + len as u64 > r.start as u64
+}
+
+fn rc_result<T>(rc: Rc<T>, what: &'static str) -> Result<T> {
+    match rc {
+        Ok(v) => Ok(v),
+        Err(ETIME) => Err(FlashError::Timeout { op: what }.into()),
+        Err(rc) => anyhow::bail!("{} failed: {}", what, rc),
+    }
+}
+
+impl<R: RegisterIo> AxiDriver<R> {
+    /// Driver over `io`; parts above 16 MiB get the 4-byte opcodes.
+    pub fn new(io: R, geometry: FlashGeometry) -> Result<Self> {
+        let FlashGeometry { mem_size, page_size, sector_size } = geometry;
+        if page_size == 0 || sector_size == 0 || mem_size == 0 {
+            anyhow::bail!("init failed: {}", EINVAL);
+        }
+        Ok(AxiDriver {
+            io,
+            mem_size,
+            page_size,
+            sector_size,
+            addr_bytes: if mem_size > ADDR3_LIMIT { 4 } else { 3 },
+            read_cmd: CMD_READ,
+            read_dummy_cycles: 0,
+            suspended: false,
+            erase_addr: 0,
+            powered_down: false,
+            timeouts: Timeouts::default(),
+            poll_yield: None,
+        })
+    }
+
+    pub fn timeouts(&self) -> Timeouts { self.timeouts }
+
+    /// Ops that stay busy longer fail with `FlashError::Timeout`.
+    pub fn set_timeouts(&mut self, t: Timeouts) { self.timeouts = t; }
+
+    /// Call `f` after every busy poll instead of spinning flat out.
+    pub fn set_poll_yield<F: FnMut() + Send + 'static>(&mut self, f: F) {
+        self.poll_yield = Some(Box::new(f));
+    }
+
+    pub fn clear_poll_yield(&mut self) { self.poll_yield = None; }
+
+    pub fn io(&self) -> &R { &self.io }
+    pub fn io_mut(&mut self) -> &mut R { &mut self.io }
+    pub fn into_io(self) -> R { self.io }
+
+    /// 3, or 4 for parts above 16 MiB.
+    pub fn addr_bytes(&self) -> u8 {
+        self.addr_bytes
+    }
+
+    /// Select the read opcode; the 4-byte variant is used automatically.
+    pub fn set_read_mode(&mut self, mode: ReadMode) -> Result<()> {
+        let (cmd, dummy) = mode.cmd_and_dummy();
+        if cmd == CMD_READ && dummy != 0 {
+            anyhow::bail!("set_read_mode failed: {}", EINVAL);
+        }
+        self.read_cmd = cmd;
+        self.read_dummy_cycles = dummy;
+        Ok(())
+    }
+
+    pub fn read_mode(&self) -> ReadMode {
+        ReadMode::from_cmd(self.read_cmd, self.read_dummy_cycles)
+    }
+
+    /// Program BP2..BP0/TB so that exactly `range` is write-protected; an
+    /// empty range clears protection. Only the ranges the part can encode
+    /// (top/bottom 1/64 .. 1/2, or everything) are accepted.
+    pub fn set_protection(&mut self, range: Range<u32>) -> Result<()> {
+        let status = if range.is_empty() {
+            Some(0u8)
+        } else {
+            (1u8..8)
+                .flat_map(|bp| [bp << SR_BP_SHIFT, (bp << SR_BP_SHIFT) | SR_TB])
+                .find(|&st| protected_range(self.mem_size, st) == range)
+        };
+        let Some(status) = status else {
+            anyhow::bail!("protection range {:#x}..{:#x} not encodable in BP/TB", range.start, range.end);
+        };
+        let rc = self.wrsr(status);
+        rc_result(rc, "wrsr")
+    }
+
+    /// Currently protected range as reported by the status register.
+    pub fn protection(&mut self) -> Result<Range<u32>> {
+        let st = self.rdsr()?;
+        Ok(protected_range(self.mem_size, st))
+    }
+
+    pub fn rdsr(&mut self) -> Result<u8> {
+        if self.powered_down { anyhow::bail!("rdsr failed: {}", ESLEEP); }
+        Ok(self.rdsr_once())
+    }
+
+    /// Status register 2; bit 7 is SUS.
+    pub fn rdsr2(&mut self) -> Result<u8> {
+        if self.powered_down { anyhow::bail!("rdsr2 failed: {}", ESLEEP); }
+        Ok(self.rdsr2_once())
+    }
+
+    pub fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        let rc = self.read_array(addr, buf);
+        rc_result(rc, "read")
+    }
+
+    pub fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        match self.program_array(addr, data) {
+            Err(EPROT) => Err(FlashError::Protected { addr, len: data.len() as u32 }.into()),
+            rc => rc_result(rc, "program"),
+        }
+    }
+
+    pub fn sector_erase(&mut self, addr: u32) -> Result<()> {
+        let rc = self.erase_start(addr).and_then(|()| self.wait_busy(self.timeouts.erase));
+        self.erase_result(addr, rc, "sector_erase")
+    }
+
+    /// Issue the erase and return; poll `rdsr` (WIP) or `suspend` around other work.
+    pub fn sector_erase_start(&mut self, addr: u32) -> Result<()> {
+        let rc = self.erase_start(addr);
+        self.erase_result(addr, rc, "sector_erase_start")
+    }
+
+    fn erase_result(&self, addr: u32, rc: Rc, what: &'static str) -> Result<()> {
+        if rc == Err(EPROT) {
+            let len = self.sector_size;
+            return Err(FlashError::Protected { addr: addr - addr % len, len }.into());
+        }
+        rc_result(rc, what)
+    }
+
+    /// Suspend (0x75) a running erase. Returns `false` if the device was idle.
+    pub fn suspend(&mut self) -> Result<bool> {
+        let rc = self.suspend_op();
+        rc_result(rc, "suspend")
+    }
+
+    /// Resume (0x7A) a suspended erase; does not wait for it to finish.
+    pub fn resume(&mut self) -> Result<()> {
+        if self.powered_down { anyhow::bail!("resume failed: {}", ESLEEP); }
+        if self.suspended {
+            self.start_cmd(CMD_RESUME, 0, 0);
+            self.suspended = false;
+        }
+        Ok(())
+    }
+
+    /// One busy poll with a single tick of back-off, so callers spinning on
+    /// it also advance a simulated device.
+    pub fn is_busy(&mut self) -> Result<bool> {
+        match self.wait_busy(1) {
+            Ok(()) => Ok(false),
+            Err(ETIME) => Ok(true),
+            Err(rc) => anyhow::bail!("is_busy failed: {}", rc),
+        }
+    }
+
+    /// Enter deep power-down (0xB9); other calls fail until `release_power_down`.
+    pub fn deep_power_down(&mut self) -> Result<()> {
+        if self.powered_down { return Ok(()); }
+        if self.rdsr_once() & SR_WIP != 0 { anyhow::bail!("deep_power_down failed: {}", EBUSY); }
+        self.start_cmd(CMD_DP, 0, 0);
+        self.powered_down = true;
+        Ok(())
+    }
+
+    /// Leave deep power-down (0xAB), waiting out the wake-up latency.
+    pub fn release_power_down(&mut self) -> Result<()> {
+        self.start_cmd(CMD_RDP, 0, 0);
+        self.powered_down = false;
+        // Status reads as all ones (WIP set) until the part is awake again
+        let rc = self.wait_busy(self.timeouts.reset);
+        rc_result(rc, "release_power_down")
+    }
+
+    /// Software reset (0x66 + 0x99): aborts in-flight or suspended ops, clears WEL.
+    pub fn reset(&mut self) -> Result<()> {
+        if self.powered_down { anyhow::bail!("reset failed: {}", ESLEEP); }
+        self.start_cmd(CMD_RSTEN, 0, 0);
+        self.start_cmd(CMD_RST, 0, 0);
+        self.suspended = false;
+        let rc = self.wait_busy(self.timeouts.reset);
+        rc_result(rc, "reset")
+    }
+
+    /// Read security register `reg` (0..3) from `offset`.
+    pub fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> Result<()> {
+        let rc = self.otp_read_op(reg, offset, buf);
+        rc_result(rc, "otp_read")
+    }
+
+    pub fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> Result<()> {
+        let rc = self.otp_program_op(reg, offset, data);
+        Self::otp_result(reg, rc, "otp_program")
+    }
+
+    pub fn otp_erase(&mut self, reg: u8) -> Result<()> {
+        let rc = self.otp_erase_op(reg);
+        Self::otp_result(reg, rc, "otp_erase")
+    }
+
+    /// Set the register's lock bit. This cannot be undone on a real part.
+    pub fn otp_lock(&mut self, reg: u8) -> Result<()> {
+        let rc = self.otp_lock_op(reg);
+        rc_result(rc, "otp_lock")
+    }
+
+    pub fn otp_is_locked(&mut self, reg: u8) -> Result<bool> {
+        if reg >= OTP_REGS { anyhow::bail!("no OTP register {}", reg); }
+        Ok(self.rdsr2()? & (SR2_LB1 << reg) != 0)
+    }
+
+    fn otp_result(reg: u8, rc: Rc, what: &'static str) -> Result<()> {
+        if rc == Err(EPROT) { return Err(FlashError::OtpLocked { reg }.into()); }
+        rc_result(rc, what)
+    }
+
+    // ---- register-level helpers, one per static function in flash_ll.c ----
+
+    fn start_cmd(&mut self, cmd: u8, addr: u32, len: u32) {
+        let addr = if self.addr_bytes == 4 { addr } else { addr & 0xFF_FFFF };
+        self.io.write(REG_SPI_CMD, cmd as u32);
+        self.io.write(REG_SPI_ADDR, addr);
+        self.io.write(REG_SPI_LEN, len);
+        self.io.write(REG_SPI_CTRL, CTRL_CS_EN | CTRL_START);
+    }
+
+    // Pick the dedicated 4-byte opcode when the part needs 4 address bytes, so
+    // the driver never depends on the volatile EN4B/EX4B mode of the device.
+    fn addr_cmd(&self, cmd3: u8, cmd4: u8) -> u8 {
+        if self.addr_bytes == 4 { cmd4 } else { cmd3 }
+    }
+
+    fn rdsr_once(&mut self) -> u8 {
+        self.start_cmd(CMD_RDSR, 0, 1);
+        self.io.read(REG_SPI_DOUT) as u8
+    }
+
+    fn rdsr2_once(&mut self) -> u8 {
+        self.start_cmd(CMD_RDSR2, 0, 1);
+        self.io.read(REG_SPI_DOUT) as u8
+    }
+
+    fn wren(&mut self) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        self.start_cmd(CMD_WREN, 0, 0);
+        Ok(())
+    }
+
+    fn wait_busy(&mut self, mut max_ticks: u32) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        loop {
+            if self.rdsr_once() & SR_WIP == 0 { return Ok(()); }
+            if max_ticks == 0 { return Err(ETIME); }
+            self.io.tick(1);
+            if let Some(f) = &mut self.poll_yield { f(); }
+            max_ticks -= 1;
+        }
+    }
+
+    fn check_oob(&self, addr: u32, len: u32) -> Rc {
+        if addr >= self.mem_size || len > self.mem_size - addr { return Err(EOOB); }
+        Ok(())
+    }
+
+    // The device silently drops PP/SE while busy, into the sector of a suspended
+    // erase, or into protected blocks, so refuse them up front
+    fn check_ready(&mut self, addr: u32, len: u32) -> Rc {
+        let st = self.rdsr_once();
+        if st & SR_WIP != 0 { return Err(EBUSY); }
+        if self.suspended {
+            let es = self.erase_addr - self.erase_addr % self.sector_size;
+            if overlaps(addr, len, es..es + self.sector_size) { return Err(EBUSY); }
+        }
+        if overlaps(addr, len, protected_range(self.mem_size, st)) { return Err(EPROT); }
+        Ok(())
+    }
+
+    fn read_array(&mut self, addr: u32, buf: &mut [u8]) -> Rc {
+        if buf.is_empty() { return Err(EINVAL); }
+        if self.powered_down { return Err(ESLEEP); }
+        self.check_oob(addr, buf.len() as u32)?;
+        let cmd = self.read_cmd;
+        let lanes = read_lanes(cmd).unwrap_or(0);
+        let fmt = self.read_dummy_cycles as u32 | lanes << 8;
+        let cmd = self.addr_cmd(cmd, read_cmd4(cmd));
+        self.read_data(cmd, addr, buf, fmt)
+    }
+
+    // Issue a read-type command and drain LEN bytes from DOUT. SPI_FMT only
+    // applies to this transfer; plain READ keeps the reset value (x1, no dummy).
+    fn read_data(&mut self, cmd: u8, addr: u32, buf: &mut [u8], fmt: u32) -> Rc {
+        if fmt != 0 { self.io.write(REG_SPI_FMT, fmt); }
+        self.start_cmd(cmd, addr, buf.len() as u32);
+        let mut read_cnt = 0;
+        let mut budget = (buf.len() as u32).wrapping_mul(8).wrapping_add(self.timeouts.fifo_slack);
+        while read_cnt < buf.len() && budget > 0 {
+            budget -= 1;
+            if self.io.read(REG_SPI_STATUS) & STATUS_RX_AVAIL != 0 {
+                buf[read_cnt] = self.io.read(REG_SPI_DOUT) as u8;
+                read_cnt += 1;
+            } else {
+                self.io.tick(1);
+            }
+        }
+        if fmt != 0 { self.io.write(REG_SPI_FMT, 0); }
+        if read_cnt == buf.len() { Ok(()) } else { Err(ETIME) } // FIFO budget ran out
+    }
+
+    // Push `data` into TX; ETIME if the FIFO budget runs out first
+    fn tx_write_all(&mut self, data: &[u8]) -> Rc {
+        let mut sent = 0;
+        let mut budget = (data.len() as u32).wrapping_mul(8).wrapping_add(self.timeouts.fifo_slack);
+        while sent < data.len() && budget > 0 {
+            budget -= 1;
+            if self.io.read(REG_SPI_STATUS) & STATUS_TX_SPACE != 0 {
+                self.io.write(REG_SPI_DIN, data[sent] as u32);
+                sent += 1;
+            } else {
+                self.io.tick(1);
+            }
+        }
+        if sent == data.len() { Ok(()) } else { Err(ETIME) }
+    }
+
+    fn wrsr(&mut self, status: u8) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        self.wren()?;
+        self.tx_write_all(&[status])?;
+        self.start_cmd(CMD_WRSR, 0, 1);
+        self.wait_busy(self.timeouts.status)?;
+        let mask = SR_BP_MASK | SR_TB;
+        if self.rdsr_once() & mask == status & mask { Ok(()) } else { Err(EIO) }
+    }
+
+    fn program_array(&mut self, mut addr: u32, data: &[u8]) -> Rc {
+        if data.is_empty() { return Err(EINVAL); }
+        if self.powered_down { return Err(ESLEEP); }
+        self.check_oob(addr, data.len() as u32)?;
+        self.check_ready(addr, data.len() as u32)?;
+        let mut rest = data;
+        while !rest.is_empty() {
+            let room = (self.page_size - addr % self.page_size) as usize;
+            let (chunk, tail) = rest.split_at(rest.len().min(room));
+            self.wren()?;
+            self.tx_write_all(chunk)?;
+            let cmd = self.addr_cmd(CMD_PP, CMD_PP4);
+            self.start_cmd(cmd, addr, chunk.len() as u32);
+            self.wait_busy(self.timeouts.program)?;
+            addr += chunk.len() as u32;
+            rest = tail;
+        }
+        Ok(())
+    }
+
+    fn erase_start(&mut self, addr: u32) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        if addr >= self.mem_size { return Err(EOOB); }
+        if self.suspended { return Err(EBUSY); } // no erase while one is suspended
+        let base = addr - addr % self.sector_size;
+        let n = self.sector_size.min(self.mem_size - base);
+        self.check_ready(base, n)?;
+        self.wren()?;
+        let cmd = self.addr_cmd(CMD_SE, CMD_SE4);
+        self.start_cmd(cmd, addr, 0);
+        self.erase_addr = base;
+        Ok(())
+    }
+
+    fn suspend_op(&mut self) -> Rc<bool> {
+        if self.powered_down { return Err(ESLEEP); }
+        if self.suspended { return Ok(true); }
+        if self.rdsr_once() & SR_WIP == 0 { return Ok(false); } // nothing running
+        self.start_cmd(CMD_SUSPEND, 0, 0);
+        self.wait_busy(self.timeouts.suspend)?; // suspend latency
+        // The op may have finished just before the suspend landed
+        self.suspended = self.rdsr2_once() & SR2_SUS != 0;
+        Ok(self.suspended)
+    }
+
+    // Security register n lives at (n + 1) << 12 on Winbond-style parts
+    fn otp_addr(reg: u8, off: u32, len: usize) -> Rc<u32> {
+        if reg >= OTP_REGS { return Err(EINVAL); }
+        if off >= OTP_SIZE || len as u64 > (OTP_SIZE - off) as u64 { return Err(EOOB); }
+        Ok((reg as u32 + 1) << 12 | off)
+    }
+
+    // The device drops OTP program/erase while busy or while an erase is suspended
+    fn otp_check_unlocked(&mut self, reg: u8) -> Rc {
+        if self.suspended || self.rdsr_once() & SR_WIP != 0 { return Err(EBUSY); }
+        if self.rdsr2_once() & (SR2_LB1 << reg) != 0 { Err(EPROT) } else { Ok(()) }
+    }
+
+    fn otp_read_op(&mut self, reg: u8, off: u32, buf: &mut [u8]) -> Rc {
+        if buf.is_empty() { return Err(EINVAL); }
+        if self.powered_down { return Err(ESLEEP); }
+        let addr = Self::otp_addr(reg, off, buf.len())?;
+        self.read_data(CMD_OTP_READ, addr, buf, 8) // x1, 8 dummy
+    }
+
+    fn otp_program_op(&mut self, reg: u8, off: u32, data: &[u8]) -> Rc {
+        if data.is_empty() { return Err(EINVAL); }
+        if self.powered_down { return Err(ESLEEP); }
+        let addr = Self::otp_addr(reg, off, data.len())?;
+        self.otp_check_unlocked(reg)?;
+        self.wren()?;
+        self.tx_write_all(data)?;
+        self.start_cmd(CMD_OTP_PROG, addr, data.len() as u32);
+        self.wait_busy(self.timeouts.program)
+    }
+
+    fn otp_erase_op(&mut self, reg: u8) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        let addr = Self::otp_addr(reg, 0, 0)?;
+        self.otp_check_unlocked(reg)?;
+        self.wren()?;
+        self.start_cmd(CMD_OTP_ERASE, addr, 0);
+        self.wait_busy(self.timeouts.erase)
+    }
+
+    fn otp_lock_op(&mut self, reg: u8) -> Rc {
+        if self.powered_down { return Err(ESLEEP); }
+        if reg >= OTP_REGS { return Err(EINVAL); }
+        let lb = SR2_LB1 << reg;
+        let sr2 = self.rdsr2_once();
+        if sr2 & lb != 0 { return Ok(()); }
+        self.wren()?;
+        self.tx_write_all(&[sr2 | lb])?;
+        self.start_cmd(CMD_WRSR2, 0, 1);
+        self.wait_busy(self.timeouts.status)?;
+        if self.rdsr2_once() & lb != 0 { Ok(()) } else { Err(EIO) }
+    }
+}
+
+impl<R: RegisterIo> Flash for AxiDriver<R> {
+    fn geometry(&self) -> FlashGeometry {
+        FlashGeometry { mem_size: self.mem_size, page_size: self.page_size, sector_size: self.sector_size }
+    }
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> { self.read(addr, buf) }
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> { self.program(addr, data) }
+    fn sector_erase(&mut self, addr: u32) -> Result<()> { self.sector_erase(addr) }
+    fn rdsr(&mut self) -> Result<u8> { self.rdsr() }
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> { self.sector_erase_start(addr) }
+    fn is_busy(&mut self) -> Result<bool> { self.is_busy() }
+    fn suspend(&mut self) -> Result<bool> { self.suspend() }
+    fn resume(&mut self) -> Result<()> { self.resume() }
+    fn set_power(&mut self, state: PowerState) -> Result<()> {
+        match state {
+            PowerState::Active => self.release_power_down(),
+            PowerState::DeepPowerDown => self.deep_power_down(),
+        }
+    }
+}
+
+impl<R: RegisterIo> Otp for AxiDriver<R> {
+    fn otp_layout(&self) -> (u8, u32) { (OTP_REGS, OTP_SIZE) }
+    fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> Result<()> { self.otp_read(reg, offset, buf) }
+    fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> Result<()> { self.otp_program(reg, offset, data) }
+    fn otp_erase(&mut self, reg: u8) -> Result<()> { self.otp_erase(reg) }
+    fn otp_lock(&mut self, reg: u8) -> Result<()> { self.otp_lock(reg) }
+    fn otp_is_locked(&mut self, reg: u8) -> Result<bool> { self.otp_is_locked(reg) }
+}
@@ -0,0 +1,665 @@
+//! Pure-Rust port of the C simulator: `NorModel` mirrors `flash_sim.c` and
+//! `AxiSpiModel` mirrors `axi_spi_sim.c`, down to FIFO limits, status bits,
+//! busy ticks and bus cycle counts. `flash_ll/tests/lockstep_tests.rs`
+//! checks both against the C sim access by access; change them together.
+
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use flash_core::regs::*;
+use flash_core::{FlashGeometry, RegisterIo};
+
+/// Same fields and meaning as `FlashSimConfig`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NorConfig {
+    pub mem_size: u32,
+    pub page_size: u32,
+    pub sector_size: u32,
+    pub prog_busy_ticks: u32,
+    pub erase_busy_ticks: u32,
+    /// Dummy clocks expected by FAST/DUAL/QUAD reads (0 = 8).
+    pub read_dummy_cycles: u32,
+    /// Release-from-deep-power-down latency (tRES1).
+    pub wake_ticks: u32,
+}
+
+impl Default for NorConfig {
+    fn default() -> Self {
+        NorConfig {
+            mem_size: 8192,
+            page_size: 256,
+            sector_size: 4096,
+            prog_busy_ticks: 4,
+            erase_busy_ticks: 64,
+            read_dummy_cycles: 8,
+            wake_ticks: 3,
+        }
+    }
+}
+
+impl NorConfig {
+    pub fn geometry(&self) -> FlashGeometry {
+        FlashGeometry { mem_size: self.mem_size, page_size: self.page_size, sector_size: self.sector_size }
+    }
+}
+
+/// Operation that owns WIP (or is suspended).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusyOp {
+    None,
+    Program,
+    Erase,
+    Wrsr,
+}
+
+const SR_WRSR_MASK: u8 = SR_BP_MASK | SR_TB;
+const SR2_LB_MASK: u8 = SR2_LB1 | SR2_LB1 << 1 | SR2_LB1 << 2;
+/// PP data the engine forwards per command (`buf[256]` in the C sim).
+const PP_BUF: usize = 256;
+
+/// SPI NOR array, status registers and security registers.
+pub struct NorModel {
+    cfg: NorConfig,
+    mem: Vec<u8>,
+    status: u8,
+    busy_ticks: u32,
+    addr4: bool,
+    status2: u8,
+    busy_op: BusyOp,
+    busy_addr: u32,
+    susp_op: BusyOp,
+    susp_addr: u32,
+    susp_ticks: u32,
+    powered_down: bool,
+    wake_ticks: u32,
+    reset_enabled: bool,
+    otp: [[u8; OTP_SIZE as usize]; OTP_REGS as usize],
+}
+
+impl NorModel {
+    pub fn new(cfg: NorConfig) -> Result<Self> {
+        if cfg.mem_size == 0 || cfg.page_size == 0 || cfg.sector_size == 0 {
+            anyhow::bail!("invalid NOR geometry: {:?}", cfg);
+        }
+        let mut cfg = cfg;
+        if cfg.read_dummy_cycles == 0 { cfg.read_dummy_cycles = 8; }
+        Ok(NorModel {
+            mem: vec![0xFF; cfg.mem_size as usize],
+            cfg,
+            status: 0,
+            busy_ticks: 0,
+            addr4: false,
+            status2: 0,
+            busy_op: BusyOp::None,
+            busy_addr: 0,
+            susp_op: BusyOp::None,
+            susp_addr: 0,
+            susp_ticks: 0,
+            powered_down: false,
+            wake_ticks: 0,
+            reset_enabled: false,
+            otp: [[0xFF; OTP_SIZE as usize]; OTP_REGS as usize],
+        })
+    }
+
+    pub fn config(&self) -> &NorConfig { &self.cfg }
+    pub fn mem(&self) -> &[u8] { &self.mem }
+    pub fn otp(&self, reg: u8) -> &[u8] { &self.otp[reg as usize] }
+    pub fn busy_op(&self) -> BusyOp { self.busy_op }
+    pub fn busy_ticks(&self) -> u32 { self.busy_ticks }
+    pub fn addr4(&self) -> bool { self.addr4 }
+
+    /// Advance time; clears WIP when the running op finishes.
+    pub fn tick(&mut self, ticks: u32) {
+        self.wake_ticks = self.wake_ticks.saturating_sub(ticks);
+        if self.busy_ticks > 0 {
+            if ticks >= self.busy_ticks {
+                self.busy_ticks = 0;
+                self.busy_op = BusyOp::None;
+                self.status &= !SR_WIP;
+            } else {
+                self.busy_ticks -= ticks;
+            }
+        }
+    }
+
+    /// Powered up and past the wake-up latency: the part accepts commands.
+    pub fn awake(&self) -> bool {
+        !self.powered_down && self.wake_ticks == 0
+    }
+
+    pub fn rdsr(&self) -> u8 { self.status }
+    pub fn rdsr2(&self) -> u8 { self.status2 }
+
+    pub fn wren(&mut self) {
+        if self.awake() { self.status |= SR_WEL; }
+    }
+
+    pub fn en4b(&mut self) {
+        if self.awake() { self.addr4 = true; }
+    }
+
+    pub fn ex4b(&mut self) {
+        if self.awake() { self.addr4 = false; }
+    }
+
+    /// Deep power-down; ignored while busy. Returns whether it was accepted.
+    pub fn deep_power_down(&mut self) -> bool {
+        if !self.awake() || self.status & SR_WIP != 0 { return false; }
+        self.powered_down = true;
+        true
+    }
+
+    /// Commands stay ignored for `wake_ticks` afterwards.
+    pub fn release_power_down(&mut self) {
+        if self.powered_down {
+            self.powered_down = false;
+            self.wake_ticks = self.cfg.wake_ticks;
+        }
+    }
+
+    pub fn reset_enable(&mut self) {
+        if self.awake() { self.reset_enabled = true; }
+    }
+
+    /// Needs `reset_enable` right before it. Clears WEL and SUS, aborts any
+    /// in-flight or suspended op, exits 4-byte mode; BP/TB, lock bits and the
+    /// array keep their contents.
+    pub fn reset(&mut self) -> bool {
+        if !self.awake() || !self.reset_enabled { return false; }
+        self.reset_enabled = false;
+        self.status &= !(SR_WIP | SR_WEL);
+        self.status2 &= !SR2_SUS;
+        self.busy_ticks = 0;
+        self.busy_op = BusyOp::None;
+        self.susp_op = BusyOp::None;
+        self.susp_ticks = 0;
+        self.addr4 = false;
+        true
+    }
+
+    /// Park a running program/erase: WIP clears, SUS sets.
+    pub fn suspend(&mut self) -> bool {
+        if !self.awake() || self.status2 & SR2_SUS != 0 || self.status & SR_WIP == 0 { return false; }
+        if self.busy_op != BusyOp::Program && self.busy_op != BusyOp::Erase { return false; }
+        self.susp_op = self.busy_op;
+        self.susp_addr = self.busy_addr;
+        self.susp_ticks = self.busy_ticks;
+        self.busy_op = BusyOp::None;
+        self.busy_ticks = 0;
+        self.status &= !SR_WIP;
+        self.status2 |= SR2_SUS;
+        true
+    }
+
+    /// Resume the suspended op with its remaining ticks.
+    pub fn resume(&mut self) -> bool {
+        if !self.awake() || self.status2 & SR2_SUS == 0 || self.status & SR_WIP != 0 { return false; }
+        self.busy_op = self.susp_op;
+        self.busy_addr = self.susp_addr;
+        self.busy_ticks = self.susp_ticks;
+        self.susp_op = BusyOp::None;
+        self.susp_ticks = 0;
+        self.status2 &= !SR2_SUS;
+        if self.busy_ticks > 0 {
+            self.status |= SR_WIP;
+        } else {
+            self.busy_op = BusyOp::None;
+        }
+        true
+    }
+
+    // Status writes, OTP writes and erases need WEL, no WIP and no suspend
+    fn writable(&self) -> bool {
+        self.awake() && self.status & SR_WIP == 0 && self.status & SR_WEL != 0 && self.status2 & SR2_SUS == 0
+    }
+
+    fn start_busy(&mut self, op: BusyOp, addr: u32, ticks: u32) {
+        self.status |= SR_WIP;
+        self.status &= !SR_WEL;
+        self.busy_ticks = ticks;
+        self.busy_op = op;
+        self.busy_addr = addr;
+    }
+
+    /// Write Status Register (BP/TB); busy like a page program.
+    pub fn wrsr(&mut self, value: u8) -> bool {
+        if !self.writable() { return false; }
+        self.status = (self.status & !SR_WRSR_MASK) | (value & SR_WRSR_MASK);
+        let addr = self.busy_addr;
+        self.start_busy(BusyOp::Wrsr, addr, self.cfg.prog_busy_ticks);
+        true
+    }
+
+    /// Write Status Register 2: only the lock bits, which can never be cleared.
+    pub fn wrsr2(&mut self, value: u8) -> bool {
+        if !self.writable() { return false; }
+        self.status2 |= value & SR2_LB_MASK;
+        let addr = self.busy_addr;
+        self.start_busy(BusyOp::Wrsr, addr, self.cfg.prog_busy_ticks);
+        true
+    }
+
+    /// Protected `[start, end)` from BP/TB; `0..0` when nothing is protected.
+    pub fn protected_range(&self) -> std::ops::Range<u32> {
+        protected_range(self.cfg.mem_size, self.status)
+    }
+
+    fn overlaps_protected(&self, addr: u32, len: usize) -> bool {
+        let r = self.protected_range();
+        r.start < r.end && addr < r.end && addr as u64 + len as u64 > r.start as u64
+    }
+
+    // Register index for a security register address
+    fn otp_index(addr: u32) -> Option<usize> {
+        let n = (addr >> 12) & 0xFFF;
+        (1..=OTP_REGS as u32).contains(&n).then(|| n as usize - 1)
+    }
+
+    fn otp_locked(&self, idx: usize) -> bool {
+        self.status2 & (SR2_LB1 << idx) != 0
+    }
+
+    /// Security register bytes; accesses wrap inside the register and
+    /// invalid addresses read as 0xFF.
+    pub fn otp_read(&self, addr: u32, out: &mut [u8]) -> usize {
+        let idx = Self::otp_index(addr);
+        for (i, b) in out.iter_mut().enumerate() {
+            *b = match idx {
+                Some(idx) => self.otp[idx][(addr as usize + i) % OTP_SIZE as usize],
+                None => 0xFF,
+            };
+        }
+        out.len()
+    }
+
+    /// 1->0 like PP; rejected (WEL cleared) when locked.
+    pub fn otp_program(&mut self, addr: u32, data: &[u8]) -> usize {
+        if !self.awake() || data.is_empty() || !self.writable() { return 0; }
+        let idx = match Self::otp_index(addr) {
+            Some(idx) if !self.otp_locked(idx) => idx,
+            _ => {
+                self.status &= !SR_WEL;
+                return 0;
+            }
+        };
+        let data = &data[..data.len().min(OTP_SIZE as usize)];
+        for (i, &d) in data.iter().enumerate() {
+            self.otp[idx][(addr as usize + i) % OTP_SIZE as usize] &= d;
+        }
+        self.start_busy(BusyOp::Program, addr, self.cfg.prog_busy_ticks);
+        data.len()
+    }
+
+    pub fn otp_erase(&mut self, addr: u32) -> bool {
+        if !self.writable() { return false; }
+        let Some(idx) = Self::otp_index(addr) else { return false };
+        if self.otp_locked(idx) {
+            self.status &= !SR_WEL;
+            return false;
+        }
+        self.otp[idx] = [0xFF; OTP_SIZE as usize];
+        self.start_busy(BusyOp::Erase, addr, self.cfg.erase_busy_ticks);
+        true
+    }
+
+    /// Array bytes from `addr`, clamped to the end of the array.
+    pub fn read(&self, addr: u32, out: &mut [u8]) -> usize {
+        if out.is_empty() || addr >= self.cfg.mem_size { return 0; }
+        let n = out.len().min((self.cfg.mem_size - addr) as usize);
+        out[..n].copy_from_slice(&self.mem[addr as usize..addr as usize + n]);
+        n
+    }
+
+    /// Page Program up to the page boundary, 1->0 only. Returns bytes programmed.
+    pub fn page_program(&mut self, addr: u32, data: &[u8]) -> usize {
+        if !self.awake() || data.is_empty() { return 0; }
+        if self.status & SR_WIP != 0 || self.status & SR_WEL == 0 { return 0; }
+        if addr >= self.cfg.mem_size { return 0; }
+        let in_page = (self.cfg.page_size - addr % self.cfg.page_size) as usize;
+        let n = data.len().min(in_page).min((self.cfg.mem_size - addr) as usize);
+        if self.overlaps_protected(addr, n) {
+            self.status &= !SR_WEL;
+            return 0;
+        }
+        if self.status2 & SR2_SUS != 0 {
+            // Only an erase suspend allows programming, and not inside that sector
+            let sec = self.cfg.sector_size;
+            if self.susp_op != BusyOp::Erase || addr / sec == self.susp_addr / sec { return 0; }
+        }
+        for (m, &d) in self.mem[addr as usize..addr as usize + n].iter_mut().zip(data) {
+            *m &= d;
+        }
+        self.start_busy(BusyOp::Program, addr, self.cfg.prog_busy_ticks);
+        n
+    }
+
+    /// Sector Erase; refused (WEL cleared) when the sector is protected.
+    pub fn sector_erase(&mut self, addr: u32) -> bool {
+        if !self.writable() || addr >= self.cfg.mem_size { return false; }
+        let base = addr - addr % self.cfg.sector_size;
+        let n = self.cfg.sector_size.min(self.cfg.mem_size - base) as usize;
+        if self.overlaps_protected(base, n) {
+            self.status &= !SR_WEL;
+            return false;
+        }
+        self.mem[base as usize..base as usize + n].fill(0xFF);
+        self.start_busy(BusyOp::Erase, base, self.cfg.erase_busy_ticks);
+        true
+    }
+}
+
+/// How an active read produces bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadStream {
+    Data,
+    /// Host samples lines the flash does not drive.
+    Ones,
+    /// Wrong dummy count: bytes come back bit-shifted by `rd_shift`.
+    Skew,
+}
+
+/// AXI-Lite SPI engine in front of a `NorModel`, speaking the `REG_SPI_*` map.
+pub struct AxiSpiModel {
+    nor: NorModel,
+    cmd: u8,
+    addr: u32,
+    len: u32,
+    ctrl: u32,
+    status: u32,
+    fmt: u32,
+    bus_cycles: u64,
+    fifo_cap: usize,
+    tx: VecDeque<u8>,
+    rx: VecDeque<u8>,
+    // read in progress: bytes [rd_pos, rd_len) are streamed into RX as it drains
+    rd_cmd: u8,
+    rd_mode: ReadStream,
+    rd_addr: u32,
+    rd_pos: u32,
+    rd_len: u32,
+    rd_shift: i32,
+}
+
+fn is_4byte_cmd(cmd: u8) -> bool {
+    matches!(cmd, CMD_READ4 | CMD_PP4 | CMD_SE4 | CMD_FAST_READ4 | CMD_DREAD4 | CMD_QREAD4)
+}
+
+// Read opcodes: data lanes (SPI_FMT encoding) and whether the flash inserts
+// dummy cycles before the data
+fn decode_read(cmd: u8) -> Option<(u32, bool)> {
+    match cmd {
+        CMD_READ | CMD_READ4 => Some((0, false)),
+        CMD_FAST_READ | CMD_FAST_READ4 => Some((0, true)),
+        CMD_DREAD | CMD_DREAD4 => Some((1, true)),
+        CMD_QREAD | CMD_QREAD4 => Some((2, true)),
+        CMD_OTP_READ => Some((0, true)),
+        _ => None,
+    }
+}
+
+impl AxiSpiModel {
+    pub fn new(nor: NorModel, fifo_cap: usize) -> Result<Self> {
+        if fifo_cap == 0 { anyhow::bail!("FIFO capacity must be non-zero"); }
+        let mut s = AxiSpiModel {
+            nor,
+            cmd: 0,
+            addr: 0,
+            len: 0,
+            ctrl: 0,
+            status: 0,
+            fmt: 0,
+            bus_cycles: 0,
+            fifo_cap,
+            tx: VecDeque::with_capacity(fifo_cap),
+            rx: VecDeque::with_capacity(fifo_cap),
+            rd_cmd: 0,
+            rd_mode: ReadStream::Data,
+            rd_addr: 0,
+            rd_pos: 0,
+            rd_len: 0,
+            rd_shift: 0,
+        };
+        s.update_status();
+        Ok(s)
+    }
+
+    /// `NorConfig::default()` with 1024-byte FIFOs matches `SimEnv::new()`.
+    pub fn with_config(cfg: NorConfig, fifo_cap: usize) -> Result<Self> {
+        Self::new(NorModel::new(cfg)?, fifo_cap)
+    }
+
+    pub fn nor(&self) -> &NorModel { &self.nor }
+    pub fn nor_mut(&mut self) -> &mut NorModel { &mut self.nor }
+    pub fn into_nor(self) -> NorModel { self.nor }
+
+    /// SPI clocks spent so far (opcode + address + dummy + data).
+    pub fn bus_cycles(&self) -> u64 { self.bus_cycles }
+
+    fn push_rx(&mut self, b: u8) {
+        if self.rx.len() < self.fifo_cap { self.rx.push_back(b); }
+    }
+
+    fn update_status(&mut self) {
+        if self.nor.busy_ticks > 0 { self.nor.status |= SR_WIP; }
+        self.status = 0;
+        if self.nor.status & SR_WIP != 0 { self.status |= STATUS_BUSY; }
+        if !self.rx.is_empty() { self.status |= STATUS_RX_AVAIL; }
+        if self.tx.len() < self.fifo_cap { self.status |= STATUS_TX_SPACE; }
+    }
+
+    // Address as seen by the flash: dedicated 4-byte opcodes always clock out
+    // 32 bits, legacy opcodes only do so once the part is in 4-byte mode.
+    fn four_byte(&self) -> bool {
+        is_4byte_cmd(self.cmd) || self.nor.addr4
+    }
+
+    fn cmd_addr(&self) -> u32 {
+        if self.four_byte() { self.addr } else { self.addr & 0xFF_FFFF }
+    }
+
+    fn cmd_addr_bytes(&self) -> u64 {
+        if self.four_byte() { 4 } else { 3 }
+    }
+
+    // Array or security registers, depending on the read opcode
+    fn stream_byte(&self, a: u32, idx: i64) -> u8 {
+        let mut b = [0xFF];
+        if idx >= 0 {
+            let a = a.wrapping_add(idx as u32);
+            if self.rd_cmd == CMD_OTP_READ {
+                self.nor.otp_read(a, &mut b);
+            } else {
+                self.nor.read(a, &mut b);
+            }
+        }
+        b[0]
+    }
+
+    // Next byte of the read stream at position i, as the host samples it
+    fn read_byte_at(&self, i: u32) -> u8 {
+        match self.rd_mode {
+            ReadStream::Ones => 0xFF,
+            ReadStream::Skew => {
+                let k0 = i as i64 * 8 - self.rd_shift as i64;
+                let q = k0.div_euclid(8);
+                let r = k0.rem_euclid(8) as u32;
+                let w = (self.stream_byte(self.rd_addr, q) as u16) << 8 | self.stream_byte(self.rd_addr, q + 1) as u16;
+                (w >> (8 - r)) as u8
+            }
+            ReadStream::Data => self.stream_byte(self.rd_addr, i as i64),
+        }
+    }
+
+    // Move as much of the active read into RX as fits; called on START and
+    // whenever the host drains DOUT, so reads can exceed the FIFO size.
+    fn rx_refill(&mut self) {
+        while self.rd_pos < self.rd_len && self.rx.len() < self.fifo_cap {
+            let b = self.read_byte_at(self.rd_pos);
+            self.rd_pos += 1;
+            self.rx.push_back(b);
+        }
+    }
+
+    fn do_read(&mut self, lanes: u32, fast: bool) {
+        let host_dummy = self.fmt & 0xFF;
+        let host_lanes = (self.fmt >> 8) & 0x3;
+        let width = 1i32 << lanes; // bits per SPI clock
+        let flash_dummy = if !fast {
+            0
+        } else if self.cmd == CMD_OTP_READ {
+            8
+        } else {
+            self.nor.cfg.read_dummy_cycles
+        };
+        self.bus_cycles += 8 + self.cmd_addr_bytes() * 8 + host_dummy as u64 + (self.len as u64 * 8) / (1u64 << host_lanes);
+
+        self.rd_addr = self.cmd_addr();
+        self.rd_pos = 0;
+        self.rd_len = self.len;
+        self.rd_shift = 0;
+        self.rd_mode = if self.nor.status & SR_WIP != 0 {
+            // Array reads are ignored while a program/erase runs (suspend first)
+            ReadStream::Ones
+        } else if host_lanes != lanes {
+            ReadStream::Ones
+        } else if host_dummy != flash_dummy {
+            self.rd_shift = (flash_dummy as i32 - host_dummy as i32) * width;
+            ReadStream::Skew
+        } else {
+            ReadStream::Data
+        };
+        self.rx_refill();
+    }
+
+    // Up to `max` bytes of PP/OTP data from TX
+    fn pop_tx(&mut self, max: usize) -> Vec<u8> {
+        let n = max.min(self.len as usize).min(self.tx.len());
+        self.tx.drain(..n).collect()
+    }
+
+    fn do_start(&mut self) {
+        // a new command ends any read still streaming
+        let cmd = self.cmd;
+        self.rd_len = 0;
+        self.rd_pos = 0;
+        self.rd_cmd = cmd;
+        if cmd != CMD_RSTEN && cmd != CMD_RST { self.nor.reset_enabled = false; }
+        let status_n = self.len.min(self.fifo_cap as u32) as u64;
+        if !self.nor.awake() && cmd != CMD_RDP {
+            // Powered down or still waking: the part ignores the command and
+            // does not drive DO, so anything clocked in reads as ones
+            self.bus_cycles += 8;
+            if decode_read(cmd).is_some() || cmd == CMD_RDSR || cmd == CMD_RDSR2 {
+                for _ in 0..self.len {
+                    if self.rx.len() >= self.fifo_cap { break; }
+                    self.rx.push_back(0xFF);
+                }
+            }
+        } else if cmd == CMD_RDP {
+            self.bus_cycles += 8;
+            self.nor.release_power_down();
+        } else if cmd == CMD_DP {
+            self.bus_cycles += 8;
+            self.nor.deep_power_down();
+        } else if cmd == CMD_RSTEN {
+            self.bus_cycles += 8;
+            self.nor.reset_enable();
+        } else if cmd == CMD_RST {
+            self.bus_cycles += 8;
+            self.nor.reset();
+        } else if let Some((lanes, fast)) = decode_read(cmd) {
+            self.do_read(lanes, fast);
+        } else if cmd == CMD_RDSR || cmd == CMD_RDSR2 {
+            self.bus_cycles += 8 + self.len as u64 * 8;
+            let st = if cmd == CMD_RDSR { self.nor.rdsr() } else { self.nor.rdsr2() };
+            for _ in 0..status_n {
+                self.push_rx(st);
+            }
+        } else if cmd == CMD_SUSPEND {
+            self.bus_cycles += 8;
+            self.nor.suspend();
+        } else if cmd == CMD_RESUME {
+            self.bus_cycles += 8;
+            self.nor.resume();
+        } else if cmd == CMD_WRSR || cmd == CMD_WRSR2 {
+            // status byte comes from TX like PP data
+            self.bus_cycles += 16;
+            if self.len > 0 {
+                if let Some(v) = self.tx.pop_front() {
+                    if cmd == CMD_WRSR { self.nor.wrsr(v); } else { self.nor.wrsr2(v); }
+                }
+            }
+        } else if cmd == CMD_OTP_PROG || cmd == CMD_PP || cmd == CMD_PP4 {
+            let max = if cmd == CMD_OTP_PROG { OTP_SIZE as usize } else { PP_BUF };
+            let data = self.pop_tx(max);
+            self.bus_cycles += 8 + self.cmd_addr_bytes() * 8 + data.len() as u64 * 8;
+            let addr = self.cmd_addr();
+            if cmd == CMD_OTP_PROG {
+                self.nor.otp_program(addr, &data);
+            } else {
+                self.nor.page_program(addr, &data);
+            }
+        } else if cmd == CMD_OTP_ERASE || cmd == CMD_SE || cmd == CMD_SE4 {
+            self.bus_cycles += 8 + self.cmd_addr_bytes() * 8;
+            let addr = self.cmd_addr();
+            if cmd == CMD_OTP_ERASE {
+                self.nor.otp_erase(addr);
+            } else {
+                self.nor.sector_erase(addr);
+            }
+        } else if cmd == CMD_WREN {
+            self.bus_cycles += 8;
+            self.nor.wren();
+        } else if cmd == CMD_EN4B {
+            self.bus_cycles += 8;
+            self.nor.en4b();
+        } else if cmd == CMD_EX4B {
+            self.bus_cycles += 8;
+            self.nor.ex4b();
+        }
+        self.ctrl &= !CTRL_START;
+        self.update_status();
+    }
+}
+
+impl RegisterIo for AxiSpiModel {
+    fn read(&mut self, offset: u32) -> u32 {
+        match offset {
+            REG_SPI_CMD => self.cmd as u32,
+            REG_SPI_ADDR => self.addr,
+            REG_SPI_LEN => self.len,
+            REG_SPI_DOUT => {
+                let b = self.rx.pop_front().unwrap_or(0);
+                self.rx_refill();
+                self.update_status();
+                b as u32
+            }
+            REG_SPI_CTRL => self.ctrl,
+            REG_SPI_STATUS => self.status,
+            REG_SPI_FMT => self.fmt,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            REG_SPI_CMD => self.cmd = value as u8,
+            REG_SPI_ADDR => self.addr = value,
+            REG_SPI_LEN => self.len = value,
+            REG_SPI_FMT => self.fmt = value & 0x3FF,
+            REG_SPI_DIN if self.tx.len() < self.fifo_cap => self.tx.push_back(value as u8),
+            REG_SPI_CTRL => {
+                self.ctrl = value;
+                if self.ctrl & CTRL_START != 0 { self.do_start(); }
+            }
+            _ => {}
+        }
+        self.update_status();
+    }
+
+    fn tick(&mut self, ticks: u32) {
+        self.nor.tick(ticks);
+        self.update_status();
+    }
+}
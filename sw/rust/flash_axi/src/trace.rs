@@ -0,0 +1,297 @@
+//! Register-access tracing for the AXI SPI engine.
+//!
+//! `TracingIo` wraps any `RegisterIo` (the C sim, `AxiSpiModel`, real MMIO)
+//! and records each access with the tick count it happened at. A trace can
+//! be saved as text, replayed against a model, diffed against the replay,
+//! and decoded into SPI transactions (`WREN`, `PP 0x000100 len 4`, ...).
+//!
+//! Text format, one access per line (`#` starts a comment):
+//!
+//! ```text
+//! W 0x00 0x00000006 0
+//! R 0x10 0x00000002 17
+//! ```
+//!
+//! kind (`R`/`W`), register offset, value, tick.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use flash_core::regs::*;
+use flash_core::RegisterIo;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub kind: AccessKind,
+    pub offset: u32,
+    pub value: u32,
+    /// Ticks passed to the backend before this access.
+    pub tick: u64,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let k = match self.kind {
+            AccessKind::Read => 'R',
+            AccessKind::Write => 'W',
+        };
+        write!(f, "{} {:#04x} {:#010x} {}", k, self.offset, self.value, self.tick)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub accesses: Vec<Access>,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for a in &self.accesses {
+            writeln!(f, "{}", a)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_u(s: &str) -> Result<u64> {
+    let v = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    v.with_context(|| format!("bad number {:?}", s))
+}
+
+fn parse_u32(s: &str) -> Result<u32> {
+    u32::try_from(parse_u(s)?).with_context(|| format!("{:?} does not fit in 32 bits", s))
+}
+
+impl Trace {
+    /// Parse the text format written by `Display`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut accesses = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let f: Vec<&str> = line.split_whitespace().collect();
+            let parsed = (|| -> Result<Access> {
+                let [k, off, val, tick] = f[..] else { anyhow::bail!("expected 4 fields") };
+                let kind = match k {
+                    "R" => AccessKind::Read,
+                    "W" => AccessKind::Write,
+                    _ => anyhow::bail!("kind must be R or W"),
+                };
+                Ok(Access { kind, offset: parse_u32(off)?, value: parse_u32(val)?, tick: parse_u(tick)? })
+            })();
+            accesses.push(parsed.with_context(|| format!("trace line {}", n + 1))?);
+        }
+        Ok(Trace { accesses })
+    }
+}
+
+/// Records every access to `inner`.
+pub struct TracingIo<R> {
+    inner: R,
+    trace: Trace,
+    tick: u64,
+}
+
+impl<R: RegisterIo> TracingIo<R> {
+    pub fn new(inner: R) -> Self {
+        TracingIo { inner, trace: Trace::default(), tick: 0 }
+    }
+
+    pub fn trace(&self) -> &Trace { &self.trace }
+    /// Hand over what was recorded so far and start a fresh trace.
+    pub fn take_trace(&mut self) -> Trace { std::mem::take(&mut self.trace) }
+    pub fn inner(&self) -> &R { &self.inner }
+    pub fn inner_mut(&mut self) -> &mut R { &mut self.inner }
+    pub fn into_inner(self) -> (R, Trace) { (self.inner, self.trace) }
+
+    fn record(&mut self, kind: AccessKind, offset: u32, value: u32) {
+        self.trace.accesses.push(Access { kind, offset, value, tick: self.tick });
+    }
+}
+
+impl<R: RegisterIo> RegisterIo for TracingIo<R> {
+    fn read(&mut self, offset: u32) -> u32 {
+        let v = self.inner.read(offset);
+        self.record(AccessKind::Read, offset, v);
+        v
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        self.record(AccessKind::Write, offset, value);
+        self.inner.write(offset, value)
+    }
+    fn tick(&mut self, ticks: u32) {
+        self.tick += ticks as u64;
+        self.inner.tick(ticks)
+    }
+}
+
+/// Issue the writes and reads of `trace` against `io`, advancing time as
+/// recorded, and return what `io` answered.
+pub fn replay<R: RegisterIo>(trace: &Trace, io: &mut R) -> Trace {
+    let mut out = Trace::default();
+    let mut now = 0u64;
+    for a in &trace.accesses {
+        while now < a.tick {
+            let step = (a.tick - now).min(u32::MAX as u64);
+            io.tick(step as u32);
+            now += step;
+        }
+        let value = match a.kind {
+            AccessKind::Read => io.read(a.offset),
+            AccessKind::Write => {
+                io.write(a.offset, a.value);
+                a.value
+            }
+        };
+        out.accesses.push(Access { value, ..*a });
+    }
+    out
+}
+
+/// First access where two traces disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: Option<Access>,
+    pub actual: Option<Access>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |a: &Option<Access>| a.map_or("<end of trace>".to_string(), |a| a.to_string());
+        write!(f, "access {}: expected {}, got {}", self.index, show(&self.expected), show(&self.actual))
+    }
+}
+
+/// Compare a captured trace with a replay of it; `None` if identical.
+pub fn diff(expected: &Trace, actual: &Trace) -> Option<Divergence> {
+    let (e, a) = (&expected.accesses, &actual.accesses);
+    let index = match e.iter().zip(a).position(|(x, y)| x != y) {
+        Some(i) => i,
+        None if e.len() == a.len() => return None,
+        None => e.len().min(a.len()),
+    };
+    Some(Divergence { index, expected: e.get(index).copied(), actual: a.get(index).copied() })
+}
+
+/// One START of the engine with the register state it used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub cmd: u8,
+    pub addr: u32,
+    pub len: u32,
+    /// SPI_FMT at START (dummy cycles, data lanes).
+    pub fmt: u32,
+    /// Bytes pushed to DIN since the previous START.
+    pub tx: Vec<u8>,
+    /// Bytes popped from DOUT until the next START.
+    pub rx: Vec<u8>,
+    /// Index of the CTRL write that started it.
+    pub access: usize,
+    pub tick: u64,
+}
+
+/// Mnemonic for an opcode, `"?"` if unknown.
+pub fn cmd_name(cmd: u8) -> &'static str {
+    match cmd {
+        CMD_WREN => "WREN",
+        CMD_RDSR => "RDSR",
+        CMD_WRSR => "WRSR",
+        CMD_RDSR2 => "RDSR2",
+        CMD_SUSPEND => "SUSPEND",
+        CMD_RESUME => "RESUME",
+        CMD_DP => "DP",
+        CMD_RDP => "RDP",
+        CMD_RSTEN => "RSTEN",
+        CMD_RST => "RST",
+        CMD_WRSR2 => "WRSR2",
+        CMD_OTP_READ => "OTP_READ",
+        CMD_OTP_PROG => "OTP_PROG",
+        CMD_OTP_ERASE => "OTP_ERASE",
+        CMD_READ => "READ",
+        CMD_PP => "PP",
+        CMD_SE => "SE",
+        CMD_EN4B => "EN4B",
+        CMD_EX4B => "EX4B",
+        CMD_READ4 => "READ4",
+        CMD_PP4 => "PP4",
+        CMD_SE4 => "SE4",
+        CMD_FAST_READ => "FAST_READ",
+        CMD_DREAD => "DREAD",
+        CMD_QREAD => "QREAD",
+        CMD_FAST_READ4 => "FAST_READ4",
+        CMD_DREAD4 => "DREAD4",
+        CMD_QREAD4 => "QREAD4",
+        _ => "?",
+    }
+}
+
+fn has_addr(cmd: u8) -> bool {
+    matches!(
+        cmd,
+        CMD_READ | CMD_PP | CMD_SE | CMD_READ4 | CMD_PP4 | CMD_SE4 | CMD_FAST_READ | CMD_DREAD | CMD_QREAD
+            | CMD_FAST_READ4 | CMD_DREAD4 | CMD_QREAD4 | CMD_OTP_READ | CMD_OTP_PROG | CMD_OTP_ERASE
+    )
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match cmd_name(self.cmd) {
+            "?" => write!(f, "?{:#04x}", self.cmd)?,
+            name => write!(f, "{}", name)?,
+        }
+        if has_addr(self.cmd) { write!(f, " {:#08x} len {}", self.addr, self.len)?; }
+        if self.fmt != 0 { write!(f, " fmt {:#x}", self.fmt)?; }
+        if matches!(self.cmd, CMD_RDSR | CMD_RDSR2) && !self.rx.is_empty() {
+            write!(f, " -> {:#04x}", self.rx[0])?;
+        }
+        Ok(())
+    }
+}
+
+/// Group a register stream into the commands it issued.
+pub fn decode(trace: &Trace) -> Vec<Transaction> {
+    let (mut cmd, mut addr, mut len, mut fmt) = (0u8, 0u32, 0u32, 0u32);
+    let mut tx = Vec::new();
+    let mut out: Vec<Transaction> = Vec::new();
+    for (i, a) in trace.accesses.iter().enumerate() {
+        match (a.kind, a.offset) {
+            (AccessKind::Write, REG_SPI_CMD) => cmd = a.value as u8,
+            (AccessKind::Write, REG_SPI_ADDR) => addr = a.value,
+            (AccessKind::Write, REG_SPI_LEN) => len = a.value,
+            (AccessKind::Write, REG_SPI_FMT) => fmt = a.value & 0x3FF,
+            (AccessKind::Write, REG_SPI_DIN) => tx.push(a.value as u8),
+            (AccessKind::Write, REG_SPI_CTRL) if a.value & CTRL_START != 0 => {
+                out.push(Transaction {
+                    cmd,
+                    addr,
+                    len,
+                    fmt,
+                    tx: std::mem::take(&mut tx),
+                    rx: Vec::new(),
+                    access: i,
+                    tick: a.tick,
+                });
+            }
+            (AccessKind::Read, REG_SPI_DOUT) => {
+                if let Some(t) = out.last_mut() { t.rx.push(a.value as u8); }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Transaction that issued or consumed access `index` (the last START at or before it).
+pub fn transaction_at(txns: &[Transaction], index: usize) -> Option<&Transaction> {
+    txns.iter().rev().find(|t| t.access <= index)
+}
@@ -0,0 +1,78 @@
+use flash_axi::AxiDriver;
+use flash_core::regs::*;
+use flash_core::{FlashError, FlashGeometry, RegisterIo};
+
+/// Register block of an always-idle part: FIFOs never stall, RDSR returns
+/// `status`, every data byte reads 0xFF. Records the opcode of each START.
+#[derive(Default)]
+struct Idle {
+    status: u8,
+    cmd: u32,
+    din: Vec<u8>,
+    started: Vec<u8>,
+}
+
+impl RegisterIo for Idle {
+    fn read(&mut self, offset: u32) -> u32 {
+        match offset {
+            REG_SPI_STATUS => STATUS_RX_AVAIL | STATUS_TX_SPACE,
+            REG_SPI_DOUT if self.cmd == CMD_RDSR as u32 => self.status as u32,
+            REG_SPI_DOUT => 0xFF,
+            _ => 0,
+        }
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        match offset {
+            REG_SPI_CMD => self.cmd = value,
+            REG_SPI_DIN => self.din.push(value as u8),
+            REG_SPI_CTRL if value & CTRL_START != 0 => self.started.push(self.cmd as u8),
+            _ => {}
+        }
+    }
+}
+
+const GEOM: FlashGeometry = FlashGeometry { mem_size: 8192, page_size: 256, sector_size: 4096 };
+
+#[test]
+fn program_splits_pages_like_flash_ll() {
+    let mut drv = AxiDriver::new(Idle::default(), GEOM).unwrap();
+    drv.program(0xFE, &[1, 2, 3, 4]).unwrap();
+    let io = drv.io();
+    assert_eq!(io.din, [1, 2, 3, 4]);
+    // check_ready, then WREN + PP + WIP poll per page
+    assert_eq!(
+        io.started,
+        [CMD_RDSR, CMD_WREN, CMD_PP, CMD_RDSR, CMD_WREN, CMD_PP, CMD_RDSR]
+    );
+}
+
+#[test]
+fn errors_match_c_codes() {
+    let mut drv = AxiDriver::new(Idle::default(), GEOM).unwrap();
+    let err = drv.read(8190, &mut [0u8; 4]).unwrap_err();
+    assert_eq!(err.to_string(), "read failed: -5"); // EOOB
+    assert_eq!(drv.read(0, &mut []).unwrap_err().to_string(), "read failed: -1");
+
+    drv.io_mut().status = SR_WIP;
+    assert_eq!(drv.program(0, &[0]).unwrap_err().to_string(), "program failed: -3");
+
+    // BP=7 protects everything
+    drv.io_mut().status = SR_BP_MASK;
+    let err = drv.sector_erase(0x1004).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Protected { addr: 0x1000, len: 4096 }));
+
+    drv.io_mut().status = 0;
+    drv.deep_power_down().unwrap();
+    assert_eq!(drv.rdsr().unwrap_err().to_string(), "rdsr failed: -7"); // ESLEEP
+    drv.release_power_down().unwrap();
+    assert_eq!(drv.rdsr().unwrap(), 0);
+}
+
+#[test]
+fn large_parts_use_4byte_opcodes() {
+    let geom = FlashGeometry { mem_size: 32 << 20, ..GEOM };
+    let mut drv = AxiDriver::new(Idle::default(), geom).unwrap();
+    assert_eq!(drv.addr_bytes(), 4);
+    drv.read(0x0100_0000, &mut [0u8; 2]).unwrap();
+    assert_eq!(drv.io().started, [CMD_READ4]);
+}
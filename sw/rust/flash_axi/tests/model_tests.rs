@@ -0,0 +1,104 @@
+use flash_axi::model::{AxiSpiModel, BusyOp, NorConfig};
+use flash_axi::AxiDriver;
+use flash_core::{FlashError, ReadMode};
+
+fn driver(cfg: NorConfig) -> AxiDriver<AxiSpiModel> {
+    let geom = cfg.geometry();
+    AxiDriver::new(AxiSpiModel::with_config(cfg, 1024).unwrap(), geom).unwrap()
+}
+
+#[test]
+fn program_read_erase_without_c() {
+    let mut drv = driver(NorConfig::default());
+    let data: Vec<u8> = (0..6000u32).map(|i| (i * 7) as u8).collect();
+    drv.program(0x80, &data).unwrap();
+    let mut out = vec![0u8; data.len()];
+    drv.read(0x80, &mut out).unwrap();
+    assert_eq!(out, data);
+    // 1->0 only
+    drv.program(0x80, &[0xF0]).unwrap();
+    drv.read(0x80, &mut out[..1]).unwrap();
+    assert_eq!(out[0], data[0] & 0xF0);
+
+    drv.sector_erase(0x0FFF).unwrap();
+    let nor = drv.io().nor();
+    assert!(nor.mem()[..0x1000].iter().all(|&b| b == 0xFF));
+    assert_eq!(&nor.mem()[0x1000..0x80 + 6000], &data[0x1000 - 0x80..]);
+}
+
+#[test]
+fn busy_ticks_and_suspend() {
+    let mut drv = driver(NorConfig { erase_busy_ticks: 200, ..NorConfig::default() });
+    drv.sector_erase_start(0x1000).unwrap();
+    assert_eq!(drv.io().nor().busy_op(), BusyOp::Erase);
+    assert!(drv.io().nor().busy_ticks() == 200);
+    assert!(drv.suspend().unwrap());
+    drv.program(0x10, &[0x12]).unwrap();
+    assert!(drv.program(0x1010, &[0x12]).is_err());
+    drv.resume().unwrap();
+    let mut polls = 0;
+    while drv.is_busy().unwrap() {
+        polls += 1;
+    }
+    // ticks spent on the program during the suspend don't count
+    assert!(polls > 150 && polls <= 200, "{}", polls);
+}
+
+#[test]
+fn read_modes_and_wrong_dummy_cycles() {
+    let mut drv = driver(NorConfig::default());
+    drv.program(0x200, &[0xA5, 0x5A, 0xC3, 0x3C]).unwrap();
+    let mut out = [0u8; 4];
+    drv.set_read_mode(ReadMode::QuadOutput { dummy_cycles: 8 }).unwrap();
+    drv.read(0x200, &mut out).unwrap();
+    assert_eq!(out, [0xA5, 0x5A, 0xC3, 0x3C]);
+    let quad = drv.io().bus_cycles();
+    drv.set_read_mode(ReadMode::Fast { dummy_cycles: 4 }).unwrap();
+    drv.read(0x200, &mut out).unwrap();
+    assert_ne!(out, [0xA5, 0x5A, 0xC3, 0x3C]);
+    assert!(drv.io().bus_cycles() > quad);
+}
+
+#[test]
+fn protection_power_and_otp() {
+    let mut drv = driver(NorConfig { mem_size: 64 << 10, ..NorConfig::default() });
+    drv.set_protection(0..0x8000).unwrap();
+    let err = drv.program(0x10, &[0]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashError>(), Some(FlashError::Protected { .. })));
+    assert_eq!(drv.io().nor().protected_range(), 0..0x8000);
+
+    drv.deep_power_down().unwrap();
+    assert!(drv.io().nor().rdsr() & 1 == 0 && !drv.io().nor().awake());
+    drv.release_power_down().unwrap();
+    assert!(drv.io().nor().awake());
+
+    drv.otp_program(2, 0, b"cal").unwrap();
+    drv.otp_lock(2).unwrap();
+    assert_eq!(&drv.io().nor().otp(2)[..3], b"cal");
+    let err = drv.otp_erase(2).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::OtpLocked { reg: 2 }));
+}
+
+#[test]
+fn timeouts_and_poll_yield_without_c() {
+    use flash_core::Timeouts;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let mut drv = driver(NorConfig { prog_busy_ticks: 50, ..NorConfig::default() });
+    let polls = Arc::new(AtomicU32::new(0));
+    let p = polls.clone();
+    drv.set_poll_yield(move || {
+        p.fetch_add(1, Ordering::Relaxed);
+    });
+    drv.program(0, &[1]).unwrap();
+    assert!(polls.load(Ordering::Relaxed) >= 49);
+
+    drv.set_timeouts(Timeouts { program: 5, ..drv.timeouts() });
+    let err = drv.program(0x100, &[1]).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Timeout { op: "program" }));
+    // 8 FIFO polls per byte are enough for an idle engine
+    drv.set_timeouts(Timeouts { fifo_slack: 0, ..Timeouts::default() });
+    while drv.is_busy().unwrap() {}
+    assert!(drv.program(0x200, &[1; 4]).is_ok());
+}
@@ -0,0 +1,98 @@
+use flash_axi::model::{AxiSpiModel, NorConfig};
+use flash_axi::trace::*;
+use flash_axi::AxiDriver;
+use flash_core::regs::*;
+
+fn traced() -> AxiDriver<TracingIo<AxiSpiModel>> {
+    let cfg = NorConfig::default();
+    let geom = cfg.geometry();
+    AxiDriver::new(TracingIo::new(AxiSpiModel::with_config(cfg, 1024).unwrap()), geom).unwrap()
+}
+
+#[test]
+fn decode_program_and_read() {
+    let mut drv = traced();
+    drv.io_mut().take_trace(); // drop init traffic
+    drv.program(0x100, &[1, 2, 3]).unwrap();
+    let mut out = [0u8; 3];
+    drv.read(0x100, &mut out).unwrap();
+
+    let txns = decode(drv.io().trace());
+    let names: Vec<String> = txns.iter().map(|t| t.to_string()).collect();
+    assert_eq!(names[..3], ["RDSR -> 0x00", "WREN", "PP 0x000100 len 3"]);
+    assert_eq!(txns[2].tx, [1, 2, 3]);
+    let polls = &names[3..names.len() - 1];
+    assert!(polls.iter().all(|n| n.starts_with("RDSR ->")), "{:?}", names);
+    assert!(polls.len() > 1 && polls.last().unwrap() == "RDSR -> 0x00");
+    assert_eq!(names.last().unwrap(), "READ 0x000100 len 3");
+    assert_eq!(txns.last().unwrap().rx, [1, 2, 3]);
+}
+
+#[test]
+fn text_round_trip() {
+    let mut drv = traced();
+    drv.sector_erase(0x1000).unwrap();
+    let trace = drv.io().trace().clone();
+    let text = trace.to_string();
+    assert_eq!(Trace::parse(&text).unwrap(), trace);
+
+    let parsed = Trace::parse("# capture\nW 0x00 0x06 0\n\nR 24 0x4 12 # status\n").unwrap();
+    assert_eq!(parsed.accesses[1], Access { kind: AccessKind::Read, offset: 0x18, value: 4, tick: 12 });
+    let err = Trace::parse("W 0x00 0x06 0\nX 0 0 0\n").unwrap_err();
+    assert!(format!("{:#}", err).contains("trace line 2"), "{:#}", err);
+    let err = Trace::parse("W 0x00 0x100000006 0\n").unwrap_err();
+    assert!(format!("{:#}", err).contains("does not fit"), "{:#}", err);
+}
+
+#[test]
+fn replay_matches_and_reports_first_divergence() {
+    let mut drv = traced();
+    drv.program(0x20, b"hello").unwrap();
+    drv.sector_erase_start(0).unwrap();
+    while drv.is_busy().unwrap() {}
+    let captured = drv.io().trace().clone();
+    assert!(captured.accesses.last().unwrap().tick > 0);
+
+    let mut fresh = AxiSpiModel::with_config(NorConfig::default(), 1024).unwrap();
+    let replayed = replay(&captured, &mut fresh);
+    assert_eq!(diff(&captured, &replayed), None);
+    assert_eq!(fresh.nor().mem(), drv.io().inner().nor().mem());
+
+    // A part that finishes the erase sooner answers RDSR differently.
+    let mut fast = AxiSpiModel::with_config(NorConfig { erase_busy_ticks: 8, ..NorConfig::default() }, 1024).unwrap();
+    let d = diff(&captured, &replay(&captured, &mut fast)).unwrap();
+    let e = d.expected.unwrap();
+    assert_eq!((e.kind, e.offset), (AccessKind::Read, REG_SPI_DOUT));
+    assert_eq!(e.value as u8 & SR_WIP, SR_WIP);
+    let txns = decode(&captured);
+    assert_eq!(transaction_at(&txns, d.index).unwrap().cmd, CMD_RDSR);
+
+    let mut short = captured.clone();
+    short.accesses.pop();
+    assert_eq!(diff(&captured, &short).unwrap().actual, None);
+}
+
+#[test]
+fn diff_cli_replays_with_the_capture_config() {
+    let cfg = NorConfig { mem_size: 1 << 16, sector_size: 8192, erase_busy_ticks: 200, ..NorConfig::default() };
+    let geom = cfg.geometry();
+    let mut drv = AxiDriver::new(TracingIo::new(AxiSpiModel::with_config(cfg, 512).unwrap()), geom).unwrap();
+    drv.program(0x2000, b"trace").unwrap();
+    drv.sector_erase(0x2000).unwrap();
+    let path = std::env::temp_dir().join(format!("flash_trace_{}.txt", std::process::id()));
+    std::fs::write(&path, drv.io().trace().to_string()).unwrap();
+
+    let run = |opts: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_flash_trace"))
+            .arg("diff")
+            .arg(&path)
+            .args(opts)
+            .output()
+            .unwrap()
+    };
+    assert_eq!(run(&["65536"]).status.code(), Some(1));
+    let out = run(&["--mem-size", "65536", "--sector-size", "8192", "--erase-ticks", "200", "--fifo", "512"]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stdout).ends_with("accesses match\n"));
+}
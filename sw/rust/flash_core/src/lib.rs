@@ -1,16 +1,133 @@
 use anyhow::Result;
 
-#[derive(Clone, Copy, Debug)]
+pub mod protect;
+pub mod regs;
+pub mod verify;
+
+pub use protect::WriteProtect;
+pub use regs::{ReadMode, RegisterIo};
+pub use verify::Verify;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct FlashGeometry {
     pub mem_size: u32,
     pub page_size: u32,
     pub sector_size: u32,
 }
 
+/// Failures callers may want to tell apart from plain I/O errors. Backends
+/// return them inside `anyhow::Error`; match with `err.downcast_ref::<FlashError>()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlashError {
+    /// Program or erase of `[addr, addr + len)` hit a write-protected range.
+    Protected { addr: u32, len: u32 },
+    /// Program or erase of a locked OTP security register.
+    OtpLocked { reg: u8 },
+    /// The device stayed busy past the poll budget for `op` (see `Timeouts`).
+    Timeout { op: &'static str },
+    /// Read-back after `op` (see `Verify`) found `found` at `addr` instead of `expected`.
+    VerifyFailed { op: &'static str, addr: u32, expected: u8, found: u8 },
+    /// The device reported that erasing the sector at `addr` failed (e.g. worn out).
+    EraseFailed { addr: u32 },
+}
+
+impl std::fmt::Display for FlashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlashError::Protected { addr, len } => {
+                write!(f, "write-protected: {:#x}..{:#x}", addr, *addr as u64 + *len as u64)
+            }
+            FlashError::OtpLocked { reg } => write!(f, "OTP register {} is locked", reg),
+            FlashError::Timeout { op } => write!(f, "{} timed out", op),
+            FlashError::VerifyFailed { op, addr, expected, found } => {
+                write!(f, "{} verify failed at {:#x}: expected {:#04x}, read {:#04x}", op, addr, expected, found)
+            }
+            FlashError::EraseFailed { addr } => write!(f, "erase failed at {:#x}", addr),
+        }
+    }
+}
+
+impl std::error::Error for FlashError {}
+
+/// Busy-wait budgets of the SPI NOR drivers, in status polls (one tick of
+/// back-off each). Same defaults as `FLASH_LL_DEFAULT_*` in `flash_ll.h`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Page program and OTP program.
+    pub program: u32,
+    /// Sector erase and OTP erase.
+    pub erase: u32,
+    /// Status register writes (protection, OTP lock).
+    pub status: u32,
+    pub suspend: u32,
+    /// Reset and release from deep power-down.
+    pub reset: u32,
+    /// FIFO polls per transfer on top of 8 per byte; running out is a
+    /// `Timeout` for the op.
+    pub fifo_slack: u32,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts { program: 100_000, erase: 1_000_000, status: 100_000, suspend: 1000, reset: 10_000, fifo_slack: 1024 }
+    }
+}
+
+/// Power states for `Flash::set_power`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    Active,
+    /// Deep power-down: only leaving it is accepted.
+    DeepPowerDown,
+}
+
 pub trait Flash {
     fn geometry(&self) -> FlashGeometry;
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()>;
     fn program(&mut self, addr: u32, data: &[u8]) -> Result<()>;
     fn sector_erase(&mut self, addr: u32) -> Result<()>;
     fn rdsr(&mut self) -> Result<u8> { Ok(0) }
+
+    /// Start a sector erase without waiting for it; completion shows up as
+    /// `is_busy() == false`. Backends without background ops erase in place.
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> { self.sector_erase(addr) }
+    /// Program/erase in progress (WIP).
+    fn is_busy(&mut self) -> Result<bool> { Ok(self.rdsr()? & 1 != 0) }
+    /// Suspend a running erase so reads and programs outside its sector can
+    /// go ahead. Returns `false` if nothing was running (or not supported).
+    fn suspend(&mut self) -> Result<bool> { Ok(false) }
+    /// Resume an erase parked by `suspend`; no-op if none.
+    fn resume(&mut self) -> Result<()> { Ok(()) }
+
+    /// Optional power hook; backends without power states ignore it.
+    fn set_power(&mut self, _state: PowerState) -> Result<()> { Ok(()) }
+}
+
+/// Borrowed flash, so a caller can keep the device after handing it to a
+/// user that fails part-way (e.g. a power-loss harness).
+impl<F: Flash + ?Sized> Flash for &mut F {
+    fn geometry(&self) -> FlashGeometry { (**self).geometry() }
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> { (**self).read(addr, buf) }
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> { (**self).program(addr, data) }
+    fn sector_erase(&mut self, addr: u32) -> Result<()> { (**self).sector_erase(addr) }
+    fn rdsr(&mut self) -> Result<u8> { (**self).rdsr() }
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> { (**self).sector_erase_start(addr) }
+    fn is_busy(&mut self) -> Result<bool> { (**self).is_busy() }
+    fn suspend(&mut self) -> Result<bool> { (**self).suspend() }
+    fn resume(&mut self) -> Result<()> { (**self).resume() }
+    fn set_power(&mut self, state: PowerState) -> Result<()> { (**self).set_power(state) }
+}
+
+/// One-time-programmable security registers, e.g. for serial numbers and
+/// calibration. Registers program 1->0 like the array and can be erased
+/// until locked; locking is permanent and later program/erase calls fail
+/// with `FlashError::OtpLocked`.
+pub trait Otp {
+    /// Number of registers and bytes per register.
+    fn otp_layout(&self) -> (u8, u32);
+    fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> Result<()>;
+    fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> Result<()>;
+    fn otp_erase(&mut self, reg: u8) -> Result<()>;
+    fn otp_lock(&mut self, reg: u8) -> Result<()>;
+    fn otp_is_locked(&mut self, reg: u8) -> Result<bool>;
 }
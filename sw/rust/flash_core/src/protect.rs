@@ -0,0 +1,75 @@
+use std::ops::Range;
+
+use anyhow::Result;
+
+use crate::{Flash, FlashError, FlashGeometry, PowerState};
+
+/// Software write protection for any `Flash`, mirroring what the BP/TB
+/// status bits do on a real part: program/erase touching the protected
+/// range fail with `FlashError::Protected` and leave the array untouched.
+pub struct WriteProtect<F: Flash> {
+    inner: F,
+    range: Range<u32>,
+}
+
+impl<F: Flash> WriteProtect<F> {
+    /// Wrap `inner` with nothing protected.
+    pub fn new(inner: F) -> Self {
+        Self { inner, range: 0..0 }
+    }
+
+    /// Protect `range`; an empty range disables protection.
+    pub fn set_protection(&mut self, range: Range<u32>) {
+        self.range = range;
+    }
+
+    pub fn protection(&self) -> Range<u32> {
+        self.range.clone()
+    }
+
+    pub fn inner(&self) -> &F { &self.inner }
+    pub fn inner_mut(&mut self) -> &mut F { &mut self.inner }
+    pub fn into_inner(self) -> F { self.inner }
+
+    fn check(&self, addr: u32, len: u32) -> Result<()> {
+        let end = addr as u64 + len as u64;
+        if !self.range.is_empty() && (addr as u64) < self.range.end as u64 && end > self.range.start as u64 {
+            return Err(FlashError::Protected { addr, len }.into());
+        }
+        Ok(())
+    }
+}
+
+impl<F: Flash> Flash for WriteProtect<F> {
+    fn geometry(&self) -> FlashGeometry { self.inner.geometry() }
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.inner.read(addr, buf)
+    }
+
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.check(addr, data.len() as u32)?;
+        self.inner.program(addr, data)
+    }
+
+    fn sector_erase(&mut self, addr: u32) -> Result<()> {
+        let sector = self.inner.geometry().sector_size;
+        let base = addr - addr % sector;
+        self.check(base, sector)?;
+        self.inner.sector_erase(addr)
+    }
+
+    fn rdsr(&mut self) -> Result<u8> { self.inner.rdsr() }
+
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> {
+        let sector = self.inner.geometry().sector_size;
+        let base = addr - addr % sector;
+        self.check(base, sector)?;
+        self.inner.sector_erase_start(addr)
+    }
+
+    fn is_busy(&mut self) -> Result<bool> { self.inner.is_busy() }
+    fn suspend(&mut self) -> Result<bool> { self.inner.suspend() }
+    fn resume(&mut self) -> Result<()> { self.inner.resume() }
+    fn set_power(&mut self, state: PowerState) -> Result<()> { self.inner.set_power(state) }
+}
@@ -0,0 +1,128 @@
+//! Register map and opcodes of the AXI-Lite SPI engine, mirroring
+//! `sw/flash_ll/include/flash_ll_regs.h`, plus the IO trait drivers use to
+//! reach it. Shared by the C driver wrapper and the pure-Rust port.
+
+use std::any::Any;
+use std::ops::Range;
+
+pub const REG_SPI_CMD: u32 = 0x00;
+/// 32-bit; upper byte only used by 4-byte commands.
+pub const REG_SPI_ADDR: u32 = 0x04;
+pub const REG_SPI_LEN: u32 = 0x08;
+pub const REG_SPI_DIN: u32 = 0x0C;
+pub const REG_SPI_DOUT: u32 = 0x10;
+pub const REG_SPI_CTRL: u32 = 0x14;
+pub const REG_SPI_STATUS: u32 = 0x18;
+/// bits[7:0] dummy cycles, bits[9:8] data lanes (0 = x1, 1 = x2, 2 = x4).
+pub const REG_SPI_FMT: u32 = 0x1C;
+
+pub const CTRL_CS_EN: u32 = 1 << 0;
+pub const CTRL_START: u32 = 1 << 1;
+
+pub const STATUS_BUSY: u32 = 1 << 0;
+pub const STATUS_RX_AVAIL: u32 = 1 << 1;
+pub const STATUS_TX_SPACE: u32 = 1 << 2;
+
+pub const CMD_WREN: u8 = 0x06;
+pub const CMD_RDSR: u8 = 0x05;
+pub const CMD_WRSR: u8 = 0x01;
+pub const CMD_RDSR2: u8 = 0x35;
+pub const CMD_SUSPEND: u8 = 0x75;
+pub const CMD_RESUME: u8 = 0x7A;
+pub const CMD_DP: u8 = 0xB9;
+pub const CMD_RDP: u8 = 0xAB;
+pub const CMD_RSTEN: u8 = 0x66;
+pub const CMD_RST: u8 = 0x99;
+pub const CMD_WRSR2: u8 = 0x31;
+pub const CMD_OTP_READ: u8 = 0x48;
+pub const CMD_OTP_PROG: u8 = 0x42;
+pub const CMD_OTP_ERASE: u8 = 0x44;
+pub const CMD_READ: u8 = 0x03;
+pub const CMD_PP: u8 = 0x02;
+pub const CMD_SE: u8 = 0x20;
+pub const CMD_EN4B: u8 = 0xB7;
+pub const CMD_EX4B: u8 = 0xE9;
+pub const CMD_READ4: u8 = 0x13;
+pub const CMD_PP4: u8 = 0x12;
+pub const CMD_SE4: u8 = 0x21;
+pub const CMD_FAST_READ: u8 = 0x0B;
+pub const CMD_DREAD: u8 = 0x3B;
+pub const CMD_QREAD: u8 = 0x6B;
+pub const CMD_FAST_READ4: u8 = 0x0C;
+pub const CMD_DREAD4: u8 = 0x3C;
+pub const CMD_QREAD4: u8 = 0x6C;
+
+pub const SR_WIP: u8 = 0x01;
+pub const SR_WEL: u8 = 0x02;
+pub const SR_BP_MASK: u8 = 0x1C;
+pub const SR_BP_SHIFT: u8 = 2;
+pub const SR_TB: u8 = 0x20;
+pub const SR2_LB1: u8 = 0x08;
+pub const SR2_SUS: u8 = 0x80;
+
+pub const OTP_REGS: u8 = 3;
+pub const OTP_SIZE: u32 = 256;
+
+/// Opcode used for array reads. Fast and multi-I/O reads need the dummy
+/// cycle count the part expects after the address (typically 8).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadMode {
+    /// 0x03 READ: no dummy cycles, only valid at low SPI clock rates.
+    Standard,
+    /// 0x0B FAST READ, single data line.
+    Fast { dummy_cycles: u8 },
+    /// 0x3B dual output read.
+    DualOutput { dummy_cycles: u8 },
+    /// 0x6B quad output read.
+    QuadOutput { dummy_cycles: u8 },
+}
+
+impl ReadMode {
+    /// 3-byte opcode and dummy cycles.
+    pub fn cmd_and_dummy(self) -> (u8, u8) {
+        match self {
+            ReadMode::Standard => (CMD_READ, 0),
+            ReadMode::Fast { dummy_cycles } => (CMD_FAST_READ, dummy_cycles),
+            ReadMode::DualOutput { dummy_cycles } => (CMD_DREAD, dummy_cycles),
+            ReadMode::QuadOutput { dummy_cycles } => (CMD_QREAD, dummy_cycles),
+        }
+    }
+
+    /// Inverse of `cmd_and_dummy`; unknown opcodes map to `Standard`.
+    pub fn from_cmd(cmd: u8, dummy_cycles: u8) -> Self {
+        match cmd {
+            CMD_FAST_READ => ReadMode::Fast { dummy_cycles },
+            CMD_DREAD => ReadMode::DualOutput { dummy_cycles },
+            CMD_QREAD => ReadMode::QuadOutput { dummy_cycles },
+            _ => ReadMode::Standard,
+        }
+    }
+}
+
+/// Range a BP/TB status value protects on a part of `mem_size` bytes:
+/// BP = 1..6 cover 1/64 .. 1/2 from the top (or bottom with TB), 7 covers
+/// everything. `0..0` when nothing is protected.
+pub fn protected_range(mem_size: u32, status: u8) -> Range<u32> {
+    let bp = (status & SR_BP_MASK) >> SR_BP_SHIFT;
+    let size = match bp {
+        0 => 0,
+        7 => mem_size,
+        _ => mem_size >> (7 - bp),
+    };
+    if size == 0 {
+        0..0
+    } else if status & SR_TB != 0 {
+        0..size
+    } else {
+        mem_size - size..mem_size
+    }
+}
+
+/// The engine's register block as seen by a driver: real MMIO, a simulator,
+/// or a wrapper that logs or corrupts accesses. Offsets are the `REG_SPI_*` map.
+pub trait RegisterIo: Any + Send {
+    fn read(&mut self, offset: u32) -> u32;
+    fn write(&mut self, offset: u32, value: u32);
+    /// Let simulated time pass while the driver polls; no-op on hardware.
+    fn tick(&mut self, _ticks: u32) {}
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+
+use crate::{Flash, FlashError, FlashGeometry, PowerState};
+
+/// Read-back verification for any `Flash`: after a program the range must
+/// read as `old & data`, after an erase the sector must be blank. A mismatch
+/// repeats the operation up to `retries` times, then fails with
+/// `FlashError::VerifyFailed`. With `set_rereads`, mismatching bytes are
+/// re-read first (see `read_back`), so a transient read error doesn't count
+/// as a failure.
+///
+/// Background erases (`sector_erase_start`) are checked when `is_busy` or
+/// `rdsr` first reports completion, or before the next read, program or
+/// erase; a retry restarts the erase and reports busy. A new erase waits for
+/// the pending one to finish and pass its check.
+pub struct Verify<F: Flash> {
+    inner: F,
+    retries: u32,
+    rereads: u32,
+    retried: u32,
+    /// Sector of a background erase not yet blank-checked, with retries left.
+    pending: Option<(u32, u32)>,
+    suspended: bool,
+}
+
+impl<F: Flash> Verify<F> {
+    /// Wrap `inner`, retrying each failed operation once.
+    pub fn new(inner: F) -> Self {
+        Self { inner, retries: 1, rereads: 0, retried: 0, pending: None, suspended: false }
+    }
+
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Extra reads of a mismatch before it counts, and of the old contents
+    /// before a program (default 0).
+    pub fn set_rereads(&mut self, rereads: u32) {
+        self.rereads = rereads;
+    }
+
+    pub fn retries(&self) -> u32 { self.retries }
+    /// Operations repeated after a mismatch, in total.
+    pub fn retried(&self) -> u32 { self.retried }
+
+    pub fn inner(&self) -> &F { &self.inner }
+    pub fn inner_mut(&mut self) -> &mut F { &mut self.inner }
+    pub fn into_inner(self) -> F { self.inner }
+
+    fn sector_range(&self, addr: u32) -> (u32, usize) {
+        let g = self.inner.geometry();
+        let base = addr - addr % g.sector_size;
+        (base, g.sector_size.min(g.mem_size - base) as usize)
+    }
+
+    /// The first non-blank byte of the sector at `base`, as a verify error.
+    fn blank_check(&mut self, base: u32, len: usize) -> Result<Option<FlashError>> {
+        read_back(&mut self.inner, "sector_erase", base, &vec![0xFF; len], self.rereads)
+    }
+
+    /// Given the part's busy bit, blank-check a background erase it has
+    /// finished; `true` while it (or the retry this starts) is running.
+    fn settle(&mut self, busy: bool) -> Result<bool> {
+        if busy { return Ok(true); }
+        let Some((addr, left)) = self.pending else { return Ok(false) };
+        if self.suspended { return Ok(false); }
+        self.pending = None;
+        let (base, len) = self.sector_range(addr);
+        match self.blank_check(base, len)? {
+            None => Ok(false),
+            Some(e) if left == 0 => Err(e.into()),
+            Some(_) => {
+                self.retried += 1;
+                self.inner.sector_erase_start(addr)?;
+                self.pending = Some((addr, left - 1));
+                Ok(true)
+            }
+        }
+    }
+
+    /// `settle` a running background erase before other work goes to the part.
+    fn poll_pending(&mut self) -> Result<bool> {
+        if self.pending.is_none() || self.suspended { return Ok(false); }
+        let busy = self.inner.is_busy()?;
+        self.settle(busy)
+    }
+}
+
+/// `len` bytes at `addr`, read twice when `rereads` allows; bytes the two
+/// reads disagree on take the majority of a third.
+fn read_settled<F: Flash>(flash: &mut F, addr: u32, len: usize, rereads: u32) -> Result<Vec<u8>> {
+    let mut a = vec![0u8; len];
+    flash.read(addr, &mut a)?;
+    if rereads == 0 { return Ok(a); }
+    let mut b = vec![0u8; len];
+    flash.read(addr, &mut b)?;
+    if a == b { return Ok(a); }
+    let mut c = vec![0u8; len];
+    flash.read(addr, &mut c)?;
+    for i in 0..len {
+        if a[i] != b[i] && a[i] != c[i] { a[i] = b[i]; }
+    }
+    Ok(a)
+}
+
+/// Read `expected.len()` bytes at `addr` and compare. Bytes that differ are
+/// read again up to `rereads` times and only count if they differ every
+/// time; the first of those comes back as `FlashError::VerifyFailed` for `op`.
+pub fn read_back<F: Flash>(flash: &mut F, op: &'static str, addr: u32, expected: &[u8], rereads: u32) -> Result<Option<FlashError>> {
+    let mut found = vec![0u8; expected.len()];
+    flash.read(addr, &mut found)?;
+    let mut bad: Vec<usize> = (0..expected.len()).filter(|&i| found[i] != expected[i]).collect();
+    for _ in 0..rereads {
+        if bad.is_empty() { break; }
+        flash.read(addr, &mut found)?;
+        bad.retain(|&i| found[i] != expected[i]);
+    }
+    Ok(bad.first().map(|&i| FlashError::VerifyFailed { op, addr: addr + i as u32, expected: expected[i], found: found[i] }))
+}
+
+impl<F: Flash> Flash for Verify<F> {
+    fn geometry(&self) -> FlashGeometry { self.inner.geometry() }
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.poll_pending()?;
+        self.inner.read(addr, buf)
+    }
+
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.poll_pending()?;
+        let mut expected = read_settled(&mut self.inner, addr, data.len(), self.rereads)?;
+        for (e, d) in expected.iter_mut().zip(data) { *e &= d; }
+        let mut attempt = 0;
+        loop {
+            self.inner.program(addr, data)?;
+            let Some(err) = read_back(&mut self.inner, "program", addr, &expected, self.rereads)? else { return Ok(()) };
+            if attempt == self.retries { return Err(err.into()); }
+            attempt += 1;
+            self.retried += 1;
+        }
+    }
+
+    fn sector_erase(&mut self, addr: u32) -> Result<()> {
+        while self.poll_pending()? {}
+        let (base, len) = self.sector_range(addr);
+        let mut attempt = 0;
+        loop {
+            self.inner.sector_erase(addr)?;
+            let Some(err) = self.blank_check(base, len)? else { return Ok(()) };
+            if attempt == self.retries { return Err(err.into()); }
+            attempt += 1;
+            self.retried += 1;
+        }
+    }
+
+    fn rdsr(&mut self) -> Result<u8> {
+        let st = self.inner.rdsr()?;
+        Ok(if self.settle(st & 1 != 0)? { st | 1 } else { st })
+    }
+
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> {
+        while self.poll_pending()? {}
+        self.inner.sector_erase_start(addr)?;
+        self.pending = Some((addr, self.retries));
+        Ok(())
+    }
+
+    fn is_busy(&mut self) -> Result<bool> {
+        let busy = self.inner.is_busy()?;
+        self.settle(busy)
+    }
+
+    fn suspend(&mut self) -> Result<bool> {
+        let s = self.inner.suspend()?;
+        self.suspended |= s;
+        Ok(s)
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()?;
+        self.suspended = false;
+        Ok(())
+    }
+
+    fn set_power(&mut self, state: PowerState) -> Result<()> { self.inner.set_power(state) }
+}
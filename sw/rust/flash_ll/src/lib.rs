@@ -1,25 +1,263 @@
 use flash_ll_sys as sys;
-use flash_core::{Flash, FlashGeometry};
+use std::any::Any;
+use std::ffi::c_void;
+use std::ops::Range;
 
+use flash_core::{Flash, FlashError, FlashGeometry, Otp, PowerState, Timeouts};
+
+// Any `RegisterIo` can back the C driver (`Driver::with_io`): a logger, a
+// fault injector, a remote model. It is called through `extern "C"`
+// trampolines, so a panic in one aborts.
+pub use flash_core::{ReadMode, RegisterIo};
+
+/// `FlashLlIo` whose `io` argument is a `*mut Box<dyn RegisterIo>`.
+static REGISTER_IO_OPS: sys::FlashLlIo = sys::FlashLlIo {
+    read: Some(io_read),
+    write: Some(io_write),
+    tick: Some(io_tick),
+};
+
+unsafe fn io_backend<'a>(io: *mut c_void) -> &'a mut dyn RegisterIo {
+    unsafe { &mut **(io as *mut Box<dyn RegisterIo>) }
+}
+
+unsafe extern "C" fn io_read(io: *mut c_void, offset: u32) -> u32 {
+    unsafe { io_backend(io).read(offset) }
+}
+
+unsafe extern "C" fn io_write(io: *mut c_void, offset: u32, value: u32) {
+    unsafe { io_backend(io).write(offset, value) }
+}
+
+unsafe extern "C" fn io_tick(io: *mut c_void, ticks: u32) {
+    unsafe { io_backend(io).tick(ticks) }
+}
+
+type PollYield = Box<dyn FnMut() + Send>;
+
+unsafe extern "C" fn poll_yield(arg: *mut c_void) {
+    unsafe { (*(arg as *mut PollYield))() }
+}
+
+/// `FlashError::Timeout` for `FLASH_LL_ETIME`, otherwise "{op} failed: {rc}".
+fn fail(op: &'static str, rc: i32) -> anyhow::Error {
+    if rc == sys::FlashLlErr_FLASH_LL_ETIME {
+        return FlashError::Timeout { op }.into();
+    }
+    anyhow::anyhow!("{} failed: {}", op, rc)
+}
+
+/// Heap slot for a `RegisterIo`; its thin address is what the C side holds.
+struct IoBox(*mut Box<dyn RegisterIo>);
+
+impl Drop for IoBox {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.0)) }
+    }
+}
+
+/// Safe wrapper over a C driver context.
+///
+/// The driver owns the register backend `ctx.io` points at (see `with_io` and `sim::driver_with_env`),
+/// so the backend cannot be dropped or touched while the driver is alive.
+///
+/// `Driver` is `Send`: the C context has no global or thread-local state and
+/// everything it points to is owned by (or, for `new_with_sim`, promised to)
+/// this driver, so it may move to another thread, e.g. inside `Eeprom<Driver>`.
+/// All I/O takes `&mut self`; wrap it in a `Mutex` to share it.
 pub struct Driver {
     ctx: sys::FlashLlCtx,
+    backend: Backend,
+    /// Target of `ctx.cfg.poll_yield_arg`.
+    poll_yield: Option<Box<PollYield>>,
+}
+
+/// What keeps `ctx.io` alive.
+enum Backend {
+    /// Managed by the caller of `Driver::new_with_sim`.
+    Raw,
+    Io(IoBox),
+    #[cfg(feature = "sim")]
+    Sim(sim::SimEnv),
 }
 
+// SAFETY: see the type docs; the raw pointers in `ctx` are uniquely owned.
+unsafe impl Send for Driver {}
+
 impl Driver {
-    pub fn new_with_sim(mem_size: u32, page_size: u32, sector_size: u32, sim: *mut sys::AxiSpiSim) -> anyhow::Result<Self> {
+    /// Driver over a caller-managed AXI sim.
+    ///
+    /// # Safety
+    ///
+    /// `sim` must point to an initialised `AxiSpiSim` that outlives the driver
+    /// and is not used elsewhere (on any thread) while the driver is alive.
+    /// Prefer `sim::driver_with_env`, which hands ownership to the driver.
+    pub unsafe fn new_with_sim(mem_size: u32, page_size: u32, sector_size: u32, sim: *mut sys::AxiSpiSim) -> anyhow::Result<Self> {
+        unsafe { Self::init(mem_size, page_size, sector_size, sys::flash_ll_axi_sim_ops(), sim as *mut _, Backend::Raw) }
+    }
+
+    /// Driver over any Rust register backend.
+    pub fn with_io<R: RegisterIo>(io: R, geometry: FlashGeometry) -> anyhow::Result<Self> {
+        let slot = IoBox(Box::into_raw(Box::new(Box::new(io) as Box<dyn RegisterIo>)));
+        let FlashGeometry { mem_size, page_size, sector_size } = geometry;
+        let io = slot.0 as *mut c_void;
+        unsafe { Self::init(mem_size, page_size, sector_size, &REGISTER_IO_OPS, io, Backend::Io(slot)) }
+    }
+
+    /// The backend passed to `with_io`, if it is an `R`.
+    pub fn io<R: RegisterIo>(&self) -> Option<&R> {
+        match &self.backend {
+            Backend::Io(slot) => {
+                let io: &dyn Any = unsafe { &**slot.0 };
+                io.downcast_ref()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn io_mut<R: RegisterIo>(&mut self) -> Option<&mut R> {
+        match &mut self.backend {
+            Backend::Io(slot) => {
+                let io: &mut dyn Any = unsafe { &mut **slot.0 };
+                io.downcast_mut()
+            }
+            _ => None,
+        }
+    }
+
+    /// Take the backend back, if it is an `R`.
+    pub fn into_io<R: RegisterIo>(self) -> Option<R> {
+        let Backend::Io(slot) = self.backend else { return None };
+        let io: Box<dyn Any> = *unsafe { Box::from_raw(slot.0) };
+        std::mem::forget(slot);
+        io.downcast().ok().map(|io| *io)
+    }
+
+    unsafe fn init(
+        mem_size: u32,
+        page_size: u32,
+        sector_size: u32,
+        ops: *const sys::FlashLlIo,
+        io: *mut c_void,
+        backend: Backend,
+    ) -> anyhow::Result<Self> {
         unsafe {
             let cfg = sys::FlashLlConfig {
                 base_addr: 0, // unused
                 mem_size,
                 page_size,
                 sector_size,
+                addr_bytes: 0, // auto: 4-byte opcodes above 16 MiB
+                read_cmd: 0,   // READ; see set_read_mode
+                read_dummy_cycles: 0,
+                // 0 = FLASH_LL_DEFAULT_*; see set_timeouts
+                program_timeout: 0,
+                erase_timeout: 0,
+                status_timeout: 0,
+                suspend_timeout: 0,
+                reset_timeout: 0,
+                fifo_slack: 0,
+                poll_yield: None,
+                poll_yield_arg: std::ptr::null_mut(),
             };
             let mut ctx = std::mem::MaybeUninit::<sys::FlashLlCtx>::zeroed();
-            let ops = sys::flash_ll_axi_sim_ops();
-            let rc = sys::flash_ll_init(ctx.as_mut_ptr(), &cfg, ops, sim as *mut _);
+            let rc = sys::flash_ll_init(ctx.as_mut_ptr(), &cfg, ops, io);
             if rc != 0 { anyhow::bail!("flash_ll_init failed: {}", rc); }
-            Ok(Driver { ctx: ctx.assume_init() })
+            Ok(Driver { ctx: ctx.assume_init(), backend, poll_yield: None })
+        }
+    }
+
+    /// Busy-wait budgets in use; `Timeouts::default()` unless changed.
+    pub fn timeouts(&self) -> Timeouts {
+        let cfg = &self.ctx.cfg;
+        Timeouts {
+            program: cfg.program_timeout,
+            erase: cfg.erase_timeout,
+            status: cfg.status_timeout,
+            suspend: cfg.suspend_timeout,
+            reset: cfg.reset_timeout,
+            fifo_slack: cfg.fifo_slack,
+        }
+    }
+
+    /// Ops that stay busy longer fail with `FlashError::Timeout`.
+    pub fn set_timeouts(&mut self, t: Timeouts) {
+        let cfg = &mut self.ctx.cfg;
+        cfg.program_timeout = t.program;
+        cfg.erase_timeout = t.erase;
+        cfg.status_timeout = t.status;
+        cfg.suspend_timeout = t.suspend;
+        cfg.reset_timeout = t.reset;
+        cfg.fifo_slack = t.fifo_slack;
+    }
+
+    /// Call `f` after every busy poll instead of spinning flat out (sleep,
+    /// `thread::yield_now`, feed a watchdog). Runs inside the C driver, so a
+    /// panic in `f` aborts.
+    pub fn set_poll_yield<F: FnMut() + Send + 'static>(&mut self, f: F) {
+        let mut slot: Box<PollYield> = Box::new(Box::new(f));
+        self.ctx.cfg.poll_yield = Some(poll_yield);
+        self.ctx.cfg.poll_yield_arg = &mut *slot as *mut PollYield as *mut c_void;
+        self.poll_yield = Some(slot);
+    }
+
+    pub fn clear_poll_yield(&mut self) {
+        self.ctx.cfg.poll_yield = None;
+        self.ctx.cfg.poll_yield_arg = std::ptr::null_mut();
+        self.poll_yield = None;
+    }
+
+    /// Address width resolved by the C driver (3, or 4 for parts above 16 MiB).
+    pub fn addr_bytes(&self) -> u8 {
+        self.ctx.cfg.addr_bytes
+    }
+
+    /// Select the read opcode; the 4-byte variant is used automatically.
+    pub fn set_read_mode(&mut self, mode: ReadMode) -> anyhow::Result<()> {
+        let (cmd, dummy) = mode.cmd_and_dummy();
+        unsafe {
+            let rc = sys::flash_ll_set_read_mode(&mut self.ctx, cmd, dummy);
+            if rc != 0 { anyhow::bail!("set_read_mode failed: {}", rc); }
         }
+        Ok(())
+    }
+
+    pub fn read_mode(&self) -> ReadMode {
+        ReadMode::from_cmd(self.ctx.cfg.read_cmd, self.ctx.cfg.read_dummy_cycles)
+    }
+
+    /// Program BP2..BP0/TB so that exactly `range` is write-protected; an
+    /// empty range clears protection. Only the ranges the part can encode
+    /// (top/bottom 1/64 .. 1/2, or everything) are accepted.
+    pub fn set_protection(&mut self, range: Range<u32>) -> anyhow::Result<()> {
+        let status = if range.is_empty() {
+            Some(0u8)
+        } else {
+            let tb = sys::FLASH_LL_SR_TB as u8;
+            (1u8..8)
+                .flat_map(|bp| [bp << sys::FLASH_LL_SR_BP_SHIFT, (bp << sys::FLASH_LL_SR_BP_SHIFT) | tb])
+                .find(|&st| self.protected_range(st) == range)
+        };
+        let Some(status) = status else {
+            anyhow::bail!("protection range {:#x}..{:#x} not encodable in BP/TB", range.start, range.end);
+        };
+        unsafe {
+            let rc = sys::flash_ll_wrsr(&mut self.ctx, status);
+            if rc != 0 { return Err(fail("wrsr", rc)); }
+        }
+        Ok(())
+    }
+
+    /// Currently protected range as reported by the status register.
+    pub fn protection(&mut self) -> anyhow::Result<Range<u32>> {
+        let st = self.rdsr()?;
+        Ok(self.protected_range(st))
+    }
+
+    fn protected_range(&self, status: u8) -> Range<u32> {
+        let (mut start, mut end) = (0u32, 0u32);
+        unsafe { sys::flash_ll_protected_range(&self.ctx, status, &mut start, &mut end) };
+        start..end
     }
 
     pub fn rdsr(&mut self) -> anyhow::Result<u8> {
@@ -34,7 +272,7 @@ impl Driver {
     pub fn read(&mut self, addr: u32, buf: &mut [u8]) -> anyhow::Result<()> {
         unsafe {
             let rc = sys::flash_ll_read(&mut self.ctx, addr, buf.as_mut_ptr() as *mut _, buf.len());
-            if rc != 0 { anyhow::bail!("read failed: {}", rc); }
+            if rc != 0 { return Err(fail("read", rc)); }
             Ok(())
         }
     }
@@ -42,28 +280,154 @@ impl Driver {
     pub fn program(&mut self, addr: u32, data: &[u8]) -> anyhow::Result<()> {
         unsafe {
             let rc = sys::flash_ll_program(&mut self.ctx, addr, data.as_ptr() as *const _, data.len());
-            if rc != 0 { anyhow::bail!("program failed: {}", rc); }
+            if rc == sys::FlashLlErr_FLASH_LL_EPROT {
+                return Err(FlashError::Protected { addr, len: data.len() as u32 }.into());
+            }
+            if rc != 0 { return Err(fail("program", rc)); }
             Ok(())
         }
     }
 
     pub fn sector_erase(&mut self, addr: u32) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_sector_erase(&mut self.ctx, addr) };
+        self.erase_result(addr, rc, "sector_erase")
+    }
+
+    /// Issue the erase and return; poll `rdsr` (WIP) or `suspend` around other work.
+    pub fn sector_erase_start(&mut self, addr: u32) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_sector_erase_start(&mut self.ctx, addr) };
+        self.erase_result(addr, rc, "sector_erase_start")
+    }
+
+    fn erase_result(&self, addr: u32, rc: i32, what: &'static str) -> anyhow::Result<()> {
+        if rc == sys::FlashLlErr_FLASH_LL_EPROT {
+            let len = self.ctx.cfg.sector_size;
+            return Err(FlashError::Protected { addr: addr - addr % len, len }.into());
+        }
+        if rc != 0 { return Err(fail(what, rc)); }
+        Ok(())
+    }
+
+    /// Enter deep power-down (0xB9); other calls fail until `release_power_down`.
+    pub fn deep_power_down(&mut self) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_deep_power_down(&mut self.ctx) };
+        if rc != 0 { anyhow::bail!("deep_power_down failed: {}", rc); }
+        Ok(())
+    }
+
+    /// Leave deep power-down (0xAB), waiting out the wake-up latency.
+    pub fn release_power_down(&mut self) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_release_power_down(&mut self.ctx) };
+        if rc != 0 { return Err(fail("release_power_down", rc)); }
+        Ok(())
+    }
+
+    /// Software reset (0x66 + 0x99): aborts in-flight or suspended ops, clears WEL.
+    pub fn reset(&mut self) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_reset(&mut self.ctx) };
+        if rc != 0 { return Err(fail("reset", rc)); }
+        Ok(())
+    }
+
+    /// Read security register `reg` (0..3) from `offset`.
+    pub fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_otp_read(&mut self.ctx, reg, offset, buf.as_mut_ptr() as *mut _, buf.len()) };
+        if rc != 0 { return Err(fail("otp_read", rc)); }
+        Ok(())
+    }
+
+    pub fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_otp_program(&mut self.ctx, reg, offset, data.as_ptr() as *const _, data.len()) };
+        Self::otp_result(reg, rc, "otp_program")
+    }
+
+    pub fn otp_erase(&mut self, reg: u8) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_otp_erase(&mut self.ctx, reg) };
+        Self::otp_result(reg, rc, "otp_erase")
+    }
+
+    /// Set the register's lock bit. This cannot be undone on a real part.
+    pub fn otp_lock(&mut self, reg: u8) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_otp_lock(&mut self.ctx, reg) };
+        if rc != 0 { return Err(fail("otp_lock", rc)); }
+        Ok(())
+    }
+
+    pub fn otp_is_locked(&mut self, reg: u8) -> anyhow::Result<bool> {
+        if reg as u32 >= sys::FLASH_LL_OTP_REGS { anyhow::bail!("no OTP register {}", reg); }
+        Ok(self.rdsr2()? & ((sys::FLASH_LL_SR2_LB1 as u8) << reg) != 0)
+    }
+
+    fn otp_result(reg: u8, rc: i32, what: &'static str) -> anyhow::Result<()> {
+        if rc == sys::FlashLlErr_FLASH_LL_EPROT { return Err(FlashError::OtpLocked { reg }.into()); }
+        if rc != 0 { return Err(fail(what, rc)); }
+        Ok(())
+    }
+
+    /// Status register 2; bit 7 is SUS.
+    pub fn rdsr2(&mut self) -> anyhow::Result<u8> {
         unsafe {
-            let rc = sys::flash_ll_sector_erase(&mut self.ctx, addr);
-            if rc != 0 { anyhow::bail!("sector_erase failed: {}", rc); }
-            Ok(())
+            let mut st: u8 = 0;
+            let rc = sys::flash_ll_rdsr2(&mut self.ctx, &mut st);
+            if rc != 0 { anyhow::bail!("rdsr2 failed: {}", rc); }
+            Ok(st)
+        }
+    }
+
+    /// Suspend (0x75) a running erase. Returns `false` if the device was idle.
+    pub fn suspend(&mut self) -> anyhow::Result<bool> {
+        let rc = unsafe { sys::flash_ll_suspend(&mut self.ctx) };
+        if rc < 0 { return Err(fail("suspend", rc)); }
+        Ok(rc == 1)
+    }
+
+    /// Resume (0x7A) a suspended erase; does not wait for it to finish.
+    pub fn resume(&mut self) -> anyhow::Result<()> {
+        let rc = unsafe { sys::flash_ll_resume(&mut self.ctx) };
+        if rc != 0 { anyhow::bail!("resume failed: {}", rc); }
+        Ok(())
+    }
+
+    /// One busy poll with a single tick of back-off, so callers spinning on
+    /// it also advance the simulated device.
+    pub fn is_busy(&mut self) -> anyhow::Result<bool> {
+        let rc = unsafe { sys::flash_ll_wait_busy(&mut self.ctx, 1) };
+        match rc {
+            0 => Ok(false),
+            sys::FlashLlErr_FLASH_LL_ETIME => Ok(true),
+            _ => anyhow::bail!("is_busy failed: {}", rc),
         }
     }
 }
 
 impl Flash for Driver {
     fn geometry(&self) -> FlashGeometry {
-        FlashGeometry { mem_size: 0, page_size: 256, sector_size: 4096 }
+        let cfg = &self.ctx.cfg;
+        FlashGeometry { mem_size: cfg.mem_size, page_size: cfg.page_size, sector_size: cfg.sector_size }
     }
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> anyhow::Result<()> { self.read(addr, buf) }
     fn program(&mut self, addr: u32, data: &[u8]) -> anyhow::Result<()> { self.program(addr, data) }
     fn sector_erase(&mut self, addr: u32) -> anyhow::Result<()> { self.sector_erase(addr) }
     fn rdsr(&mut self) -> anyhow::Result<u8> { self.rdsr() }
+    fn sector_erase_start(&mut self, addr: u32) -> anyhow::Result<()> { self.sector_erase_start(addr) }
+    fn is_busy(&mut self) -> anyhow::Result<bool> { self.is_busy() }
+    fn suspend(&mut self) -> anyhow::Result<bool> { self.suspend() }
+    fn resume(&mut self) -> anyhow::Result<()> { self.resume() }
+    fn set_power(&mut self, state: PowerState) -> anyhow::Result<()> {
+        match state {
+            PowerState::Active => self.release_power_down(),
+            PowerState::DeepPowerDown => self.deep_power_down(),
+        }
+    }
+}
+
+impl Otp for Driver {
+    fn otp_layout(&self) -> (u8, u32) { (sys::FLASH_LL_OTP_REGS as u8, sys::FLASH_LL_OTP_SIZE) }
+    fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> anyhow::Result<()> { self.otp_read(reg, offset, buf) }
+    fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> anyhow::Result<()> { self.otp_program(reg, offset, data) }
+    fn otp_erase(&mut self, reg: u8) -> anyhow::Result<()> { self.otp_erase(reg) }
+    fn otp_lock(&mut self, reg: u8) -> anyhow::Result<()> { self.otp_lock(reg) }
+    fn otp_is_locked(&mut self, reg: u8) -> anyhow::Result<bool> { self.otp_is_locked(reg) }
 }
 
 #[cfg(feature = "sim")]
@@ -71,45 +435,179 @@ pub mod sim {
     use super::*;
     use std::mem;
 
+    /// Geometry and timing of a simulated part. Busy times are in sim ticks;
+    /// the driver advances one tick per status poll.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SimEnvConfig {
+        pub mem_size: u32,
+        pub page_size: u32,
+        pub sector_size: u32,
+        pub prog_busy_ticks: u32,
+        pub erase_busy_ticks: u32,
+        pub read_dummy_cycles: u32,
+        pub wake_ticks: u32,
+        /// AXI TX/RX FIFO depth in bytes; must hold at least one page.
+        pub fifo_size: usize,
+    }
+
+    impl Default for SimEnvConfig {
+        fn default() -> Self {
+            SimEnvConfig {
+                mem_size: 8192, // two sectors for EEPROM emulation
+                page_size: 256,
+                sector_size: 4096,
+                prog_busy_ticks: 4,
+                erase_busy_ticks: 64,
+                read_dummy_cycles: 8,
+                wake_ticks: 3,
+                fifo_size: 1024,
+            }
+        }
+    }
+
+    impl SimEnvConfig {
+        pub fn new() -> Self { Self::default() }
+
+        pub fn mem_size(mut self, bytes: u32) -> Self { self.mem_size = bytes; self }
+        pub fn page_size(mut self, bytes: u32) -> Self { self.page_size = bytes; self }
+        pub fn sector_size(mut self, bytes: u32) -> Self { self.sector_size = bytes; self }
+        pub fn prog_busy_ticks(mut self, ticks: u32) -> Self { self.prog_busy_ticks = ticks; self }
+        pub fn erase_busy_ticks(mut self, ticks: u32) -> Self { self.erase_busy_ticks = ticks; self }
+        pub fn read_dummy_cycles(mut self, cycles: u32) -> Self { self.read_dummy_cycles = cycles; self }
+        pub fn wake_ticks(mut self, ticks: u32) -> Self { self.wake_ticks = ticks; self }
+        pub fn fifo_size(mut self, bytes: usize) -> Self { self.fifo_size = bytes; self }
+
+        pub fn build(self) -> anyhow::Result<SimEnv> { SimEnv::with_config(self) }
+
+        fn validate(&self) -> anyhow::Result<()> {
+            let ok = self.mem_size > 0
+                && self.page_size > 0
+                && self.sector_size >= self.page_size
+                && self.sector_size.is_multiple_of(self.page_size)
+                && self.mem_size.is_multiple_of(self.sector_size)
+                && self.fifo_size >= self.page_size as usize; // PP pushes a full page to TX
+            if !ok { anyhow::bail!("invalid sim geometry: {:?}", self); }
+            Ok(())
+        }
+    }
+
+    /// A flash model behind an AXI SPI engine. The C structs live on the heap
+    /// and are only reachable through this env (or the `Driver` that owns it).
     pub struct SimEnv {
-        pub flash: Box<sys::FlashSim>,
-        pub axi: Box<sys::AxiSpiSim>,
+        flash: *mut sys::FlashSim,
+        axi: *mut sys::AxiSpiSim,
+        cfg: SimEnvConfig,
     }
 
+    // SAFETY: the env uniquely owns both C structs and the memory they point to.
+    unsafe impl Send for SimEnv {}
+
     impl SimEnv {
         pub fn new() -> anyhow::Result<Self> {
+            Self::with_config(SimEnvConfig::default())
+        }
+
+        /// Same timings as `new`, but with a custom array size (e.g. >16 MiB).
+        pub fn with_mem_size(mem_bytes: usize) -> anyhow::Result<Self> {
+            let mem = u32::try_from(mem_bytes).map_err(|_| anyhow::anyhow!("sim array too large: {} bytes", mem_bytes))?;
+            Self::with_config(SimEnvConfig::default().mem_size(mem))
+        }
+
+        pub fn with_config(cfg: SimEnvConfig) -> anyhow::Result<Self> {
+            cfg.validate()?;
             unsafe {
                 // Allocate on heap to keep stable addresses across moves
-                let mut flash: Box<sys::FlashSim> = Box::new(mem::zeroed());
-                let cfg = sys::FlashSimConfig {
-                    mem_bytes: 8192, // two sectors for EEPROM emulation
-                    page_size: 256,
-                    sector_size: 4096,
-                    prog_busy_ticks: 4,
-                    erase_busy_ticks: 64,
+                let flash = Box::into_raw(Box::new(mem::zeroed::<sys::FlashSim>()));
+                let fcfg = sys::FlashSimConfig {
+                    mem_bytes: cfg.mem_size as usize,
+                    page_size: cfg.page_size as usize,
+                    sector_size: cfg.sector_size as usize,
+                    prog_busy_ticks: cfg.prog_busy_ticks,
+                    erase_busy_ticks: cfg.erase_busy_ticks,
+                    read_dummy_cycles: cfg.read_dummy_cycles,
+                    wake_ticks: cfg.wake_ticks,
                 };
-                let r = sys::flash_sim_init(&mut *flash, &cfg);
-                if r != 0 { anyhow::bail!("flash_sim_init failed: {}", r); }
+                let r = sys::flash_sim_init(flash, &fcfg);
+                if r != 0 {
+                    drop(Box::from_raw(flash));
+                    anyhow::bail!("flash_sim_init failed: {}", r);
+                }
 
-                let mut axi: Box<sys::AxiSpiSim> = Box::new(mem::zeroed());
-                let r2 = sys::axi_spi_sim_init(&mut *axi, &mut *flash, 1024);
-                if r2 != 0 { anyhow::bail!("axi_spi_sim_init failed: {}", r2); }
+                let axi = Box::into_raw(Box::new(mem::zeroed::<sys::AxiSpiSim>()));
+                let r2 = sys::axi_spi_sim_init(axi, flash, cfg.fifo_size);
+                if r2 != 0 {
+                    sys::flash_sim_free(flash);
+                    drop(Box::from_raw(flash));
+                    drop(Box::from_raw(axi));
+                    anyhow::bail!("axi_spi_sim_init failed: {}", r2);
+                }
 
-                Ok(SimEnv { flash, axi })
+                Ok(SimEnv { flash, axi, cfg })
             }
         }
+
+        pub fn config(&self) -> &SimEnvConfig { &self.cfg }
+
+        /// Flash model state (status registers, array, busy op).
+        pub fn flash(&self) -> &sys::FlashSim { unsafe { &*self.flash } }
+
+        /// The flash array.
+        pub fn mem(&self) -> &[u8] {
+            let f = self.flash();
+            unsafe { std::slice::from_raw_parts(f.mem, f.cfg.mem_bytes) }
+        }
+
+        /// AXI engine state (registers, FIFOs, bus cycle count).
+        pub fn axi(&self) -> &sys::AxiSpiSim { unsafe { &*self.axi } }
     }
 
     impl Drop for SimEnv {
         fn drop(&mut self) {
             unsafe {
-                sys::axi_spi_sim_free(&mut *self.axi);
-                sys::flash_sim_free(&mut *self.flash);
+                sys::axi_spi_sim_free(self.axi);
+                sys::flash_sim_free(self.flash);
+                drop(Box::from_raw(self.axi));
+                drop(Box::from_raw(self.flash));
             }
         }
     }
 
-    pub fn driver_with_env(env: &mut SimEnv) -> anyhow::Result<Driver> {
-        Driver::new_with_sim(8192, 256, 4096, &mut *env.axi as *mut _)
+    /// Lets a Rust `RegisterIo` wrap the sim (e.g. to log or corrupt accesses).
+    impl RegisterIo for SimEnv {
+        fn read(&mut self, offset: u32) -> u32 { unsafe { sys::axi_spi_read(self.axi, offset) } }
+        fn write(&mut self, offset: u32, value: u32) { unsafe { sys::axi_spi_write(self.axi, offset, value) } }
+        fn tick(&mut self, ticks: u32) { unsafe { sys::axi_spi_tick(self.axi, ticks) } }
+    }
+
+    impl SimEnvConfig {
+        /// Geometry a driver over this sim must be configured with.
+        pub fn geometry(&self) -> FlashGeometry {
+            FlashGeometry { mem_size: self.mem_size, page_size: self.page_size, sector_size: self.sector_size }
+        }
+    }
+
+    /// Driver that owns `env`, configured from its geometry.
+    pub fn driver_with_env(env: SimEnv) -> anyhow::Result<Driver> {
+        let SimEnvConfig { mem_size, page_size, sector_size, .. } = env.cfg;
+        let axi = env.axi;
+        unsafe { Driver::init(mem_size, page_size, sector_size, sys::flash_ll_axi_sim_ops(), axi as *mut _, Backend::Sim(env)) }
+    }
+
+    impl Driver {
+        /// The sim this driver owns, if it was built by `driver_with_env`.
+        pub fn sim(&self) -> Option<&SimEnv> {
+            match &self.backend {
+                Backend::Sim(env) => Some(env),
+                _ => None,
+            }
+        }
+
+        /// Give the sim back (e.g. to reopen it with a fresh driver, like a reboot).
+        pub fn into_sim(self) -> Option<SimEnv> {
+            match self.backend {
+                Backend::Sim(env) => Some(env),
+                _ => None,
+            }
+        }
     }
 }
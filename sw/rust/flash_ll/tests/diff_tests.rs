@@ -0,0 +1,254 @@
+#![cfg(feature = "sim")]
+//! Differential tests: the C driver (`flash_ll::Driver`) and its Rust port
+//! (`flash_axi::AxiDriver`) run the same calls on identical sims and must
+//! return the same results through the same register accesses.
+
+use std::fmt::Debug;
+
+use flash_axi::AxiDriver;
+use flash_core::regs::{REG_SPI_STATUS, STATUS_RX_AVAIL, STATUS_TX_SPACE};
+use flash_core::{FlashError, ReadMode, RegisterIo, Timeouts};
+use flash_ll::sim::*;
+use flash_ll::Driver;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Access {
+    Read(u32, u32),
+    Write(u32, u32),
+    Tick(u32),
+}
+
+struct Recorder {
+    env: SimEnv,
+    log: Vec<Access>,
+    /// Hide RX_AVAIL/TX_SPACE, as if the SPI engine never moved a byte.
+    stall_fifos: bool,
+}
+
+impl RegisterIo for Recorder {
+    fn read(&mut self, offset: u32) -> u32 {
+        let mut v = self.env.read(offset);
+        if self.stall_fifos && offset == REG_SPI_STATUS {
+            v &= !(STATUS_RX_AVAIL | STATUS_TX_SPACE);
+        }
+        self.log.push(Access::Read(offset, v));
+        v
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        self.log.push(Access::Write(offset, value));
+        self.env.write(offset, value)
+    }
+    fn tick(&mut self, ticks: u32) {
+        self.log.push(Access::Tick(ticks));
+        self.env.tick(ticks)
+    }
+}
+
+fn show<T: Debug>(r: anyhow::Result<T>) -> String {
+    match r {
+        Ok(v) => format!("{:?}", v),
+        Err(e) => match e.downcast_ref::<FlashError>() {
+            Some(fe) => format!("{:?}", fe),
+            None => e.to_string(),
+        },
+    }
+}
+
+fn assert_same(c: (Vec<String>, Vec<Access>), rs: (Vec<String>, Vec<Access>)) {
+    assert_eq!(c.0, rs.0, "results differ");
+    if let Some(i) = c.1.iter().zip(&rs.1).position(|(a, b)| a != b) {
+        panic!("register access {} differs: C {:?}, Rust {:?}", i, c.1[i], rs.1[i]);
+    }
+    assert_eq!(c.1.len(), rs.1.len(), "trace lengths differ");
+}
+
+/// Run `$body` once with `$d` bound to each driver; `$out.push(show(..))`
+/// records the results to compare.
+macro_rules! differential {
+    ($cfg:expr, |$d:ident, $out:ident| $body:block) => {
+        differential!($cfg, false, |$d, $out| $body)
+    };
+    ($cfg:expr, $stall:expr, |$d:ident, $out:ident| $body:block) => {{
+        let cfg: SimEnvConfig = $cfg;
+        let geom = cfg.geometry();
+        let c = {
+            let rec = Recorder { env: cfg.clone().build().unwrap(), log: Vec::new(), stall_fifos: $stall };
+            #[allow(unused_mut)]
+            let mut $d = Driver::with_io(rec, geom).unwrap();
+            let mut $out: Vec<String> = Vec::new();
+            $body
+            ($out, $d.into_io::<Recorder>().unwrap().log)
+        };
+        let rs = {
+            let rec = Recorder { env: cfg.build().unwrap(), log: Vec::new(), stall_fifos: $stall };
+            #[allow(unused_mut)]
+            let mut $d = AxiDriver::new(rec, geom).unwrap();
+            let mut $out: Vec<String> = Vec::new();
+            $body
+            ($out, $d.into_io().log)
+        };
+        assert_same(c, rs);
+    }};
+}
+
+#[test]
+fn diff_program_and_read_modes() {
+    differential!(SimEnvConfig::new(), |d, out| {
+        let data: Vec<u8> = (0..600u32).map(|i| (i * 3) as u8).collect();
+        out.push(show(d.program(0xF0, &data)));
+        for mode in [
+            ReadMode::Standard,
+            ReadMode::Fast { dummy_cycles: 8 },
+            ReadMode::DualOutput { dummy_cycles: 8 },
+            ReadMode::QuadOutput { dummy_cycles: 8 },
+            ReadMode::Fast { dummy_cycles: 4 }, // wrong for the part: garbage, same garbage
+        ] {
+            out.push(show(d.set_read_mode(mode)));
+            let mut buf = vec![0u8; 40];
+            out.push(show(d.read(0x100, &mut buf).map(|()| buf)));
+        }
+        out.push(show(d.read(8190, &mut [0u8; 4])));
+        out.push(show(d.read(0, &mut [])));
+        out.push(show(d.program(0, &[])));
+        out.push(show(d.rdsr()));
+    });
+}
+
+#[test]
+fn diff_erase_suspend_resume() {
+    differential!(SimEnvConfig::new(), |d, out| {
+        out.push(show(d.program(0x1000, &[0x11; 16])));
+        out.push(show(d.sector_erase_start(0x1000)));
+        out.push(show(d.program(0x10, &[1]))); // EBUSY
+        out.push(show(d.suspend()));
+        out.push(show(d.program(0x10, &[1])));
+        out.push(show(d.program(0x1010, &[1]))); // erased sector: EBUSY
+        out.push(show(d.sector_erase_start(0)));
+        out.push(show(d.rdsr2()));
+        out.push(show(d.resume()));
+        let mut polls = 0;
+        while d.is_busy().unwrap() {
+            polls += 1;
+        }
+        out.push(polls.to_string());
+        out.push(show(d.suspend())); // idle
+        let mut buf = [0u8; 16];
+        out.push(show(d.read(0x1000, &mut buf).map(|()| buf)));
+        out.push(show(d.sector_erase(0x1FFF)));
+        out.push(show(d.sector_erase(0x2000)));
+    });
+}
+
+#[test]
+fn diff_block_protect() {
+    differential!(SimEnvConfig::new().mem_size(64 << 10), |d, out| {
+        out.push(show(d.set_protection(0xF000..0x10000)));
+        out.push(show(d.protection()));
+        out.push(show(d.program(0xEFFE, &[0; 4])));
+        out.push(show(d.sector_erase(0xF800)));
+        out.push(show(d.set_protection(0..0x123)));
+        out.push(show(d.set_protection(0..0x8000)));
+        out.push(show(d.program(0x8000, &[0xAB])));
+        out.push(show(d.program(0x7FFF, &[0xAB])));
+        out.push(show(d.set_protection(0..0)));
+        out.push(show(d.protection()));
+    });
+}
+
+#[test]
+fn diff_power_and_reset() {
+    differential!(SimEnvConfig::new().wake_ticks(7), |d, out| {
+        out.push(show(d.sector_erase_start(0)));
+        out.push(show(d.deep_power_down())); // EBUSY
+        out.push(show(d.reset()));
+        out.push(show(d.rdsr()));
+        out.push(show(d.deep_power_down()));
+        out.push(show(d.deep_power_down()));
+        out.push(show(d.rdsr()));
+        out.push(show(d.program(0, &[0])));
+        out.push(show(d.resume()));
+        out.push(show(d.release_power_down()));
+        out.push(show(d.program(0, &[0x5A])));
+    });
+}
+
+#[test]
+fn diff_otp() {
+    differential!(SimEnvConfig::new().mem_size(16 << 10), |d, out| {
+        out.push(show(d.otp_program(1, 0x10, b"serial-0042")));
+        let mut buf = [0u8; 11];
+        out.push(show(d.otp_read(1, 0x10, &mut buf).map(|()| buf)));
+        out.push(show(d.otp_read(3, 0, &mut buf)));
+        out.push(show(d.otp_read(0, 250, &mut buf)));
+        out.push(show(d.otp_lock(1)));
+        out.push(show(d.otp_lock(1)));
+        out.push(show(d.otp_is_locked(1)));
+        out.push(show(d.otp_is_locked(3)));
+        out.push(show(d.otp_program(1, 0, b"x")));
+        out.push(show(d.otp_erase(1)));
+        out.push(show(d.otp_erase(2)));
+    });
+}
+
+#[test]
+fn diff_otp_during_suspended_erase() {
+    differential!(SimEnvConfig::new(), |d, out| {
+        out.push(show(d.sector_erase_start(0)));
+        out.push(show(d.suspend()));
+        out.push(show(d.otp_program(0, 0, b"id"))); // EBUSY
+        out.push(show(d.otp_erase(0)));
+        out.push(show(d.rdsr()));
+        out.push(show(d.resume()));
+        while d.is_busy().unwrap() {}
+        out.push(show(d.otp_program(0, 0, b"id")));
+        let mut buf = [0u8; 2];
+        out.push(show(d.otp_read(0, 0, &mut buf).map(|()| buf)));
+    });
+}
+
+#[test]
+fn diff_4byte_addressing() {
+    differential!(SimEnvConfig::new().mem_size(32 << 20), |d, out| {
+        out.push(d.addr_bytes().to_string());
+        let hi = (32 << 20) - 0x1000 + 0x20;
+        out.push(show(d.program(hi, &[1, 2, 3, 4])));
+        out.push(show(d.set_read_mode(ReadMode::QuadOutput { dummy_cycles: 8 })));
+        let mut buf = [0u8; 4];
+        out.push(show(d.read(hi, &mut buf).map(|()| buf)));
+        out.push(show(d.sector_erase(hi)));
+    });
+}
+
+#[test]
+fn diff_timeouts_and_poll_yield() {
+    differential!(SimEnvConfig::new().wake_ticks(20), |d, out| {
+        let polls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let p = polls.clone();
+        d.set_poll_yield(move || {
+            p.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        let t = Timeouts { program: 2, erase: 30, status: 1, suspend: 0, reset: 5, fifo_slack: 0 };
+        d.set_timeouts(t);
+        out.push(show(d.program(0x10, &[0x5A; 300])));
+        out.push(show(d.sector_erase(0x1000)));
+        out.push(show(d.suspend()));
+        out.push(show(d.set_protection(0..0x1000)));
+        out.push(show(d.reset()));
+        out.push(show(d.deep_power_down()));
+        out.push(show(d.release_power_down()));
+        out.push(show(d.otp_erase(0)));
+        out.push(polls.load(std::sync::atomic::Ordering::Relaxed).to_string());
+    });
+}
+
+#[test]
+fn diff_fifo_budget_timeout() {
+    differential!(SimEnvConfig::new(), true, |d, out| {
+        d.set_timeouts(Timeouts { fifo_slack: 4, ..Timeouts::default() });
+        out.push(show(d.program(0, &[1; 4])));
+        out.push(show(d.otp_program(0, 0, b"id")));
+        out.push(show(d.read(0, &mut [0u8; 4])));
+        let ops = ["program", "otp_program", "read"];
+        assert_eq!(out, ops.map(|op| format!("{:?}", FlashError::Timeout { op })));
+    });
+}
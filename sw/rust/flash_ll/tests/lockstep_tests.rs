@@ -0,0 +1,181 @@
+#![cfg(feature = "sim")]
+//! The Rust model (`flash_axi::model`) against the C sim: every register
+//! access goes to both and every value read back must match.
+
+use flash_axi::model::{AxiSpiModel, NorConfig};
+use flash_axi::trace::{diff, replay, TracingIo};
+use flash_axi::AxiDriver;
+use flash_core::regs::*;
+use flash_core::{ReadMode, RegisterIo};
+use flash_ll::sim::*;
+use flash_ll::Driver;
+
+/// Forwards to both models and returns the C sim's value. Mismatches are
+/// recorded rather than panicking, since the C driver calls in via FFI.
+struct Lockstep {
+    c: SimEnv,
+    rs: AxiSpiModel,
+    accesses: usize,
+    mismatch: Option<String>,
+}
+
+impl Lockstep {
+    fn new(cfg: &SimEnvConfig) -> Self {
+        let nor = NorConfig {
+            mem_size: cfg.mem_size,
+            page_size: cfg.page_size,
+            sector_size: cfg.sector_size,
+            prog_busy_ticks: cfg.prog_busy_ticks,
+            erase_busy_ticks: cfg.erase_busy_ticks,
+            read_dummy_cycles: cfg.read_dummy_cycles,
+            wake_ticks: cfg.wake_ticks,
+        };
+        Lockstep {
+            c: cfg.clone().build().unwrap(),
+            rs: AxiSpiModel::with_config(nor, cfg.fifo_size).unwrap(),
+            accesses: 0,
+            mismatch: None,
+        }
+    }
+
+    fn check(&self) {
+        if let Some(m) = &self.mismatch {
+            panic!("{}", m);
+        }
+        assert_eq!(self.c.mem(), self.rs.nor().mem(), "arrays differ");
+        assert_eq!(self.c.axi().bus_cycles, self.rs.bus_cycles(), "bus cycles differ");
+    }
+}
+
+impl RegisterIo for Lockstep {
+    fn read(&mut self, offset: u32) -> u32 {
+        let (c, rs) = (self.c.read(offset), self.rs.read(offset));
+        if c != rs && self.mismatch.is_none() {
+            self.mismatch = Some(format!("access {}: read {:#x}: C {:#x}, Rust {:#x}", self.accesses, offset, c, rs));
+        }
+        self.accesses += 1;
+        c
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        self.c.write(offset, value);
+        self.rs.write(offset, value);
+        self.accesses += 1;
+    }
+    fn tick(&mut self, ticks: u32) {
+        self.c.tick(ticks);
+        self.rs.tick(ticks);
+    }
+}
+
+/// Exercise most of the command set through a driver.
+macro_rules! workout {
+    ($d:ident) => {{
+        let data: Vec<u8> = (0..1500u32).map(|i| (i * 11) as u8).collect();
+        $d.program(0xF0, &data).unwrap();
+        for mode in [
+            ReadMode::Fast { dummy_cycles: 8 },
+            ReadMode::DualOutput { dummy_cycles: 8 },
+            ReadMode::QuadOutput { dummy_cycles: 6 },
+            ReadMode::Standard,
+        ] {
+            $d.set_read_mode(mode).unwrap();
+            let mut out = vec![0u8; 1400];
+            $d.read(0x100, &mut out).unwrap();
+        }
+        $d.sector_erase_start(0x1000).unwrap();
+        assert!($d.suspend().unwrap());
+        $d.program(0x2000, &[1, 2, 3]).unwrap();
+        $d.resume().unwrap();
+        while $d.is_busy().unwrap() {}
+        $d.set_protection(0x3000..0x4000).unwrap();
+        assert!($d.program(0x3F00, &[0]).is_err());
+        $d.set_protection(0..0).unwrap();
+        $d.deep_power_down().unwrap();
+        $d.release_power_down().unwrap();
+        $d.otp_program(0, 4, b"id").unwrap();
+        $d.otp_lock(0).unwrap();
+        let mut id = [0u8; 2];
+        $d.otp_read(0, 4, &mut id).unwrap();
+        assert_eq!(&id, b"id");
+        $d.sector_erase_start(0).unwrap();
+        $d.reset().unwrap();
+    }};
+}
+
+fn cfg() -> SimEnvConfig {
+    SimEnvConfig::new().mem_size(16 << 10).erase_busy_ticks(100).fifo_size(256)
+}
+
+#[test]
+fn c_driver_sees_same_registers() {
+    let mut d = Driver::with_io(Lockstep::new(&cfg()), cfg().geometry()).unwrap();
+    workout!(d);
+    let ls = d.into_io::<Lockstep>().unwrap();
+    assert!(ls.accesses > 10_000);
+    ls.check();
+}
+
+#[test]
+fn rust_driver_sees_same_registers() {
+    let mut d = AxiDriver::new(Lockstep::new(&cfg()), cfg().geometry()).unwrap();
+    workout!(d);
+    d.into_io().check();
+}
+
+/// A trace captured on the C sim replays on the model without divergence.
+#[test]
+fn c_sim_trace_replays_on_model() {
+    let cfg = cfg();
+    let mut d = Driver::with_io(TracingIo::new(cfg.clone().build().unwrap()), cfg.geometry()).unwrap();
+    workout!(d);
+    let (env, captured) = d.into_io::<TracingIo<SimEnv>>().unwrap().into_inner();
+    let mut model = Lockstep::new(&cfg).rs;
+    let replayed = replay(&captured, &mut model);
+    if let Some(div) = diff(&captured, &replayed) {
+        panic!("{}", div);
+    }
+    assert_eq!(env.mem(), model.nor().mem());
+}
+
+/// Raw register pokes, including sequences no driver would issue.
+#[test]
+fn random_register_traffic() {
+    const CMDS: &[u8] = &[
+        CMD_WREN, CMD_RDSR, CMD_WRSR, CMD_RDSR2, CMD_SUSPEND, CMD_RESUME, CMD_DP, CMD_RDP, CMD_RSTEN,
+        CMD_RST, CMD_WRSR2, CMD_OTP_READ, CMD_OTP_PROG, CMD_OTP_ERASE, CMD_READ, CMD_PP, CMD_SE,
+        CMD_EN4B, CMD_EX4B, CMD_READ4, CMD_PP4, CMD_SE4, CMD_FAST_READ, CMD_DREAD, CMD_QREAD,
+        CMD_FAST_READ4, CMD_DREAD4, CMD_QREAD4, 0x9F,
+    ];
+    for seed in 1..=8u64 {
+        let mut rng = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut next = move |n: u64| {
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            rng % n
+        };
+        let cfg = SimEnvConfig::new().erase_busy_ticks(40).fifo_size(256);
+        let mut ls = Lockstep::new(&cfg);
+        for _ in 0..20_000 {
+            match next(12) {
+                0 | 1 => ls.write(REG_SPI_CMD, CMDS[next(CMDS.len() as u64) as usize] as u32),
+                2 => {
+                    let addr = next(cfg.mem_size as u64 + 64) as u32 | (next(4) as u32) << 24;
+                    ls.write(REG_SPI_ADDR, addr)
+                }
+                3 => ls.write(REG_SPI_LEN, next(300) as u32),
+                4 => ls.write(REG_SPI_DIN, next(256) as u32),
+                5 => ls.write(REG_SPI_FMT, next(12) as u32 | (next(3) as u32) << 8),
+                6 | 7 => ls.write(REG_SPI_CTRL, CTRL_CS_EN | CTRL_START),
+                8 | 9 => {
+                    ls.read(REG_SPI_DOUT);
+                }
+                10 => {
+                    ls.read(next(9) as u32 * 4);
+                }
+                _ => ls.tick(next(20) as u32),
+            }
+        }
+        ls.check();
+    }
+}
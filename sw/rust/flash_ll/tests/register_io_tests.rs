@@ -0,0 +1,130 @@
+#![cfg(feature = "sim")]
+use flash_ll::sim::*;
+use flash_ll::{Driver, RegisterIo};
+use flash_ll_sys as sys;
+
+/// Records every register access, then forwards to the wrapped backend.
+struct Logger<R> {
+    inner: R,
+    writes: Vec<(u32, u32)>,
+    reads: usize,
+}
+
+impl<R: RegisterIo> RegisterIo for Logger<R> {
+    fn read(&mut self, offset: u32) -> u32 {
+        self.reads += 1;
+        self.inner.read(offset)
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        self.writes.push((offset, value));
+        self.inner.write(offset, value)
+    }
+    fn tick(&mut self, ticks: u32) { self.inner.tick(ticks) }
+}
+
+/// Flips bit 0 of every data byte returned by a READ; status reads are left alone.
+struct CorruptReads<R> {
+    inner: R,
+    in_read: bool,
+}
+
+impl<R: RegisterIo> RegisterIo for CorruptReads<R> {
+    fn read(&mut self, offset: u32) -> u32 {
+        let v = self.inner.read(offset);
+        if self.in_read && offset == sys::FLASH_LL_REG_SPI_DOUT { v ^ 1 } else { v }
+    }
+    fn write(&mut self, offset: u32, value: u32) {
+        if offset == sys::FLASH_LL_REG_SPI_CMD {
+            self.in_read = value == sys::FLASH_LL_CMD_READ;
+        }
+        self.inner.write(offset, value)
+    }
+    fn tick(&mut self, ticks: u32) { self.inner.tick(ticks) }
+}
+
+#[test]
+fn logger_sees_driver_commands() {
+    let env = SimEnv::new().unwrap();
+    let geom = env.config().geometry();
+    let log = Logger { inner: env, writes: Vec::new(), reads: 0 };
+    let mut drv = Driver::with_io(log, geom).unwrap();
+    drv.program(0x100, &[1, 2, 3]).unwrap();
+    let mut out = [0u8; 3];
+    drv.read(0x100, &mut out).unwrap();
+    assert_eq!(out, [1, 2, 3]);
+
+    let log = drv.io::<Logger<SimEnv>>().unwrap();
+    let cmds: Vec<u32> = log
+        .writes
+        .iter()
+        .filter(|&&(off, _)| off == sys::FLASH_LL_REG_SPI_CMD)
+        .map(|&(_, v)| v)
+        .collect();
+    let pp = cmds.iter().position(|&c| c == sys::FLASH_LL_CMD_PP).unwrap();
+    assert_eq!(cmds[pp - 1], sys::FLASH_LL_CMD_WREN);
+    assert_eq!(*cmds.last().unwrap(), sys::FLASH_LL_CMD_READ);
+    assert!(log.reads > 0);
+    assert!(drv.io::<SimEnv>().is_none());
+}
+
+#[test]
+fn fault_injector_corrupts_reads() {
+    let env = SimEnv::new().unwrap();
+    let geom = env.config().geometry();
+    let mut drv = Driver::with_io(CorruptReads { inner: env, in_read: false }, geom).unwrap();
+    let mut out = [0u8; 4];
+    drv.read(0, &mut out).unwrap();
+    assert_eq!(out, [0xFE; 4]);
+
+    drv.program(0x10, &[0xA5]).unwrap();
+    drv.read(0x10, &mut out[..1]).unwrap();
+    assert_eq!(out[0], 0xA4);
+
+    // only the read path was corrupted; the array holds what was written
+    let env = drv.into_io::<CorruptReads<SimEnv>>().unwrap().inner;
+    let mut drv = driver_with_env(env).unwrap();
+    drv.read(0x10, &mut out[..1]).unwrap();
+    assert_eq!(out[0], 0xA5);
+}
+
+/// Turns the first `left` WRENs into RDSRs, so the sim ignores the following PP.
+struct DropWren<R> {
+    inner: R,
+    left: u32,
+}
+
+impl<R: RegisterIo> RegisterIo for DropWren<R> {
+    fn read(&mut self, offset: u32) -> u32 { self.inner.read(offset) }
+    fn write(&mut self, offset: u32, mut value: u32) {
+        if offset == sys::FLASH_LL_REG_SPI_CMD && value == sys::FLASH_LL_CMD_WREN && self.left > 0 {
+            self.left -= 1;
+            value = sys::FLASH_LL_CMD_RDSR;
+        }
+        self.inner.write(offset, value)
+    }
+    fn tick(&mut self, ticks: u32) { self.inner.tick(ticks) }
+}
+
+#[test]
+fn verify_catches_silently_dropped_program() {
+    use flash_core::{Flash, FlashError, Verify};
+    let env = SimEnv::new().unwrap();
+    let geom = env.config().geometry();
+    let mut drv = Driver::with_io(DropWren { inner: env, left: 1 }, geom).unwrap();
+    // without verification the lost PP goes unnoticed
+    drv.program(0x40, &[0x5A]).unwrap();
+    let mut b = [0u8; 1];
+    drv.read(0x40, &mut b).unwrap();
+    assert_eq!(b[0], 0xFF);
+
+    drv.io_mut::<DropWren<SimEnv>>().unwrap().left = 1;
+    let mut f = Verify::new(drv);
+    f.program(0x40, &[0x5A]).unwrap();
+    assert_eq!(f.retried(), 1);
+    f.read(0x40, &mut b).unwrap();
+    assert_eq!(b[0], 0x5A);
+
+    f.inner_mut().io_mut::<DropWren<SimEnv>>().unwrap().left = 10;
+    let err = f.program(0x41, &[0x00]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashError>(), Some(FlashError::VerifyFailed { op: "program", addr: 0x41, .. })));
+}
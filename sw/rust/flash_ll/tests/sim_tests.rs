@@ -1,10 +1,10 @@
 #![cfg(feature = "sim")]
 use flash_ll::sim::*;
+use flash_ll::ReadMode;
 
 #[test]
 fn drv_rdsr_after_reset() {
-    let mut env = SimEnv::new().unwrap();
-    let mut drv = driver_with_env(&mut env).unwrap();
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
     let st = drv.rdsr().unwrap();
     assert_eq!(st & 1, 0); // WIP=0
     assert_eq!((st >> 1) & 1, 0); // WEL=0
@@ -12,8 +12,7 @@ fn drv_rdsr_after_reset() {
 
 #[test]
 fn drv_pp_and_readback() {
-    let mut env = SimEnv::new().unwrap();
-    let mut drv = driver_with_env(&mut env).unwrap();
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
     let addr = 0x10u32;
     let data = [0xDEu8, 0xAD, 0xBE, 0xEF];
     drv.program(addr, &data).unwrap();
@@ -24,8 +23,7 @@ fn drv_pp_and_readback() {
 
 #[test]
 fn drv_page_boundary_multi_page() {
-    let mut env = SimEnv::new().unwrap();
-    let mut drv = driver_with_env(&mut env).unwrap();
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
     let addr = 0xFEu32;
     let data = [0xAAu8, 0xBB, 0xCC, 0xDD];
     drv.program(addr, &data).unwrap();
@@ -34,3 +32,240 @@ fn drv_page_boundary_multi_page() {
     assert_eq!(&out, &data);
 }
 
+#[test]
+fn drv_4byte_addr_no_alias() {
+    const MEM: u32 = 32 << 20;
+    let mut drv = driver_with_env(SimEnv::with_mem_size(MEM as usize).unwrap()).unwrap();
+    assert_eq!(drv.addr_bytes(), 4);
+    let hi = MEM - 0x1000 + 0x20;
+    let data = [0x11u8, 0x22, 0x33, 0x44];
+    drv.program(hi, &data).unwrap();
+    let mut out = [0u8; 4];
+    drv.read(hi, &mut out).unwrap();
+    assert_eq!(out, data);
+    // the 24-bit alias of the same address must be untouched
+    drv.read(hi & 0xFF_FFFF, &mut out).unwrap();
+    assert_eq!(out, [0xFF; 4]);
+    drv.sector_erase(hi).unwrap();
+    drv.read(hi, &mut out).unwrap();
+    assert_eq!(out, [0xFF; 4]);
+}
+
+#[test]
+fn drv_fast_dual_quad_reads() {
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    let data: Vec<u8> = (0..64u8).collect();
+    drv.program(0x200, &data).unwrap();
+    let mut cycles = Vec::new();
+    for mode in [
+        ReadMode::Standard,
+        ReadMode::Fast { dummy_cycles: 8 },
+        ReadMode::DualOutput { dummy_cycles: 8 },
+        ReadMode::QuadOutput { dummy_cycles: 8 },
+    ] {
+        drv.set_read_mode(mode).unwrap();
+        assert_eq!(drv.read_mode(), mode);
+        let before = drv.sim().unwrap().axi().bus_cycles;
+        let mut out = vec![0u8; data.len()];
+        drv.read(0x200, &mut out).unwrap();
+        assert_eq!(out, data, "{:?}", mode);
+        cycles.push(drv.sim().unwrap().axi().bus_cycles - before);
+    }
+    // multi-I/O reads move the 64-byte payload in fewer clocks
+    assert!(cycles[3] < cycles[2] && cycles[2] < cycles[1]);
+}
+
+#[test]
+fn drv_fast_read_wrong_dummy_corrupts() {
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    drv.program(0x20, &[0xA5, 0x5A]).unwrap();
+    drv.set_read_mode(ReadMode::Fast { dummy_cycles: 4 }).unwrap();
+    let mut out = [0u8; 2];
+    drv.read(0x20, &mut out).unwrap();
+    assert_ne!(out, [0xA5, 0x5A]);
+    assert!(drv.set_read_mode(ReadMode::Standard).is_ok());
+}
+
+#[test]
+fn drv_block_protect() {
+    use flash_core::FlashError;
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    assert_eq!(drv.protection().unwrap(), 0..0);
+    // 8 KiB part: BP=6 with TB set covers the bottom half (the "bootloader")
+    drv.set_protection(0..4096).unwrap();
+    assert_eq!(drv.protection().unwrap(), 0..4096);
+    let err = drv.program(0x10, &[0x00]).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Protected { addr: 0x10, len: 1 }));
+    let err = drv.sector_erase(0x20).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Protected { addr: 0, len: 4096 }));
+    drv.program(0x1000, &[0x5A]).unwrap();
+    let mut b = [0u8; 1];
+    drv.read(0x1000, &mut b).unwrap();
+    assert_eq!(b[0], 0x5A);
+    // ranges BP/TB cannot express are refused rather than rounded
+    assert!(drv.set_protection(0..100).is_err());
+    drv.set_protection(0..0).unwrap();
+    drv.program(0x10, &[0x00]).unwrap();
+}
+
+#[test]
+fn drv_erase_suspend_resume() {
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    assert!(!drv.suspend().unwrap());
+    drv.sector_erase_start(0x0000).unwrap();
+    assert!(drv.program(0x1000, &[0x11]).is_err()); // still erasing
+    assert!(drv.suspend().unwrap());
+    assert_eq!(drv.rdsr2().unwrap() & 0x80, 0x80); // SUS
+    drv.program(0x1000, &[0x11]).unwrap();
+    let mut b = [0u8; 1];
+    drv.read(0x1000, &mut b).unwrap();
+    assert_eq!(b[0], 0x11);
+    drv.resume().unwrap();
+    assert_eq!(drv.rdsr2().unwrap() & 0x80, 0);
+    while drv.is_busy().unwrap() {}
+    drv.read(0x0000, &mut b).unwrap();
+    assert_eq!(b[0], 0xFF);
+}
+
+#[test]
+fn drv_power_down_and_reset() {
+    use flash_core::{Flash, PowerState};
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    drv.program(0x20, &[0x5A]).unwrap();
+    drv.set_power(PowerState::DeepPowerDown).unwrap();
+    let mut b = [0u8; 1];
+    assert!(drv.read(0x20, &mut b).is_err());
+    assert!(drv.reset().is_err()); // ignored while powered down
+    drv.set_power(PowerState::Active).unwrap();
+    drv.read(0x20, &mut b).unwrap();
+    assert_eq!(b[0], 0x5A);
+    // reset drops a running erase
+    drv.sector_erase_start(0x1000).unwrap();
+    drv.reset().unwrap();
+    assert_eq!(drv.rdsr().unwrap() & 0x03, 0); // WIP, WEL
+    drv.program(0x1000, &[0x00]).unwrap();
+}
+
+#[test]
+fn drv_otp_provisioning() {
+    use flash_core::{FlashError, Otp};
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    assert_eq!(Otp::otp_layout(&drv), (3, 256));
+    drv.otp_program(1, 0, b"CAL:0123").unwrap();
+    let mut b = [0u8; 8];
+    drv.otp_read(1, 0, &mut b).unwrap();
+    assert_eq!(&b, b"CAL:0123");
+    assert!(!drv.otp_is_locked(1).unwrap());
+    drv.otp_lock(1).unwrap();
+    assert!(drv.otp_is_locked(1).unwrap());
+    let err = drv.otp_erase(1).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::OtpLocked { reg: 1 }));
+    // the array and the other registers are unaffected
+    drv.program(0x1000, &[0x00]).unwrap();
+    drv.otp_erase(0).unwrap();
+}
+
+#[test]
+fn drv_read_whole_image_in_one_call() {
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    // 8 KiB image through the 1 KiB RX FIFO
+    let img: Vec<u8> = (0..8192u32).map(|i| (i ^ (i >> 8)) as u8).collect();
+    drv.program(0, &img).unwrap();
+    let mut out = vec![0u8; 8192];
+    drv.read(0, &mut out).unwrap();
+    assert_eq!(out, img);
+}
+
+#[test]
+fn drv_custom_sim_config() {
+    use flash_core::Flash;
+    let env = SimEnvConfig::new()
+        .mem_size(64 << 10)
+        .page_size(128)
+        .sector_size(8192)
+        .prog_busy_ticks(20)
+        .erase_busy_ticks(500)
+        .fifo_size(128)
+        .build()
+        .unwrap();
+    let mut drv = driver_with_env(env).unwrap();
+    let g = drv.geometry();
+    assert_eq!((g.mem_size, g.page_size, g.sector_size), (64 << 10, 128, 8192));
+
+    // crosses a 128-byte page and is larger than the FIFO
+    let data: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+    drv.program(0x2040, &data).unwrap();
+    let mut out = vec![0u8; data.len()];
+    drv.read(0x2040, &mut out).unwrap();
+    assert_eq!(out, data);
+
+    // the erase covers the whole 8 KiB sector
+    drv.sector_erase(0x3FFF).unwrap();
+    drv.read(0x2040, &mut out).unwrap();
+    assert!(out.iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn sim_config_rejects_bad_geometry() {
+    assert!(SimEnvConfig::new().sector_size(1000).build().is_err());
+    assert!(SimEnvConfig::new().mem_size(6000).build().is_err());
+    assert!(SimEnvConfig::new().fifo_size(128).build().is_err());
+    // 4 GiB + 64 KiB would wrap to a valid 64 KiB
+    assert!(SimEnv::with_mem_size((1usize << 32) + (64 << 10)).is_err());
+}
+
+#[test]
+fn drv_owns_sim_and_moves_across_threads() {
+    use flash_core::Flash;
+    let mut drv = driver_with_env(SimEnv::new().unwrap()).unwrap();
+    drv.program(0x40, b"persist").unwrap();
+    // the env travels with the driver; nothing dangles on this thread
+    let env = std::thread::spawn(move || {
+        let mut out = [0u8; 7];
+        drv.read(0x40, &mut out).unwrap();
+        assert_eq!(&out, b"persist");
+        drv.into_sim().unwrap()
+    })
+    .join()
+    .unwrap();
+    // reopen the same array with a fresh driver, as after a reboot
+    let mut drv = driver_with_env(env).unwrap();
+    let mut out = [0u8; 7];
+    Flash::read(&mut drv, 0x40, &mut out).unwrap();
+    assert_eq!(&out, b"persist");
+    assert!(drv.sim().unwrap().axi().bus_cycles > 0);
+}
+
+#[test]
+fn drv_timeouts_name_the_op_and_poll_yield_runs() {
+    use flash_core::{FlashError, Timeouts};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let env = SimEnvConfig::new().erase_busy_ticks(64).prog_busy_ticks(4).build().unwrap();
+    let mut drv = driver_with_env(env).unwrap();
+    assert_eq!(drv.timeouts(), Timeouts::default());
+    let polls = Arc::new(AtomicU32::new(0));
+    let p = polls.clone();
+    drv.set_poll_yield(move || {
+        p.fetch_add(1, Ordering::Relaxed);
+    });
+    drv.set_timeouts(Timeouts { erase: 10, ..Timeouts::default() });
+
+    let err = drv.sector_erase(0x1000).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Timeout { op: "sector_erase" }));
+    assert_eq!(err.to_string(), "sector_erase timed out");
+    assert_eq!(polls.load(Ordering::Relaxed), 10);
+
+    while drv.is_busy().unwrap() {}
+    drv.set_timeouts(Timeouts { program: 1, ..Timeouts::default() });
+    let err = drv.program(0, &[1]).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Timeout { op: "program" }));
+
+    drv.clear_poll_yield();
+    let before = polls.load(Ordering::Relaxed);
+    drv.set_timeouts(Timeouts::default());
+    while drv.is_busy().unwrap() {}
+    drv.program(0x10, &[1]).unwrap();
+    assert_eq!(polls.load(Ordering::Relaxed), before);
+}
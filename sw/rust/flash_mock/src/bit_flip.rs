@@ -0,0 +1,78 @@
+use anyhow::Result;
+use flash_core::{Flash, FlashGeometry, PowerState};
+
+use crate::physics::Rng;
+
+/// Read-disturb injection: bits flip in the data a read returns, the array
+/// itself is untouched. Everything but `read` passes straight through.
+pub struct BitFlips<F: Flash> {
+    inner: F,
+    rng: Rng,
+    rate: f64,
+    /// One-shot flips for the next read covering each address.
+    once: Vec<(u32, u8)>,
+    flipped: u64,
+}
+
+impl<F: Flash> BitFlips<F> {
+    pub fn new(inner: F, seed: u64) -> Self {
+        Self { inner, rng: Rng::new(seed), rate: 0.0, once: Vec::new(), flipped: 0 }
+    }
+
+    /// Chance per byte read that one random bit of it comes back flipped.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate;
+    }
+
+    /// Flip `bit` of the byte at `addr` in the next read that covers it;
+    /// flips queued for the same byte go to successive reads.
+    pub fn flip_next(&mut self, addr: u32, bit: u8) {
+        self.once.push((addr, bit & 7));
+    }
+
+    /// Bits flipped so far.
+    pub fn flipped(&self) -> u64 { self.flipped }
+    pub fn inner(&self) -> &F { &self.inner }
+    pub fn inner_mut(&mut self) -> &mut F { &mut self.inner }
+    pub fn into_inner(self) -> F { self.inner }
+}
+
+impl<F: Flash> Flash for BitFlips<F> {
+    fn geometry(&self) -> FlashGeometry { self.inner.geometry() }
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.inner.read(addr, buf)?;
+        let end = addr as u64 + buf.len() as u64;
+        let mut once = std::mem::take(&mut self.once);
+        let mut hit = Vec::new();
+        once.retain(|&(a, bit)| {
+            if (addr as u64..end).contains(&(a as u64)) && !hit.contains(&a) {
+                hit.push(a);
+                buf[(a - addr) as usize] ^= 1 << bit;
+                self.flipped += 1;
+                false
+            } else {
+                true
+            }
+        });
+        self.once = once;
+        if self.rate > 0.0 {
+            for b in buf.iter_mut() {
+                if self.rng.chance(self.rate) {
+                    *b ^= 1 << (self.rng.next_u64() % 8);
+                    self.flipped += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> { self.inner.program(addr, data) }
+    fn sector_erase(&mut self, addr: u32) -> Result<()> { self.inner.sector_erase(addr) }
+    fn rdsr(&mut self) -> Result<u8> { self.inner.rdsr() }
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> { self.inner.sector_erase_start(addr) }
+    fn is_busy(&mut self) -> Result<bool> { self.inner.is_busy() }
+    fn suspend(&mut self) -> Result<bool> { self.inner.suspend() }
+    fn resume(&mut self) -> Result<()> { self.inner.resume() }
+    fn set_power(&mut self, state: PowerState) -> Result<()> { self.inner.set_power(state) }
+}
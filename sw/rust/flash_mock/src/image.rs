@@ -0,0 +1,76 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::Result;
+use flash_core::FlashGeometry;
+
+use crate::MockFlash;
+
+/// Whole-device state from `MockFlash::snapshot`, settings included. The
+/// array is shared with the flash until either side writes; wear counters,
+/// marginal/stuck bits and OTP are copied.
+#[derive(Clone)]
+pub struct Snapshot(MockFlash);
+
+impl Snapshot {
+    pub fn image(&self) -> &[u8] {
+        self.0.image()
+    }
+}
+
+impl PartialEq for Snapshot {
+    /// Same geometry and array contents.
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_image(&other.0)
+    }
+}
+
+impl MockFlash {
+    /// A blank-state mock holding `bytes` (`geom.mem_size` of them) as its array.
+    pub fn from_image(geom: FlashGeometry, bytes: Vec<u8>) -> Result<Self> {
+        if bytes.len() != geom.mem_size as usize {
+            anyhow::bail!("image is {} bytes, geometry says {}", bytes.len(), geom.mem_size);
+        }
+        let mut f = MockFlash::try_new(geom.mem_size, geom.page_size, geom.sector_size)?;
+        f.mem = Arc::new(bytes);
+        Ok(f)
+    }
+
+    /// The array as stored (marginal bits at their last programmed value).
+    pub fn image(&self) -> &[u8] {
+        &self.mem
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    /// Back to `snap`: array, counters, clock, OTP and settings.
+    pub fn restore(&mut self, snap: &Snapshot) {
+        *self = snap.0.clone();
+    }
+
+    pub fn same_image(&self, other: &MockFlash) -> bool {
+        self.geom == other.geom && (Arc::ptr_eq(&self.mem, &other.mem) || self.mem == other.mem)
+    }
+
+    /// Address ranges where the array differs from `other`.
+    pub fn diff(&self, other: &[u8]) -> Vec<Range<u32>> {
+        diff_images(&self.mem, other)
+    }
+}
+
+/// Address ranges where two images differ; bytes past the shorter one count
+/// as different.
+pub fn diff_images(a: &[u8], b: &[u8]) -> Vec<Range<u32>> {
+    let mut out: Vec<Range<u32>> = Vec::new();
+    for i in 0..a.len().max(b.len()) {
+        if a.get(i) == b.get(i) { continue; }
+        let i = i as u32;
+        match out.last_mut() {
+            Some(r) if r.end == i => r.end = i + 1,
+            _ => out.push(i..i + 1),
+        }
+    }
+    out
+}
@@ -1,15 +1,389 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use anyhow::Result;
-use flash_core::{Flash, FlashGeometry};
+use flash_core::{Flash, FlashError, FlashGeometry, Otp, PowerState};
+
+mod bit_flip;
+mod image;
+mod physics;
+mod power_cut;
+mod strict;
+mod timing;
+mod wear;
+pub use bit_flip::BitFlips;
+pub use image::{diff_images, Snapshot};
+pub use physics::Physics;
+pub use power_cut::{Cut, PowerCut, PowerLost};
+pub use strict::{OnViolation, Strict, Violation};
+pub use timing::Timing;
+pub use wear::{Endurance, Wear};
+use physics::Rng;
+
+/// Security registers modelled by `MockFlash` (same layout as the C sim).
+pub const OTP_REGS: u8 = 3;
+pub const OTP_SIZE: u32 = 256;
 
+#[derive(Clone)]
 pub struct MockFlash {
     geom: FlashGeometry,
-    mem: Vec<u8>,
+    /// Shared with snapshots and clones until one of them writes.
+    mem: Arc<Vec<u8>>,
+    erase_polls: u32,
+    pending: Option<PendingErase>,
+    power: PowerState,
+    otp: Vec<[u8; OTP_SIZE as usize]>,
+    otp_locked: [bool; OTP_REGS as usize],
+    physics: Physics,
+    rng: Rng,
+    /// Marginal bits left by interrupted ops, per byte address.
+    weak: HashMap<u32, u8>,
+    erase_counts: Vec<u32>,
+    endurance: Option<Endurance>,
+    wear_rng: Rng,
+    /// Worn-out bits stuck at 0, per byte address.
+    stuck: HashMap<u32, u8>,
+    strict: Option<Strict>,
+    violations: Vec<Violation>,
+    /// Programs per `Strict::unit` since its erase.
+    program_counts: Vec<u32>,
+    timing: Option<Timing>,
+    /// Virtual clock and the part of it the array spent programming/erasing (ns).
+    now: u64,
+    busy_time: u64,
+}
+
+// Erase started with `sector_erase_start`; the array is already blank, only
+// the busy window is modelled: `polls_left` status reads, or `time_left` ns
+// of running (not suspended) time with `Timing`.
+#[derive(Clone)]
+struct PendingErase {
+    base: u32,
+    polls_left: u32,
+    time_left: u64,
+    suspended: bool,
 }
 
 impl MockFlash {
+    /// Panics on a zero size; `try_new` returns the error instead.
     pub fn new(mem_size: u32, page_size: u32, sector_size: u32) -> Self {
+        Self::try_new(mem_size, page_size, sector_size).unwrap()
+    }
+
+    pub fn try_new(mem_size: u32, page_size: u32, sector_size: u32) -> Result<Self> {
         let geom = FlashGeometry { mem_size, page_size, sector_size };
-        Self { geom, mem: vec![0xFF; mem_size as usize] }
+        if mem_size == 0 || page_size == 0 || sector_size == 0 {
+            anyhow::bail!("invalid flash geometry: {:?}", geom);
+        }
+        Ok(Self {
+            geom,
+            mem: Arc::new(vec![0xFF; mem_size as usize]),
+            erase_polls: 0,
+            pending: None,
+            power: PowerState::Active,
+            otp: vec![[0xFF; OTP_SIZE as usize]; OTP_REGS as usize],
+            otp_locked: [false; OTP_REGS as usize],
+            physics: Physics::Ordered,
+            rng: Rng::new(0),
+            weak: HashMap::new(),
+            erase_counts: vec![0; mem_size.div_ceil(sector_size) as usize],
+            endurance: None,
+            wear_rng: Rng::new(0),
+            stuck: HashMap::new(),
+            strict: None,
+            violations: Vec::new(),
+            program_counts: Vec::new(),
+            timing: None,
+            now: 0,
+            busy_time: 0,
+        })
+    }
+
+    /// Charge program, erase and read time to a virtual clock. A background
+    /// erase then stays busy (WIP in `rdsr`) for `sector_erase` ns of
+    /// unsuspended time instead of `set_erase_polls` status reads.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = Some(timing);
+    }
+
+    pub fn timing(&self) -> Option<Timing> {
+        self.timing
+    }
+
+    /// Virtual time since creation (ns).
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Virtual time the array spent programming or erasing, background erases included (ns).
+    pub fn busy_time(&self) -> u64 {
+        self.busy_time
+    }
+
+    /// Let `ns` pass with the host doing something else; a running background
+    /// erase makes progress.
+    pub fn advance(&mut self, ns: u64) {
+        self.tick(ns);
+    }
+
+    /// Check NOR rules on every call: programs that need 0->1, cross a page
+    /// or exceed `max_programs` per unit, and array access while busy.
+    /// Fails unless `strict.unit` is non-zero and divides the sector.
+    pub fn set_strict(&mut self, strict: Strict) -> Result<()> {
+        if strict.unit == 0 || !self.geom.sector_size.is_multiple_of(strict.unit) {
+            anyhow::bail!("strict unit {} must divide the {}-byte sector", strict.unit, self.geom.sector_size);
+        }
+        self.program_counts = vec![0; self.geom.mem_size.div_ceil(strict.unit) as usize];
+        self.strict = Some(strict);
+        Ok(())
+    }
+
+    pub fn strict(&self) -> Option<Strict> {
+        self.strict
+    }
+
+    /// Violations seen under `OnViolation::Record`.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Wear sectors out after `endurance.limit` erases; unlimited by default.
+    pub fn set_endurance(&mut self, endurance: Endurance) {
+        if let Wear::StuckBits { seed, .. } = endurance.wear { self.wear_rng = Rng::new(seed); }
+        self.endurance = Some(endurance);
+    }
+
+    pub fn endurance(&self) -> Option<Endurance> {
+        self.endurance
+    }
+
+    /// Erases (including failed and interrupted ones) of the sector holding `addr`.
+    pub fn erase_count(&self, addr: u32) -> u32 {
+        self.erase_counts[(addr / self.geom.sector_size) as usize]
+    }
+
+    /// Erase counts of every sector, in address order.
+    pub fn erase_counts(&self) -> &[u32] {
+        &self.erase_counts
+    }
+
+    /// Bits stuck at 0 by wear.
+    pub fn stuck_bits(&self) -> u32 {
+        self.stuck.values().map(|m| m.count_ones()).sum()
+    }
+
+    /// How ops interrupted by `PowerCut` land; also reseeds the RNG that
+    /// marginal bits read from.
+    pub fn set_physics(&mut self, physics: Physics) {
+        if let Physics::Random { seed, .. } = physics { self.rng = Rng::new(seed); }
+        self.physics = physics;
+    }
+
+    pub fn physics(&self) -> Physics {
+        self.physics
+    }
+
+    /// Marginal bits currently in the array.
+    pub fn weak_bits(&self) -> u32 {
+        self.weak.values().map(|m| m.count_ones()).sum()
+    }
+
+    /// Keep a background erase (`sector_erase_start`) busy for `polls` status
+    /// reads. While busy, reads and programs fail unless the erase is
+    /// suspended; the default 0 completes erases immediately.
+    pub fn set_erase_polls(&mut self, polls: u32) {
+        self.erase_polls = polls;
+    }
+
+    /// A background erase is parked by `suspend`.
+    pub fn is_suspended(&self) -> bool {
+        self.pending.as_ref().is_some_and(|p| p.suspended)
+    }
+
+    pub fn power(&self) -> PowerState {
+        self.power
+    }
+
+    /// Program `data` at `addr` cut short after `applied` bytes.
+    pub(crate) fn interrupt_program(&mut self, addr: u32, data: &[u8], applied: u32) -> Result<()> {
+        if applied > 0 { self.program(addr, &data[..applied as usize])?; }
+        for (i, &d) in data.iter().enumerate().skip(applied as usize) {
+            let a = addr + i as u32;
+            if a as usize >= self.mem.len() { break; }
+            let old = self.mem[a as usize];
+            self.disturb(a, old & !d, 0);
+        }
+        Ok(())
+    }
+
+    /// Erase the sector holding `addr` cut short after `applied` bytes.
+    pub(crate) fn interrupt_erase(&mut self, addr: u32, applied: u32) {
+        let base = addr - addr % self.geom.sector_size;
+        if self.wear(base).is_err() { return; }
+        let end = (base + self.geom.sector_size).min(self.mem.len() as u32);
+        let done = (base + applied).min(end);
+        Arc::make_mut(&mut self.mem)[base as usize..done as usize].fill(0xFF);
+        self.weak.retain(|&a, _| a < base || a >= done);
+        self.reset_program_counts(base, end);
+        for a in done..end {
+            let old = self.mem[a as usize];
+            self.disturb(a, !old, 0xFF);
+        }
+        self.restick(base, end);
+    }
+
+    fn violation(&mut self, v: Violation) -> Result<()> {
+        match self.strict {
+            None => Ok(()),
+            Some(Strict { on_violation: OnViolation::Error, .. }) => Err(v.into()),
+            Some(Strict { on_violation: OnViolation::Record, .. }) => {
+                self.violations.push(v);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        let Some(strict) = self.strict else { return Ok(()) };
+        if data.is_empty() { return Ok(()); }
+        let len = data.len() as u32;
+        if addr % self.geom.page_size + len > self.geom.page_size {
+            self.violation(Violation::PageOverflow { addr, len })?;
+        }
+        let end = (addr as usize + data.len()).min(self.mem.len());
+        if let Some(i) = (addr as usize..end).position(|a| !self.mem[a] & data[a - addr as usize] != 0) {
+            let a = addr as usize + i;
+            self.violation(Violation::ZeroToOne { addr: a as u32, old: self.mem[a], new: data[i] })?;
+        }
+        let units = (addr / strict.unit) as usize..=((end as u32 - 1) / strict.unit) as usize;
+        for u in units {
+            self.program_counts[u] += 1;
+            let count = self.program_counts[u];
+            if count > strict.max_programs {
+                self.violation(Violation::TooManyPrograms { addr: u as u32 * strict.unit, count })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A new erase cycle for the program units of `[base, end)`.
+    fn reset_program_counts(&mut self, base: u32, end: u32) {
+        if let Some(strict) = self.strict {
+            self.program_counts[(base / strict.unit) as usize..end.div_ceil(strict.unit) as usize].fill(0);
+        }
+    }
+
+    /// Count an erase of the sector at `base` and apply wear past the limit.
+    fn wear(&mut self, base: u32) -> Result<()> {
+        let count = &mut self.erase_counts[(base / self.geom.sector_size) as usize];
+        *count += 1;
+        let Some(e) = self.endurance else { return Ok(()) };
+        if *count <= e.limit { return Ok(()); }
+        match e.wear {
+            Wear::EraseFails => Err(FlashError::EraseFailed { addr: base }.into()),
+            Wear::StuckBits { per_erase, .. } => {
+                for _ in 0..per_erase {
+                    let r = self.wear_rng.next_u64();
+                    let a = base + (r >> 3) as u32 % self.geom.sector_size;
+                    *self.stuck.entry(a).or_insert(0) |= 1 << (r & 7);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Stuck bits in `[start, end)` read 0 whatever an erase did.
+    fn restick(&mut self, start: u32, end: u32) {
+        if self.stuck.is_empty() { return; }
+        for a in start..end {
+            if let Some(m) = self.stuck.get(&a) { Arc::make_mut(&mut self.mem)[a as usize] &= !m; }
+        }
+    }
+
+    /// Bits `mask` of the byte at `addr` were moving towards `to` when power
+    /// went; under `Physics::Random` some get there and some go marginal.
+    fn disturb(&mut self, addr: u32, mask: u8, to: u8) {
+        let Physics::Random { flip, weak, .. } = self.physics else { return };
+        for bit in (0..8).map(|b| 1u8 << b).filter(|b| mask & b != 0) {
+            if self.rng.chance(flip) {
+                let b = &mut Arc::make_mut(&mut self.mem)[addr as usize];
+                *b = (*b & !bit) | (to & bit);
+            }
+            if self.rng.chance(weak) { *self.weak.entry(addr).or_insert(0) |= bit; }
+        }
+    }
+
+    /// State after power returns: nothing in flight, awake.
+    pub(crate) fn power_on(&mut self) {
+        self.pending = None;
+        self.power = PowerState::Active;
+    }
+
+    fn awake(&self) -> Result<()> {
+        if self.power != PowerState::Active { anyhow::bail!("flash in deep power-down"); }
+        Ok(())
+    }
+
+    /// The array side of an erase (checks, wear, blank), without its time.
+    fn erase_now(&mut self, addr: u32) -> Result<()> {
+        self.awake()?;
+        if self.pending.is_some() {
+            self.violation(Violation::Busy { op: "sector_erase", addr })?;
+            anyhow::bail!("erase while erase in progress");
+        }
+        let base = ((addr as usize) / self.geom.sector_size as usize) * self.geom.sector_size as usize;
+        let end = (base + self.geom.sector_size as usize).min(self.mem.len());
+        self.wear(base as u32)?;
+        Arc::make_mut(&mut self.mem)[base..end].fill(0xFF);
+        self.weak.retain(|&a, _| (a as usize) < base || a as usize >= end);
+        self.restick(base as u32, end as u32);
+        self.reset_program_counts(base as u32, end as u32);
+        Ok(())
+    }
+
+
+    /// Move the clock on; a running timed erase progresses and may finish.
+    fn tick(&mut self, ns: u64) {
+        self.now += ns;
+        if self.timing.is_none() { return; }
+        if let Some(p) = self.pending.as_mut().filter(|p| !p.suspended) {
+            let d = ns.min(p.time_left);
+            p.time_left -= d;
+            self.busy_time += d;
+            if p.time_left == 0 { self.pending = None; }
+        }
+    }
+
+    /// A foreground program/erase taking `ns`.
+    fn spend(&mut self, ns: u64) {
+        self.tick(ns);
+        self.busy_time += ns;
+    }
+
+    fn busy(&self) -> bool {
+        self.pending.as_ref().is_some_and(|p| !p.suspended)
+    }
+
+    /// `[addr, addr + len)` overlaps the sector of a suspended erase.
+    fn in_suspended_erase(&self, addr: u32, len: usize) -> bool {
+        self.pending.as_ref().is_some_and(|p| {
+            let (start, end) = (p.base as u64, p.base as u64 + self.geom.sector_size as u64);
+            (addr as u64) < end && addr as u64 + len as u64 > start
+        })
+    }
+
+    fn otp_range(&self, reg: u8, offset: u32, len: usize) -> Result<std::ops::Range<usize>> {
+        if reg >= OTP_REGS { anyhow::bail!("no OTP register {}", reg); }
+        let end = offset as usize + len;
+        if end > OTP_SIZE as usize { anyhow::bail!("oob"); }
+        Ok(offset as usize..end)
+    }
+
+    fn otp_writable(&self, reg: u8) -> Result<()> {
+        self.awake()?;
+        if self.pending.is_some() { anyhow::bail!("OTP write while erase in progress"); }
+        if self.otp_locked[reg as usize] { return Err(FlashError::OtpLocked { reg }.into()); }
+        Ok(())
     }
 }
 
@@ -17,33 +391,157 @@ impl Flash for MockFlash {
     fn geometry(&self) -> FlashGeometry { self.geom }
 
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.awake()?;
+        if self.busy() {
+            self.violation(Violation::Busy { op: "read", addr })?;
+            anyhow::bail!("read while erase in progress");
+        }
+        if self.in_suspended_erase(addr, buf.len()) { self.violation(Violation::Busy { op: "read", addr })?; }
         let end = addr as usize + buf.len();
         if end > self.mem.len() { anyhow::bail!("oob"); }
+        if let Some(t) = self.timing { self.tick(t.read_byte * buf.len() as u64); }
         buf.copy_from_slice(&self.mem[addr as usize..end]);
+        if !self.weak.is_empty() {
+            for (i, b) in buf.iter_mut().enumerate() {
+                if let Some(&m) = self.weak.get(&(addr + i as u32)) {
+                    *b = (*b & !m) | (self.rng.next_u64() as u8 & m);
+                }
+            }
+        }
         Ok(())
     }
 
     fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.awake()?;
+        if self.busy() {
+            self.violation(Violation::Busy { op: "program", addr })?;
+            anyhow::bail!("program while erase in progress");
+        }
+        if self.in_suspended_erase(addr, data.len()) {
+            self.violation(Violation::Busy { op: "program", addr })?;
+            anyhow::bail!("program into suspended erase sector");
+        }
+        self.check_program(addr, data)?;
         // Respect page boundary: split if needed
         let mut a = addr as usize;
         let mut off = 0usize;
+        let mut pages = 0;
+        let mem = Arc::make_mut(&mut self.mem);
         while off < data.len() {
             let page_off = a % self.geom.page_size as usize;
             let room = self.geom.page_size as usize - page_off;
             let chunk = room.min(data.len() - off);
             let end = a + chunk;
-            if end > self.mem.len() { anyhow::bail!("oob"); }
-            for i in 0..chunk { self.mem[a + i] &= data[off + i]; }
+            if end > mem.len() { anyhow::bail!("oob"); }
+            for i in 0..chunk {
+                mem[a + i] &= data[off + i];
+                // programming a 0 settles a marginal bit
+                if let Some(m) = self.weak.get_mut(&((a + i) as u32)) {
+                    *m &= data[off + i];
+                    if *m == 0 { self.weak.remove(&((a + i) as u32)); }
+                }
+            }
             a += chunk; off += chunk;
+            pages += 1;
         }
+        if let Some(t) = self.timing { self.spend(t.page_program * pages); }
         Ok(())
     }
 
     fn sector_erase(&mut self, addr: u32) -> Result<()> {
-        let base = ((addr as usize) / self.geom.sector_size as usize) * self.geom.sector_size as usize;
-        let end = (base + self.geom.sector_size as usize).min(self.mem.len());
-        for b in &mut self.mem[base..end] { *b = 0xFF; }
+        self.erase_now(addr)?;
+        if let Some(t) = self.timing { self.spend(t.sector_erase); }
+        Ok(())
+    }
+
+    fn rdsr(&mut self) -> Result<u8> {
+        self.awake()?;
+        if let Some(t) = self.timing {
+            self.tick(t.read_byte);
+            return Ok(self.busy() as u8); // WIP
+        }
+        match &mut self.pending {
+            Some(p) if !p.suspended => {
+                if p.polls_left == 0 {
+                    self.pending = None;
+                    return Ok(0);
+                }
+                p.polls_left -= 1;
+                Ok(1) // WIP
+            }
+            _ => Ok(0),
+        }
+    }
+
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> {
+        self.erase_now(addr)?;
+        let time_left = self.timing.map_or(0, |t| t.sector_erase);
+        if self.erase_polls > 0 || time_left > 0 {
+            let base = addr - addr % self.geom.sector_size;
+            self.pending = Some(PendingErase { base, polls_left: self.erase_polls, time_left, suspended: false });
+        }
+        Ok(())
+    }
+
+    fn suspend(&mut self) -> Result<bool> {
+        self.awake()?;
+        match &mut self.pending {
+            Some(p) => {
+                p.suspended = true;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.awake()?;
+        if let Some(p) = &mut self.pending { p.suspended = false; }
+        Ok(())
+    }
+
+    fn set_power(&mut self, state: PowerState) -> Result<()> {
+        if state == PowerState::DeepPowerDown && self.busy() {
+            anyhow::bail!("power-down while erase in progress");
+        }
+        self.power = state;
         Ok(())
     }
 }
 
+impl Otp for MockFlash {
+    fn otp_layout(&self) -> (u8, u32) { (OTP_REGS, OTP_SIZE) }
+
+    fn otp_read(&mut self, reg: u8, offset: u32, buf: &mut [u8]) -> Result<()> {
+        self.awake()?;
+        let r = self.otp_range(reg, offset, buf.len())?;
+        buf.copy_from_slice(&self.otp[reg as usize][r]);
+        Ok(())
+    }
+
+    fn otp_program(&mut self, reg: u8, offset: u32, data: &[u8]) -> Result<()> {
+        let r = self.otp_range(reg, offset, data.len())?;
+        self.otp_writable(reg)?;
+        for (b, d) in self.otp[reg as usize][r].iter_mut().zip(data) { *b &= d; }
+        Ok(())
+    }
+
+    fn otp_erase(&mut self, reg: u8) -> Result<()> {
+        self.otp_range(reg, 0, 0)?;
+        self.otp_writable(reg)?;
+        self.otp[reg as usize].fill(0xFF);
+        Ok(())
+    }
+
+    fn otp_lock(&mut self, reg: u8) -> Result<()> {
+        self.awake()?;
+        self.otp_range(reg, 0, 0)?;
+        self.otp_locked[reg as usize] = true;
+        Ok(())
+    }
+
+    fn otp_is_locked(&mut self, reg: u8) -> Result<bool> {
+        self.otp_range(reg, 0, 0)?;
+        Ok(self.otp_locked[reg as usize])
+    }
+}
@@ -0,0 +1,38 @@
+/// How a program or erase cut short by power loss (`PowerCut`) lands.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Physics {
+    /// Programs land byte by byte in order, erases from the start of the
+    /// sector: everything before the cut is done, nothing after it.
+    #[default]
+    Ordered,
+    /// Bytes before the cut are done; every other bit the op would change
+    /// flips with probability `flip`, and each of those bits (flipped or not)
+    /// is left marginal with probability `weak`. Marginal bits read back
+    /// randomly until a program of 0 or an erase settles them.
+    Random { seed: u64, flip: f64, weak: f64 },
+}
+
+/// xorshift64*: small, and the same sequence for a seed on every platform.
+#[derive(Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // splitmix64 step so that seed 0 and nearby seeds are usable
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub(crate) fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
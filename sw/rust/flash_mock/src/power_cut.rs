@@ -0,0 +1,145 @@
+use anyhow::Result;
+use flash_core::{Flash, FlashGeometry, PowerState};
+
+use crate::MockFlash;
+
+/// Where `PowerCut` drops power.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cut {
+    /// Never; counts operations and bytes (a dry run).
+    Never,
+    /// During the `op`-th program/erase (0-based) after `applied` of its bytes.
+    Op { op: u64, applied: u32 },
+    /// Once `n` bytes have been programmed or erased in total, counting every
+    /// byte of a program and every byte of an erased sector.
+    Byte(u64),
+}
+
+/// Error returned by the interrupted call and every call after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerLost;
+
+impl std::fmt::Display for PowerLost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("power lost")
+    }
+}
+
+impl std::error::Error for PowerLost {}
+
+/// Power-loss injection over `MockFlash`. Programs and erases are counted
+/// (`ops`, `bytes`); at the `Cut` point the running one is left partially
+/// applied as the mock's `Physics` says (by default programs byte by byte in
+/// order, erases from the start of the sector) and the flash goes dead.
+/// `reset` powers it back up.
+pub struct PowerCut {
+    inner: MockFlash,
+    cut: Cut,
+    ops: u64,
+    bytes: u64,
+    dead: bool,
+}
+
+impl PowerCut {
+    pub fn new(inner: MockFlash, cut: Cut) -> Self {
+        Self { inner, cut, ops: 0, bytes: 0, dead: false }
+    }
+
+    /// Programs and erases started so far.
+    pub fn ops(&self) -> u64 { self.ops }
+    /// Bytes programmed or erased so far.
+    pub fn bytes(&self) -> u64 { self.bytes }
+    pub fn is_cut(&self) -> bool { self.dead }
+    pub fn inner(&self) -> &MockFlash { &self.inner }
+
+    /// Power back on: the surviving image, with nothing in flight.
+    pub fn reset(self) -> MockFlash {
+        let mut f = self.inner;
+        f.power_on();
+        f
+    }
+
+    fn alive(&self) -> Result<()> {
+        if self.dead { return Err(PowerLost.into()); }
+        Ok(())
+    }
+
+    /// Bytes of a `len`-byte op that land before the cut, if it cuts this op.
+    fn budget(&mut self, len: u32) -> Option<u32> {
+        let op = self.ops;
+        self.ops += 1;
+        let applied = match self.cut {
+            Cut::Never => None,
+            Cut::Op { op: at, applied } if at == op => Some(applied.min(len)),
+            Cut::Op { .. } => None,
+            Cut::Byte(n) => (n < self.bytes + len as u64).then(|| n.saturating_sub(self.bytes) as u32),
+        };
+        self.bytes += applied.unwrap_or(len) as u64;
+        if applied.is_some() { self.dead = true; }
+        applied
+    }
+
+    fn erase(&mut self, addr: u32, start: bool) -> Result<()> {
+        self.alive()?;
+        let g = self.inner.geometry();
+        let base = addr - addr % g.sector_size;
+        let len = g.sector_size.min(g.mem_size.saturating_sub(base));
+        match self.budget(len) {
+            None if start => self.inner.sector_erase_start(addr),
+            None => self.inner.sector_erase(addr),
+            Some(n) => {
+                self.inner.interrupt_erase(base, n);
+                Err(PowerLost.into())
+            }
+        }
+    }
+}
+
+impl Flash for PowerCut {
+    fn geometry(&self) -> FlashGeometry { self.inner.geometry() }
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.alive()?;
+        self.inner.read(addr, buf)
+    }
+
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.alive()?;
+        match self.budget(data.len() as u32) {
+            None => self.inner.program(addr, data),
+            Some(n) => {
+                self.inner.interrupt_program(addr, data, n)?;
+                Err(PowerLost.into())
+            }
+        }
+    }
+
+    fn sector_erase(&mut self, addr: u32) -> Result<()> { self.erase(addr, false) }
+
+    fn rdsr(&mut self) -> Result<u8> {
+        self.alive()?;
+        self.inner.rdsr()
+    }
+
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> { self.erase(addr, true) }
+
+    fn is_busy(&mut self) -> Result<bool> {
+        self.alive()?;
+        self.inner.is_busy()
+    }
+
+    fn suspend(&mut self) -> Result<bool> {
+        self.alive()?;
+        self.inner.suspend()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.alive()?;
+        self.inner.resume()
+    }
+
+    fn set_power(&mut self, state: PowerState) -> Result<()> {
+        self.alive()?;
+        self.inner.set_power(state)
+    }
+}
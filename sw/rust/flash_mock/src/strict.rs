@@ -0,0 +1,57 @@
+/// What `MockFlash` does on a broken NOR rule once strict checking is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnViolation {
+    /// Fail the call with the `Violation`.
+    Error,
+    /// Keep it in `MockFlash::violations()` and carry on as a lenient mock would.
+    Record,
+}
+
+/// Strict NOR checking for `MockFlash::set_strict`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Strict {
+    pub on_violation: OnViolation,
+    /// Bytes per program unit, the granularity `max_programs` is counted in.
+    pub unit: u32,
+    /// Programs each unit takes between erases.
+    pub max_programs: u32,
+}
+
+impl Strict {
+    /// Defaults: 4 programs per 16-byte unit.
+    pub fn new(on_violation: OnViolation) -> Self {
+        Strict { on_violation, unit: 16, max_programs: 4 }
+    }
+}
+
+/// A NOR rule broken by a call to a strict `MockFlash`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The program needed bits at `addr` to go 0->1: `old` can't become `new`.
+    ZeroToOne { addr: u32, old: u8, new: u8 },
+    /// One program of `len` bytes at `addr` ran past the end of its page.
+    PageOverflow { addr: u32, len: u32 },
+    /// The program unit at `addr` was programmed a `count`th time since its erase.
+    TooManyPrograms { addr: u32, count: u32 },
+    /// `op` touched the array while a program/erase was in progress.
+    Busy { op: &'static str, addr: u32 },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::ZeroToOne { addr, old, new } => {
+                write!(f, "program needs 0->1 at {:#x}: {:#04x} -> {:#04x}", addr, old, new)
+            }
+            Violation::PageOverflow { addr, len } => {
+                write!(f, "program of {} bytes at {:#x} crosses a page boundary", len, addr)
+            }
+            Violation::TooManyPrograms { addr, count } => {
+                write!(f, "program unit at {:#x} programmed {} times since erase", addr, count)
+            }
+            Violation::Busy { op, addr } => write!(f, "{} at {:#x} while busy", op, addr),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
@@ -0,0 +1,16 @@
+/// Virtual-clock costs for `MockFlash::set_timing`, in nanoseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    /// One page program, whatever its length.
+    pub page_program: u64,
+    pub sector_erase: u64,
+    /// Each byte of an array read, and each status read.
+    pub read_byte: u64,
+}
+
+impl Default for Timing {
+    /// Typical 4 KiB-sector SPI NOR on a 50 MHz single-bit bus.
+    fn default() -> Self {
+        Timing { page_program: 700_000, sector_erase: 45_000_000, read_byte: 160 }
+    }
+}
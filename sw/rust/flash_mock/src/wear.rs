@@ -0,0 +1,16 @@
+/// What an erase past a sector's endurance limit does (`MockFlash::set_endurance`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wear {
+    /// The erase fails with `FlashError::EraseFailed` and leaves the sector as it was.
+    EraseFails,
+    /// The erase goes through but `per_erase` more bits of the sector, picked
+    /// from an RNG seeded with `seed`, stick at 0 for good.
+    StuckBits { per_erase: u32, seed: u64 },
+}
+
+/// Erase cycles a sector survives and how it fails after that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Endurance {
+    pub limit: u32,
+    pub wear: Wear,
+}
@@ -0,0 +1,52 @@
+use flash_core::Flash;
+use flash_mock::{BitFlips, MockFlash};
+
+#[test]
+fn flip_next_hits_one_read_only() {
+    let mut f = BitFlips::new(MockFlash::new(8192, 256, 4096), 1);
+    f.program(0x10, &[0x00; 4]).unwrap();
+    f.flip_next(0x12, 3);
+    let mut b = [0u8; 4];
+    f.read(0, &mut b).unwrap();
+    assert_eq!(f.flipped(), 0, "read did not cover the address");
+    f.read(0x10, &mut b).unwrap();
+    assert_eq!(b, [0x00, 0x00, 0x08, 0x00]);
+    f.read(0x10, &mut b).unwrap();
+    assert_eq!(b, [0x00; 4], "transient: the array is untouched");
+    assert_eq!(f.flipped(), 1);
+}
+
+#[test]
+fn rate_flips_single_bits_per_byte() {
+    let mut f = BitFlips::new(MockFlash::new(8192, 256, 4096), 7);
+    f.set_rate(0.25);
+    let mut buf = vec![0u8; 4096];
+    f.read(0, &mut buf).unwrap();
+    let bad: Vec<u8> = buf.iter().filter(|&&b| b != 0xFF).copied().collect();
+    assert_eq!(bad.len() as u64, f.flipped());
+    assert!((800..1250).contains(&bad.len()), "{} flips", bad.len());
+    assert!(bad.iter().all(|b| (!b).count_ones() == 1));
+
+    // Same seed, same flips
+    let mut g = BitFlips::new(MockFlash::new(8192, 256, 4096), 7);
+    g.set_rate(0.25);
+    let mut again = vec![0u8; 4096];
+    g.read(0, &mut again).unwrap();
+    assert_eq!(again, buf);
+    assert!(g.into_inner().same_image(f.inner()));
+}
+
+#[test]
+fn flips_queued_for_one_byte_hit_successive_reads() {
+    let mut f = BitFlips::new(MockFlash::new(8192, 256, 4096), 1);
+    f.flip_next(0x20, 0);
+    f.flip_next(0x20, 0);
+    let mut b = [0u8; 1];
+    for _ in 0..2 {
+        f.read(0x20, &mut b).unwrap();
+        assert_eq!(b, [0xFE]);
+    }
+    f.read(0x20, &mut b).unwrap();
+    assert_eq!(b, [0xFF]);
+    assert_eq!(f.flipped(), 2);
+}
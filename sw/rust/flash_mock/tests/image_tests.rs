@@ -0,0 +1,51 @@
+use flash_core::{Flash, FlashGeometry};
+use flash_mock::{diff_images, MockFlash};
+
+const GEOM: FlashGeometry = FlashGeometry { mem_size: 8192, page_size: 256, sector_size: 4096 };
+
+#[test]
+fn image_round_trips() {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.program(0x10, &[1, 2, 3]).unwrap();
+    let mut copy = MockFlash::from_image(GEOM, m.image().to_vec()).unwrap();
+    assert!(copy.same_image(&m));
+    let mut b = [0u8; 3];
+    copy.read(0x10, &mut b).unwrap();
+    assert_eq!(b, [1, 2, 3]);
+
+    let err = MockFlash::from_image(GEOM, vec![0xFF; 100]).err().unwrap();
+    assert_eq!(err.to_string(), "image is 100 bytes, geometry says 8192");
+}
+
+#[test]
+fn snapshot_restore_and_branches() {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.program(0, &[0xAA]).unwrap();
+    let snap = m.snapshot();
+
+    let mut branch = m.clone();
+    m.sector_erase(0).unwrap();
+    m.program(0x1000, &[0x55]).unwrap();
+    branch.program(1, &[0x11]).unwrap();
+    assert_eq!(m.diff(snap.image()), vec![0..1, 0x1000..0x1001]);
+    assert_eq!(branch.diff(snap.image()), vec![1..2]);
+    assert_eq!(m.erase_counts(), &[1, 0]);
+
+    m.restore(&snap);
+    assert!(m.snapshot() == snap);
+    assert_eq!(m.erase_counts(), &[0, 0]);
+    let mut b = [0u8; 2];
+    m.read(0, &mut b).unwrap();
+    assert_eq!(b, [0xAA, 0xFF]);
+    // the branch kept its own array
+    branch.read(0, &mut b).unwrap();
+    assert_eq!(b, [0xAA, 0x11]);
+}
+
+#[test]
+fn diff_merges_adjacent_bytes() {
+    let a = [0u8, 1, 2, 3, 4, 5];
+    assert_eq!(diff_images(&a, &a), vec![]);
+    assert_eq!(diff_images(&a, &[0, 9, 9, 3, 9, 5]), vec![1..3, 4..5]);
+    assert_eq!(diff_images(&a, &a[..4]), vec![4..6]);
+}
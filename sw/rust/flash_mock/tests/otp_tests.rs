@@ -0,0 +1,45 @@
+use flash_core::{FlashError, Otp};
+use flash_mock::MockFlash;
+
+// Typical factory flow: write identity, verify, lock
+fn provision<F: Otp>(f: &mut F, serial: &[u8]) -> anyhow::Result<()> {
+    f.otp_program(0, 0, serial)?;
+    let mut back = vec![0u8; serial.len()];
+    f.otp_read(0, 0, &mut back)?;
+    anyhow::ensure!(back == serial, "OTP readback mismatch");
+    f.otp_lock(0)
+}
+
+#[test]
+fn provisioning_locks_register() {
+    let mut f = MockFlash::new(8192, 256, 4096);
+    assert_eq!(f.otp_layout(), (3, 256));
+    provision(&mut f, b"SN-000042").unwrap();
+    assert!(f.otp_is_locked(0).unwrap());
+    assert!(!f.otp_is_locked(1).unwrap());
+
+    let err = f.otp_program(0, 0x20, &[0x00]).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::OtpLocked { reg: 0 }));
+    let err = f.otp_erase(0).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::OtpLocked { reg: 0 }));
+    let mut b = [0u8; 9];
+    f.otp_read(0, 0, &mut b).unwrap();
+    assert_eq!(&b, b"SN-000042");
+    // a second provisioning attempt is refused rather than silently ANDed in
+    assert!(provision(&mut f, b"SN-000043").is_err());
+}
+
+#[test]
+fn unlocked_register_programs_and_erases() {
+    let mut f = MockFlash::new(8192, 256, 4096);
+    f.otp_program(2, 0xF0, &[0xF0; 16]).unwrap();
+    f.otp_program(2, 0xF0, &[0x0F]).unwrap();
+    let mut b = [0u8; 2];
+    f.otp_read(2, 0xF0, &mut b).unwrap();
+    assert_eq!(b, [0x00, 0xF0]);
+    f.otp_erase(2).unwrap();
+    f.otp_read(2, 0xF0, &mut b).unwrap();
+    assert_eq!(b, [0xFF, 0xFF]);
+    assert!(f.otp_program(2, 0xFF, &[0, 0]).is_err()); // past the register
+    assert!(f.otp_read(3, 0, &mut b).is_err());
+}
@@ -0,0 +1,133 @@
+use flash_core::Flash;
+use flash_mock::{Cut, MockFlash, Physics, PowerCut, PowerLost};
+
+fn lost(r: anyhow::Result<()>) -> bool {
+    r.unwrap_err().downcast_ref::<PowerLost>().is_some()
+}
+
+#[test]
+fn dry_run_counts_ops_and_bytes() {
+    let mut f = PowerCut::new(MockFlash::new(8192, 256, 4096), Cut::Never);
+    f.program(0x10, &[1, 2, 3]).unwrap();
+    f.sector_erase(0x1000).unwrap();
+    f.sector_erase_start(0).unwrap();
+    let mut b = [0u8; 1];
+    f.read(0, &mut b).unwrap();
+    assert_eq!((f.ops(), f.bytes()), (3, 3 + 4096 + 4096));
+    assert!(!f.is_cut());
+}
+
+#[test]
+fn cut_during_program_keeps_prefix_and_kills_the_flash() {
+    let mut f = PowerCut::new(MockFlash::new(8192, 256, 4096), Cut::Op { op: 1, applied: 2 });
+    f.program(0x0, &[0xAA]).unwrap();
+    assert!(lost(f.program(0x10, &[1, 2, 3, 4])));
+    assert!(f.is_cut());
+    assert!(lost(f.read(0, &mut [0u8; 1])));
+    assert!(lost(f.program(0x20, &[0])));
+    assert!(f.rdsr().is_err() && f.is_busy().is_err());
+
+    let mut m = f.reset();
+    let mut b = [0u8; 5];
+    m.read(0x10, &mut b).unwrap();
+    assert_eq!(b, [1, 2, 0xFF, 0xFF, 0xFF]);
+    m.read(0, &mut b[..1]).unwrap();
+    assert_eq!(b[0], 0xAA);
+}
+
+#[test]
+fn byte_cut_lands_inside_an_erase() {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.program(0x1000, &[0u8; 64]).unwrap();
+    let mut f = PowerCut::new(m, Cut::Byte(2 + 10));
+    f.program(0, &[0, 0]).unwrap();
+    assert!(lost(f.sector_erase_start(0x1000)));
+    let mut m = f.reset();
+    let mut b = [0u8; 64];
+    m.read(0x1000, &mut b).unwrap();
+    assert!(b[..10].iter().all(|&x| x == 0xFF) && b[10..].iter().all(|&x| x == 0));
+    // the reset flash is usable again
+    m.sector_erase(0x1000).unwrap();
+}
+
+#[test]
+fn cut_at_op_boundary_applies_nothing() {
+    let mut f = PowerCut::new(MockFlash::new(8192, 256, 4096), Cut::Byte(4));
+    f.program(0, &[0; 4]).unwrap();
+    assert!(lost(f.program(4, &[0; 4])));
+    let mut m = f.reset();
+    let mut b = [0u8; 8];
+    m.read(0, &mut b).unwrap();
+    assert_eq!(b, [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+fn random(seed: u64, flip: f64, weak: f64) -> MockFlash {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.set_physics(Physics::Random { seed, flip, weak });
+    m
+}
+
+fn cut_program(m: MockFlash, data: &[u8], applied: u32) -> MockFlash {
+    let mut f = PowerCut::new(m, Cut::Op { op: 0, applied });
+    assert!(lost(f.program(0x100, data)));
+    f.reset()
+}
+
+#[test]
+fn random_program_clears_a_subset_of_bits() {
+    let data: Vec<u8> = (0..64).map(|i| i as u8).collect();
+    let mut m = cut_program(random(7, 0.5, 0.0), &data, 8);
+    let mut b = [0u8; 64];
+    m.read(0x100, &mut b).unwrap();
+    assert_eq!(b[..8], data[..8]);
+    // only bits the program clears move, and only some of them
+    assert!(b.iter().zip(&data).all(|(&got, &d)| got & d == d));
+    assert!(b[8..] != data[8..] && b[8..].iter().any(|&x| x != 0xFF));
+    assert_eq!(m.weak_bits(), 0);
+
+    // same seed, same outcome
+    let mut again = cut_program(random(7, 0.5, 0.0), &data, 8);
+    let mut c = [0u8; 64];
+    again.read(0x100, &mut c).unwrap();
+    assert_eq!(b, c);
+}
+
+#[test]
+fn random_erase_sets_a_subset_of_bits() {
+    let mut m = random(3, 0.5, 0.0);
+    m.program(0x1000, &[0u8; 256]).unwrap();
+    let mut f = PowerCut::new(m, Cut::Byte(16));
+    assert!(lost(f.sector_erase(0x1000)));
+    let mut m = f.reset();
+    let mut b = [0u8; 256];
+    m.read(0x1000, &mut b).unwrap();
+    assert!(b[..16].iter().all(|&x| x == 0xFF));
+    let ones: u32 = b[16..].iter().map(|x| x.count_ones()).sum();
+    assert!(ones > 0 && ones < 240 * 8, "{} bits erased", ones);
+}
+
+#[test]
+fn weak_bits_read_unstably_until_settled() {
+    let mut m = cut_program(random(11, 0.0, 1.0), &[0u8; 16], 0);
+    assert_eq!(m.weak_bits(), 16 * 8);
+    let reads: Vec<[u8; 16]> = (0..4)
+        .map(|_| {
+            let mut b = [0u8; 16];
+            m.read(0x100, &mut b).unwrap();
+            b
+        })
+        .collect();
+    assert!(reads.windows(2).any(|w| w[0] != w[1]));
+
+    // programming 0 settles the bits it covers, erasing settles the rest
+    m.program(0x100, &[0u8; 8]).unwrap();
+    assert_eq!(m.weak_bits(), 8 * 8);
+    let mut b = [0xFFu8; 8];
+    m.read(0x100, &mut b).unwrap();
+    assert_eq!(b, [0; 8]);
+    m.sector_erase(0).unwrap();
+    assert_eq!(m.weak_bits(), 0);
+    let mut b = [0u8; 16];
+    m.read(0x100, &mut b).unwrap();
+    assert_eq!(b, [0xFF; 16]);
+}
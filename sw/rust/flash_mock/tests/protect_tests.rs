@@ -0,0 +1,55 @@
+use flash_core::{Flash, FlashError, WriteProtect};
+use flash_mock::MockFlash;
+
+fn protected_mock() -> WriteProtect<MockFlash> {
+    let mut f = WriteProtect::new(MockFlash::new(8192, 256, 4096));
+    f.set_protection(0..4096); // "bootloader" sector
+    f
+}
+
+#[test]
+fn protected_program_is_an_error() {
+    let mut f = protected_mock();
+    let err = f.program(0x10, &[0x00]).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Protected { addr: 0x10, len: 1 }));
+    // straddling the boundary is rejected as a whole
+    assert!(f.program(0x0FFF, &[0x00, 0x00]).is_err());
+    let mut b = [0u8; 2];
+    f.read(0x0FFF, &mut b).unwrap();
+    assert_eq!(b, [0xFF, 0xFF]);
+}
+
+#[test]
+fn protected_erase_is_an_error() {
+    let mut f = protected_mock();
+    f.inner_mut().program(0x20, &[0x00]).unwrap();
+    let err = f.sector_erase(0x800).unwrap_err();
+    assert_eq!(err.downcast_ref::<FlashError>(), Some(&FlashError::Protected { addr: 0, len: 4096 }));
+    let mut b = [0u8; 1];
+    f.read(0x20, &mut b).unwrap();
+    assert_eq!(b[0], 0x00);
+}
+
+#[test]
+fn unprotected_range_and_clear() {
+    let mut f = protected_mock();
+    f.program(0x1000, &[0x12]).unwrap();
+    f.sector_erase(0x1000).unwrap();
+    f.set_protection(0..0);
+    f.program(0x10, &[0x34]).unwrap();
+    let mut b = [0u8; 1];
+    f.read(0x10, &mut b).unwrap();
+    assert_eq!(b[0], 0x34);
+}
+
+#[test]
+fn power_hook_passes_through() {
+    use flash_core::PowerState;
+    let mut f = protected_mock();
+    f.set_power(PowerState::DeepPowerDown).unwrap();
+    assert_eq!(f.inner().power(), PowerState::DeepPowerDown);
+    let mut b = [0u8; 1];
+    assert!(f.read(0x1000, &mut b).is_err());
+    f.set_power(PowerState::Active).unwrap();
+    f.read(0x1000, &mut b).unwrap();
+}
@@ -0,0 +1,86 @@
+use flash_core::Flash;
+use flash_mock::{MockFlash, OnViolation, Strict, Violation};
+
+fn strict(on_violation: OnViolation) -> MockFlash {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.set_strict(Strict { on_violation, unit: 16, max_programs: 2 }).unwrap();
+    m
+}
+
+fn violation(r: anyhow::Result<()>) -> Violation {
+    r.unwrap_err().downcast_ref::<Violation>().expect("Violation").clone()
+}
+
+#[test]
+fn zero_to_one_is_rejected() {
+    let mut m = strict(OnViolation::Error);
+    m.program(0x10, &[0x0F]).unwrap();
+    // clearing more bits is fine
+    m.program(0x10, &[0x03]).unwrap();
+    let v = violation(m.program(0x10, &[0x30]));
+    assert_eq!(v, Violation::ZeroToOne { addr: 0x10, old: 0x03, new: 0x30 });
+    assert_eq!(v.to_string(), "program needs 0->1 at 0x10: 0x03 -> 0x30");
+    let mut b = [0u8; 1];
+    m.read(0x10, &mut b).unwrap();
+    assert_eq!(b[0], 0x03);
+}
+
+#[test]
+fn program_across_a_page_is_rejected() {
+    let mut m = strict(OnViolation::Error);
+    m.program(0xF0, &[0; 16]).unwrap();
+    assert_eq!(violation(m.program(0x1F8, &[0; 16])), Violation::PageOverflow { addr: 0x1F8, len: 16 });
+}
+
+#[test]
+fn programs_per_unit_are_limited_until_erase() {
+    let mut m = strict(OnViolation::Error);
+    m.program(0x20, &[0]).unwrap();
+    m.program(0x21, &[0]).unwrap();
+    assert_eq!(violation(m.program(0x2F, &[0])), Violation::TooManyPrograms { addr: 0x20, count: 3 });
+    // the next unit has its own count, and erase starts a new cycle
+    m.program(0x30, &[0]).unwrap();
+    m.sector_erase(0).unwrap();
+    m.program(0x20, &[0; 16]).unwrap();
+    m.program(0x20, &[0; 16]).unwrap();
+}
+
+#[test]
+fn access_while_busy_is_rejected() {
+    let mut m = strict(OnViolation::Error);
+    m.set_erase_polls(5);
+    m.sector_erase_start(0x1000).unwrap();
+    assert_eq!(violation(m.read(0, &mut [0u8; 4])), Violation::Busy { op: "read", addr: 0 });
+    assert_eq!(violation(m.program(0, &[0])), Violation::Busy { op: "program", addr: 0 });
+    assert_eq!(violation(m.sector_erase(0)), Violation::Busy { op: "sector_erase", addr: 0 });
+    // suspended: the rest of the array is usable, the erasing sector is not
+    assert!(m.suspend().unwrap());
+    m.read(0, &mut [0u8; 4]).unwrap();
+    assert_eq!(violation(m.read(0xFFC, &mut [0u8; 8])), Violation::Busy { op: "read", addr: 0xFFC });
+}
+
+#[test]
+fn record_mode_keeps_going() {
+    let mut m = strict(OnViolation::Record);
+    m.program(0x10, &[0x0F]).unwrap();
+    m.program(0x10, &[0xF0]).unwrap();
+    m.program(0x1F8, &[0; 16]).unwrap();
+    assert_eq!(
+        m.violations(),
+        &[Violation::ZeroToOne { addr: 0x10, old: 0x0F, new: 0xF0 }, Violation::PageOverflow { addr: 0x1F8, len: 16 }]
+    );
+    // the programs still went through as plain ANDs
+    let mut b = [0xAAu8; 1];
+    m.read(0x10, &mut b).unwrap();
+    assert_eq!(b[0], 0x00);
+}
+
+#[test]
+fn unit_must_divide_the_sector() {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    for unit in [0, 24] {
+        let err = m.set_strict(Strict { on_violation: OnViolation::Error, unit, max_programs: 2 }).unwrap_err();
+        assert_eq!(err.to_string(), format!("strict unit {} must divide the 4096-byte sector", unit));
+    }
+    assert_eq!(m.strict(), None);
+}
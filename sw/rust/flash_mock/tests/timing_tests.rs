@@ -0,0 +1,47 @@
+use flash_core::Flash;
+use flash_mock::{MockFlash, Timing};
+
+const T: Timing = Timing { page_program: 700, sector_erase: 50_000, read_byte: 10 };
+
+fn timed() -> MockFlash {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.set_timing(T);
+    m
+}
+
+#[test]
+fn ops_are_charged_to_the_clock() {
+    let mut m = timed();
+    assert_eq!(m.timing(), Some(T));
+    m.program(0x80, &[0; 256]).unwrap(); // two pages
+    assert_eq!(m.now(), 2 * T.page_program);
+    m.read(0, &mut [0u8; 100]).unwrap();
+    assert_eq!(m.now(), 2 * T.page_program + 100 * T.read_byte);
+    m.sector_erase(0).unwrap();
+    assert_eq!(m.now(), 2 * T.page_program + 100 * T.read_byte + T.sector_erase);
+    assert_eq!(m.busy_time(), 2 * T.page_program + T.sector_erase);
+    assert_eq!(m.rdsr().unwrap(), 0);
+    assert_eq!(Timing::default().sector_erase, 45_000_000);
+}
+
+#[test]
+fn background_erase_reports_wip_until_its_time_is_up() {
+    let mut m = timed();
+    m.sector_erase_start(0x1000).unwrap();
+    assert_eq!(m.rdsr().unwrap() & 1, 1);
+    assert!(m.read(0, &mut [0u8; 1]).is_err());
+
+    // suspended, the erase makes no progress while the host programs elsewhere
+    assert!(m.suspend().unwrap());
+    m.program(0, &[0; 4]).unwrap();
+    m.advance(T.sector_erase);
+    assert!(m.is_suspended());
+    m.resume().unwrap();
+
+    // one status read ran before the suspend; two more finish the erase
+    m.advance(T.sector_erase - 3 * T.read_byte);
+    assert!(m.is_busy().unwrap());
+    assert!(!m.is_busy().unwrap());
+    assert_eq!(m.busy_time(), T.page_program + T.sector_erase);
+    m.read(0x1000, &mut [0u8; 4]).unwrap();
+}
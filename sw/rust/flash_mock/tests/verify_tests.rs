@@ -0,0 +1,195 @@
+use anyhow::Result;
+use flash_core::{Flash, FlashError, FlashGeometry, Verify};
+use flash_mock::{BitFlips, MockFlash};
+
+/// Silently drops the first `drop_programs` programs and leaves one byte
+/// of each of the first `stuck_erases` erases at 0x00 (foreground or background).
+struct Flaky {
+    inner: MockFlash,
+    drop_programs: u32,
+    stuck_erases: u32,
+    /// Background erase to leave a stuck byte in once it completes.
+    erasing: Option<u32>,
+}
+
+impl Flaky {
+    fn new(drop_programs: u32, stuck_erases: u32) -> Self {
+        let mut inner = MockFlash::new(8192, 256, 4096);
+        inner.set_erase_polls(3);
+        Flaky { inner, drop_programs, stuck_erases, erasing: None }
+    }
+
+    fn stick(&mut self, addr: u32) -> Result<()> {
+        if self.stuck_erases > 0 {
+            self.stuck_erases -= 1;
+            let base = addr - addr % 4096;
+            self.inner.program(base + 7, &[0x00])?;
+        }
+        Ok(())
+    }
+}
+
+impl Flash for Flaky {
+    fn geometry(&self) -> FlashGeometry { self.inner.geometry() }
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> { self.inner.read(addr, buf) }
+    fn program(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        if self.drop_programs > 0 {
+            self.drop_programs -= 1;
+            return Ok(());
+        }
+        self.inner.program(addr, data)
+    }
+    fn sector_erase(&mut self, addr: u32) -> Result<()> {
+        self.inner.sector_erase(addr)?;
+        self.stick(addr)
+    }
+    fn rdsr(&mut self) -> Result<u8> {
+        let st = self.inner.rdsr()?;
+        if st & 1 == 0 {
+            if let Some(addr) = self.erasing.take() { self.stick(addr)?; }
+        }
+        Ok(st)
+    }
+    fn sector_erase_start(&mut self, addr: u32) -> Result<()> {
+        self.inner.sector_erase_start(addr)?;
+        self.erasing = Some(addr);
+        Ok(())
+    }
+}
+
+fn verify_error(err: &anyhow::Error) -> &FlashError {
+    err.downcast_ref::<FlashError>().expect("FlashError")
+}
+
+#[test]
+fn dropped_program_is_retried() {
+    let mut f = Verify::new(Flaky::new(1, 0));
+    f.program(0x10, &[0x12, 0x34]).unwrap();
+    assert_eq!(f.retried(), 1);
+    let mut b = [0u8; 2];
+    f.read(0x10, &mut b).unwrap();
+    assert_eq!(b, [0x12, 0x34]);
+}
+
+#[test]
+fn program_expects_and_of_old_and_new() {
+    let mut f = Verify::new(MockFlash::new(8192, 256, 4096));
+    f.program(0x20, &[0xF0]).unwrap();
+    // 0x0F over 0xF0 reads back 0x00, which is what NOR gives: not a failure
+    f.program(0x20, &[0x0F]).unwrap();
+    assert_eq!(f.retried(), 0);
+}
+
+#[test]
+fn persistent_program_failure_is_a_verify_error() {
+    let mut f = Verify::new(Flaky::new(10, 0));
+    f.set_retries(3);
+    let err = f.program(0x100, &[0xAA]).unwrap_err();
+    assert_eq!(verify_error(&err), &FlashError::VerifyFailed { op: "program", addr: 0x100, expected: 0xAA, found: 0xFF });
+    assert_eq!(f.retried(), 3);
+    assert_eq!(f.inner().drop_programs, 6);
+    assert_eq!(err.to_string(), "program verify failed at 0x100: expected 0xaa, read 0xff");
+}
+
+#[test]
+fn read_flips_are_reread_not_retried() {
+    let mut f = Verify::new(BitFlips::new(MockFlash::new(8192, 256, 4096), 0));
+    f.set_rereads(1);
+    // While reading the old contents
+    f.inner_mut().flip_next(0x31, 5);
+    f.program(0x30, &[0x12, 0x34]).unwrap();
+    assert_eq!((f.inner().flipped(), f.retried()), (1, 0));
+
+    // Two reads of the old contents, then the read-back
+    for _ in 0..3 { f.inner_mut().flip_next(0x41, 5); }
+    f.program(0x41, &[0x00]).unwrap();
+    assert_eq!((f.inner().flipped(), f.retried()), (4, 0));
+
+    // Wrong on the re-read too: the program is retried
+    for _ in 0..4 { f.inner_mut().flip_next(0x51, 5); }
+    f.program(0x51, &[0x00]).unwrap();
+    assert_eq!((f.inner().flipped(), f.retried()), (8, 1));
+}
+
+#[test]
+fn erase_is_blank_checked_and_retried() {
+    let mut f = Verify::new(Flaky::new(0, 1));
+    f.program(0x1000, &[0u8; 16]).unwrap();
+    f.sector_erase(0x1000).unwrap();
+    assert_eq!(f.retried(), 1);
+
+    let mut f = Verify::new(Flaky::new(0, 5));
+    f.set_retries(0);
+    let err = f.sector_erase(0x1FFF).unwrap_err();
+    assert_eq!(verify_error(&err), &FlashError::VerifyFailed { op: "sector_erase", addr: 0x1007, expected: 0xFF, found: 0 });
+}
+
+#[test]
+fn background_erase_is_checked_on_completion() {
+    let mut f = Verify::new(Flaky::new(0, 1));
+    f.sector_erase_start(0).unwrap();
+    let mut polls = 0;
+    while f.is_busy().unwrap() {
+        polls += 1;
+    }
+    assert_eq!(f.retried(), 1);
+    assert!(polls > 3, "the retry restarts the erase: {}", polls);
+
+    let mut f = Verify::new(Flaky::new(0, 5));
+    f.sector_erase_start(0).unwrap();
+    let err = loop {
+        match f.is_busy() {
+            Ok(true) => {}
+            Ok(false) => panic!("stuck byte not reported"),
+            Err(e) => break e,
+        }
+    };
+    assert!(matches!(verify_error(&err), FlashError::VerifyFailed { op: "sector_erase", addr: 7, .. }));
+}
+
+#[test]
+fn background_erase_is_checked_by_rdsr_pollers() {
+    let mut f = Verify::new(Flaky::new(0, 1));
+    f.sector_erase_start(0).unwrap();
+    while f.rdsr().unwrap() & 1 != 0 {}
+    assert_eq!(f.retried(), 1);
+    let mut b = [0u8];
+    f.read(7, &mut b).unwrap();
+    assert_eq!(b, [0xFF]);
+}
+
+#[test]
+fn next_erase_waits_for_the_background_check() {
+    let mut f = Verify::new(Flaky::new(0, 1));
+    f.sector_erase_start(0).unwrap();
+    f.sector_erase_start(0x1000).unwrap();
+    assert_eq!(f.retried(), 1);
+    while f.is_busy().unwrap() {}
+
+    let mut f = Verify::new(Flaky::new(0, 1));
+    f.sector_erase_start(0).unwrap();
+    f.sector_erase(0x1000).unwrap();
+    assert_eq!(f.retried(), 1);
+    let mut b = [0u8];
+    f.read(7, &mut b).unwrap();
+    assert_eq!(b, [0xFF]);
+
+    let mut f = Verify::new(Flaky::new(0, 5));
+    f.set_retries(0);
+    f.sector_erase_start(0).unwrap();
+    let err = f.sector_erase_start(0x1000).unwrap_err();
+    assert!(matches!(verify_error(&err), FlashError::VerifyFailed { op: "sector_erase", addr: 7, .. }));
+}
+
+#[test]
+fn finished_background_erase_is_checked_before_read_or_program() {
+    for program in [false, true] {
+        let mut f = Verify::new(Flaky::new(0, 5));
+        f.set_retries(0);
+        f.sector_erase_start(0).unwrap();
+        while f.inner_mut().is_busy().unwrap() {} // behind Verify's back
+        let err = if program { f.program(0x1000, &[0]) } else { f.read(0x1000, &mut [0u8; 4]) }.unwrap_err();
+        assert!(matches!(verify_error(&err), FlashError::VerifyFailed { op: "sector_erase", addr: 7, .. }));
+        f.read(0x1000, &mut [0u8; 4]).unwrap();
+    }
+}
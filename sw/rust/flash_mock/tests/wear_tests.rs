@@ -0,0 +1,81 @@
+use flash_core::{Flash, FlashError};
+use flash_mock::{Endurance, MockFlash, Wear};
+
+fn flash_error(err: &anyhow::Error) -> &FlashError {
+    err.downcast_ref::<FlashError>().expect("FlashError")
+}
+
+#[test]
+fn erases_are_counted_per_sector() {
+    let mut m = MockFlash::new(16384, 256, 4096);
+    m.set_erase_polls(2);
+    m.sector_erase(0x1000).unwrap();
+    m.sector_erase(0x1FFF).unwrap();
+    m.sector_erase_start(0x3000).unwrap();
+    while m.is_busy().unwrap() {}
+    assert_eq!(m.erase_counts(), &[0, 2, 0, 1]);
+    assert_eq!(m.erase_count(0x1234), 2);
+    assert_eq!(m.endurance(), None);
+}
+
+#[test]
+fn worn_erase_fails_and_leaves_the_sector() {
+    let mut m = MockFlash::new(8192, 256, 4096);
+    m.set_endurance(Endurance { limit: 3, wear: Wear::EraseFails });
+    for _ in 0..3 {
+        m.sector_erase(0x1000).unwrap();
+    }
+    m.program(0x1010, &[0x12]).unwrap();
+    let err = m.sector_erase_start(0x1000).unwrap_err();
+    assert_eq!(flash_error(&err), &FlashError::EraseFailed { addr: 0x1000 });
+    assert_eq!(err.to_string(), "erase failed at 0x1000");
+    assert!(!m.is_busy().unwrap());
+    let mut b = [0u8; 1];
+    m.read(0x1010, &mut b).unwrap();
+    assert_eq!(b[0], 0x12);
+    // the other sector still has its cycles
+    m.sector_erase(0).unwrap();
+    assert_eq!(m.erase_counts(), &[1, 4]);
+}
+
+#[test]
+fn worn_erase_sticks_bits_at_zero() {
+    let worn = |seed| {
+        let mut m = MockFlash::new(8192, 256, 4096);
+        m.set_endurance(Endurance { limit: 2, wear: Wear::StuckBits { per_erase: 4, seed } });
+        for _ in 0..2 {
+            m.sector_erase(0).unwrap();
+        }
+        assert_eq!(m.stuck_bits(), 0);
+        m.sector_erase(0).unwrap();
+        m
+    };
+    let mut m = worn(9);
+    let stuck = m.stuck_bits();
+    assert!(stuck > 0 && stuck <= 4);
+    let mut img = vec![0u8; 4096];
+    m.read(0, &mut img).unwrap();
+    let zeros: u32 = img.iter().map(|b| b.count_zeros()).sum();
+    assert_eq!(zeros, stuck);
+
+    // stuck bits survive further erases and more pile up
+    m.sector_erase(0).unwrap();
+    assert!(m.stuck_bits() >= stuck);
+    let mut again = vec![0u8; 4096];
+    m.read(0, &mut again).unwrap();
+    assert!(img.iter().zip(&again).all(|(a, b)| b & !a == 0));
+
+    // reproducible per seed
+    let mut other = vec![0u8; 4096];
+    worn(9).read(0, &mut other).unwrap();
+    assert_eq!(img, other);
+}
+
+#[test]
+fn zero_sized_geometry_is_an_error() {
+    let err = MockFlash::try_new(8192, 256, 0).err().unwrap();
+    assert!(err.to_string().starts_with("invalid flash geometry"), "{}", err);
+    assert!(MockFlash::try_new(0, 256, 4096).is_err());
+    let geom = flash_core::FlashGeometry { mem_size: 16, page_size: 16, sector_size: 0 };
+    assert!(MockFlash::from_image(geom, vec![0xFF; 16]).is_err());
+}